@@ -999,7 +999,7 @@ export default function SettingsModal() {
                       if (id) {
                         setSelectedPwInput(id);
                         localStorage.setItem("pw_input_device", id);
-                        setDesktopDefaultDevice(id);
+                        setDesktopDefaultDevice(id, "input");
                       }
                     }}
                     style={selectStyle}
@@ -1077,7 +1077,7 @@ export default function SettingsModal() {
                       if (id) {
                         setSelectedPwOutput(id);
                         localStorage.setItem("pw_output_device", id);
-                        setDesktopDefaultDevice(id);
+                        setDesktopDefaultDevice(id, "output");
                       }
                     }}
                     style={selectStyle}
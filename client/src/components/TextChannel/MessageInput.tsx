@@ -504,9 +504,9 @@ export default function MessageInput(props: MessageInputProps) {
               const pastedText = e.clipboardData?.getData("text/plain") || "";
               e.preventDefault();
               tauriInvoke("read_clipboard_image")
-                .then((base64: string | null) => {
-                  if (base64) {
-                    const binary = atob(base64);
+                .then((image: { data: string; mime: string } | null) => {
+                  if (image) {
+                    const binary = atob(image.data);
                     const bytes = new Uint8Array(binary.length);
                     for (let i = 0; i < binary.length; i++) bytes[i] = binary.charCodeAt(i);
                     // Detect format from magic bytes
@@ -87,9 +87,12 @@ export async function enumerateDevices() {
   }
 }
 
-export async function setDesktopDefaultDevice(id: string): Promise<boolean> {
+export async function setDesktopDefaultDevice(
+  id: string,
+  direction?: "input" | "output"
+): Promise<boolean> {
   try {
-    return await tauriInvoke("set_default_audio_device", { id });
+    return await tauriInvoke("set_default_audio_device", { id, direction });
   } catch {
     return false;
   }
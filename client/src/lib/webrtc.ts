@@ -445,7 +445,7 @@ export function resetVoiceState() {
 export async function switchMicrophone(deviceId: string) {
   if (isDesktop) {
     // Desktop: tell Rust to switch input device
-    await tauriInvoke("voice_set_input_device", { deviceName: deviceId });
+    await tauriInvoke("voice_set_input_device", { deviceId });
     return;
   }
 
@@ -0,0 +1,304 @@
+//! CoreAudio stand-ins for the `wpctl` calls behind the device commands.
+//! Device ids are CoreAudio `AudioObjectID`s.
+
+use std::mem::size_of;
+use std::ptr::null;
+
+use core_foundation::base::TCFType;
+use core_foundation::string::{CFString, CFStringRef};
+use coreaudio_sys::{
+    kAudioDevicePropertyMute, kAudioDevicePropertyStreams, kAudioDevicePropertyVolumeScalar,
+    kAudioHardwarePropertyDefaultInputDevice, kAudioHardwarePropertyDefaultOutputDevice,
+    kAudioHardwarePropertyDevices, kAudioObjectPropertyName, kAudioObjectPropertyScopeGlobal,
+    kAudioObjectPropertyScopeInput, kAudioObjectPropertyScopeOutput, kAudioObjectSystemObject,
+    AudioObjectGetPropertyData, AudioObjectGetPropertyDataSize, AudioObjectHasProperty,
+    AudioObjectID, AudioObjectPropertyAddress, AudioObjectPropertyScope,
+    AudioObjectPropertySelector, AudioObjectSetPropertyData,
+};
+
+use crate::{AudioDevice, AudioDevices, DeviceVolume};
+
+/// kAudioObjectPropertyElementMain: the device as a whole rather than one channel.
+const MAIN_ELEMENT: u32 = 0;
+/// Channels tried when a device only has per-channel volume/mute.
+const CHANNEL_ELEMENTS: [u32; 2] = [1, 2];
+
+/// Which default `set_default_device` changes, as the UI asks for it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    Input,
+    Output,
+}
+
+fn address(
+    selector: AudioObjectPropertySelector,
+    scope: AudioObjectPropertyScope,
+    element: u32,
+) -> AudioObjectPropertyAddress {
+    AudioObjectPropertyAddress {
+        mSelector: selector,
+        mScope: scope,
+        mElement: element,
+    }
+}
+
+/// Read a fixed-size property, None if the object doesn't have it.
+fn get<T: Copy + Default>(id: AudioObjectID, addr: &AudioObjectPropertyAddress) -> Option<T> {
+    let mut value = T::default();
+    let mut size = size_of::<T>() as u32;
+    let status = unsafe {
+        AudioObjectGetPropertyData(
+            id,
+            addr,
+            0,
+            null(),
+            &mut size,
+            (&mut value as *mut T).cast(),
+        )
+    };
+    (status == 0).then_some(value)
+}
+
+fn set<T: Copy>(id: AudioObjectID, addr: &AudioObjectPropertyAddress, value: T) -> bool {
+    let status = unsafe {
+        AudioObjectSetPropertyData(
+            id,
+            addr,
+            0,
+            null(),
+            size_of::<T>() as u32,
+            (&value as *const T).cast(),
+        )
+    };
+    status == 0
+}
+
+fn has(id: AudioObjectID, addr: &AudioObjectPropertyAddress) -> bool {
+    unsafe { AudioObjectHasProperty(id, addr) != 0 }
+}
+
+/// Every audio device the system knows, inputs and outputs alike.
+fn device_ids() -> Vec<AudioObjectID> {
+    let addr = address(
+        kAudioHardwarePropertyDevices,
+        kAudioObjectPropertyScopeGlobal,
+        MAIN_ELEMENT,
+    );
+    let mut size = 0u32;
+    if unsafe {
+        AudioObjectGetPropertyDataSize(kAudioObjectSystemObject, &addr, 0, null(), &mut size)
+    } != 0
+    {
+        return Vec::new();
+    }
+    let mut ids = vec![0 as AudioObjectID; size as usize / size_of::<AudioObjectID>()];
+    let status = unsafe {
+        AudioObjectGetPropertyData(
+            kAudioObjectSystemObject,
+            &addr,
+            0,
+            null(),
+            &mut size,
+            ids.as_mut_ptr().cast(),
+        )
+    };
+    if status != 0 {
+        return Vec::new();
+    }
+    ids.truncate(size as usize / size_of::<AudioObjectID>());
+    ids
+}
+
+/// Whether the device has any streams in `scope` (input or output).
+fn has_streams(id: AudioObjectID, scope: AudioObjectPropertyScope) -> bool {
+    let addr = address(kAudioDevicePropertyStreams, scope, MAIN_ELEMENT);
+    let mut size = 0u32;
+    let status = unsafe { AudioObjectGetPropertyDataSize(id, &addr, 0, null(), &mut size) };
+    status == 0 && size > 0
+}
+
+fn device_name(id: AudioObjectID) -> Option<String> {
+    let addr = address(
+        kAudioObjectPropertyName,
+        kAudioObjectPropertyScopeGlobal,
+        MAIN_ELEMENT,
+    );
+    let mut name: CFStringRef = null();
+    let mut size = size_of::<CFStringRef>() as u32;
+    let status = unsafe {
+        AudioObjectGetPropertyData(
+            id,
+            &addr,
+            0,
+            null(),
+            &mut size,
+            (&mut name as *mut CFStringRef).cast(),
+        )
+    };
+    if status != 0 || name.is_null() {
+        return None;
+    }
+    // The caller owns the returned string and must release it
+    Some(unsafe { CFString::wrap_under_create_rule(name) }.to_string())
+}
+
+/// Elements that carry `selector` in `scope`: the main element if the device
+/// has one, otherwise its first channels.
+fn elements(
+    id: AudioObjectID,
+    selector: AudioObjectPropertySelector,
+    scope: AudioObjectPropertyScope,
+) -> Vec<u32> {
+    if has(id, &address(selector, scope, MAIN_ELEMENT)) {
+        return vec![MAIN_ELEMENT];
+    }
+    CHANNEL_ELEMENTS
+        .into_iter()
+        .filter(|&e| has(id, &address(selector, scope, e)))
+        .collect()
+}
+
+/// Scope a device's volume and mute are read and set in: output if it has
+/// any, otherwise input.
+fn volume_scope(id: AudioObjectID) -> AudioObjectPropertyScope {
+    if has_streams(id, kAudioObjectPropertyScopeOutput) {
+        kAudioObjectPropertyScopeOutput
+    } else {
+        kAudioObjectPropertyScopeInput
+    }
+}
+
+fn volume(id: AudioObjectID, scope: AudioObjectPropertyScope) -> Option<DeviceVolume> {
+    let volumes: Vec<f32> = elements(id, kAudioDevicePropertyVolumeScalar, scope)
+        .into_iter()
+        .filter_map(|e| get::<f32>(id, &address(kAudioDevicePropertyVolumeScalar, scope, e)))
+        .collect();
+    let muted = elements(id, kAudioDevicePropertyMute, scope)
+        .into_iter()
+        .filter_map(|e| get::<u32>(id, &address(kAudioDevicePropertyMute, scope, e)))
+        .any(|m| m != 0);
+    if volumes.is_empty() {
+        // No software volume control (many USB/HDMI outputs): always full
+        return Some(DeviceVolume { volume: 1.0, muted });
+    }
+    Some(DeviceVolume {
+        volume: volumes.iter().sum::<f32>() / volumes.len() as f32,
+        muted,
+    })
+}
+
+fn list(
+    scope: AudioObjectPropertyScope,
+    default_selector: AudioObjectPropertySelector,
+) -> Vec<AudioDevice> {
+    let default_id = get::<AudioObjectID>(
+        kAudioObjectSystemObject,
+        &address(
+            default_selector,
+            kAudioObjectPropertyScopeGlobal,
+            MAIN_ELEMENT,
+        ),
+    );
+    device_ids()
+        .into_iter()
+        .filter(|&id| has_streams(id, scope))
+        .filter_map(|id| {
+            let DeviceVolume { volume, muted } = volume(id, scope)?;
+            Some(AudioDevice {
+                id: id.to_string(),
+                name: device_name(id)?,
+                default: default_id == Some(id),
+                volume,
+                muted,
+            })
+        })
+        .collect()
+}
+
+pub fn get_audio_devices() -> AudioDevices {
+    AudioDevices {
+        inputs: list(
+            kAudioObjectPropertyScopeInput,
+            kAudioHardwarePropertyDefaultInputDevice,
+        ),
+        outputs: list(
+            kAudioObjectPropertyScopeOutput,
+            kAudioHardwarePropertyDefaultOutputDevice,
+        ),
+    }
+}
+
+pub fn get_device_volume(id: &str) -> Option<DeviceVolume> {
+    let id = id.parse().ok()?;
+    volume(id, volume_scope(id))
+}
+
+/// CoreAudio volume tops out at 1.0; there's no boost like PipeWire's.
+pub fn set_device_volume(id: &str, volume: f32) -> bool {
+    let Ok(id) = id.parse() else {
+        return false;
+    };
+    let scope = volume_scope(id);
+    let volume = volume.clamp(0.0, 1.0);
+    let elements = elements(id, kAudioDevicePropertyVolumeScalar, scope);
+    !elements.is_empty()
+        && elements.into_iter().all(|e| {
+            set(
+                id,
+                &address(kAudioDevicePropertyVolumeScalar, scope, e),
+                volume,
+            )
+        })
+}
+
+pub fn set_device_mute(id: &str, muted: bool) -> bool {
+    let Ok(id) = id.parse() else {
+        return false;
+    };
+    let scope = volume_scope(id);
+    let elements = elements(id, kAudioDevicePropertyMute, scope);
+    !elements.is_empty()
+        && elements.into_iter().all(|e| {
+            set(
+                id,
+                &address(kAudioDevicePropertyMute, scope, e),
+                muted as u32,
+            )
+        })
+}
+
+/// Make the device the system default for `direction`, or without one for
+/// each direction it has streams in. A device that does both (a headset, an
+/// aggregate) only replaces the default the UI picked it for; with no
+/// direction it becomes the default input and output together.
+pub fn set_default_device(id: &str, direction: Option<Direction>) -> bool {
+    let Ok(id) = id.parse::<AudioObjectID>() else {
+        return false;
+    };
+    let mut set_any = false;
+    for (dir, scope, selector) in [
+        (
+            Direction::Output,
+            kAudioObjectPropertyScopeOutput,
+            kAudioHardwarePropertyDefaultOutputDevice,
+        ),
+        (
+            Direction::Input,
+            kAudioObjectPropertyScopeInput,
+            kAudioHardwarePropertyDefaultInputDevice,
+        ),
+    ] {
+        if direction.is_some_and(|d| d != dir) {
+            continue;
+        }
+        if has_streams(id, scope) {
+            let addr = address(selector, kAudioObjectPropertyScopeGlobal, MAIN_ELEMENT);
+            if !set(kAudioObjectSystemObject, &addr, id) {
+                return false;
+            }
+            set_any = true;
+        }
+    }
+    set_any
+}
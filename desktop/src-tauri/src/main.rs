@@ -1,13 +1,15 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-#[cfg(target_os = "linux")]
+#[cfg(any(target_os = "linux", windows))]
 mod screen;
 mod voice;
+#[cfg(target_os = "macos")]
+mod macos_audio;
 
 use std::sync::Arc;
-#[cfg(target_os = "linux")]
 use tauri::Manager;
 use serde::Serialize;
+#[cfg(not(target_os = "macos"))]
 use std::process::Command;
 use tokio::sync::Mutex;
 #[cfg(target_os = "linux")]
@@ -17,21 +19,47 @@ use image::ImageEncoder;
 
 use voice::{
     VoiceEngine,
-    voice_start, voice_stop, voice_handle_offer, voice_handle_ice,
-    voice_set_mute, voice_set_deafen, voice_set_master_volume, voice_set_mic_gain,
-    voice_list_devices, voice_set_input_device, voice_set_output_device,
+    voice_start, voice_prewarm, voice_stop, voice_handle_offer, voice_handle_ice, voice_create_offer, voice_renegotiate, voice_handle_answer, voice_get_stats, voice_get_ice_gathering_state, voice_configure_ice,
+    voice_set_reconnect_policy,
+    voice_set_mute, voice_set_deafen, voice_set_master_volume, voice_set_latency_mode,
+    voice_start_recording, voice_stop_recording, voice_play_sound, voice_stop_sound, voice_set_mic_gain,
+    voice_play_test_tone,
+    voice_set_monitor, voice_set_opus_bitrate, voice_set_opus_complexity, voice_set_mono, voice_set_frame_ms,
+    voice_list_remote_tracks, voice_set_peer_pan,
+    voice_set_remote_speaking_params, voice_set_speaking_params,
+    voice_list_devices, voice_set_input_device, voice_set_output_device, voice_set_preferred_rate,
+    voice_list_hosts, voice_set_audio_host,
+    voice_set_buffer_ms,
 };
-#[cfg(target_os = "linux")]
+#[cfg(debug_assertions)]
+use voice::voice_start_loopback;
+#[cfg(any(target_os = "linux", windows))]
 use screen::{
     ScreenEngine,
-    screen_start, screen_stop, screen_handle_offer, screen_handle_ice,
+    screen_start, screen_stop, screen_handle_offer, screen_handle_ice, screen_get_stats, screen_get_ice_gathering_state, screen_get_encoder, screen_get_session_info,
+    screen_create_offer, screen_handle_answer, voice_add_screen_track,
+    screen_set_auto_crop, screen_set_region, screen_clear_region, screen_set_audio_muted, screen_set_audio_gain, screen_set_preview_fullres, screen_set_bitrate_limits,
+    screen_set_preferred_codec, screen_set_h264_profile, screen_pause, screen_resume, screen_clear_saved_source,
+    screen_set_cursor_mode, screen_set_audio_source,
 };
+#[cfg(windows)]
+use screen::screen_list_monitors;
 
 #[derive(Serialize, Clone)]
 struct AudioDevice {
     id: String,
     name: String,
     default: bool,
+    /// 1.0 = 100%. wpctl volumes can go above when boosted; CoreAudio's can't.
+    volume: f32,
+    muted: bool,
+}
+
+/// Current volume of one device, from `wpctl get-volume` (CoreAudio on macOS).
+#[derive(Serialize)]
+struct DeviceVolume {
+    volume: f32,
+    muted: bool,
 }
 
 #[derive(Serialize)]
@@ -40,20 +68,31 @@ struct AudioDevices {
     outputs: Vec<AudioDevice>,
 }
 
+/// Tree-drawing prefix of a `wpctl status` line. Newer WirePlumber uses box
+/// glyphs, some builds/terminals fall back to ASCII.
+#[cfg(not(target_os = "macos"))]
+fn strip_wpctl_tree(line: &str) -> &str {
+    line.trim_start_matches(|c: char| {
+        c.is_whitespace() || matches!(c, '\u{2502}' | '\u{251c}' | '\u{2514}' | '\u{2500}' | '|' | '`' | '-' | '+')
+    })
+}
+
+#[cfg(not(target_os = "macos"))]
 fn parse_wpctl_section(output: &str, section: &str) -> Vec<AudioDevice> {
     // No ^ anchor: wpctl lines have │ box-drawing chars that \s can't match
-    let re = regex::Regex::new(r"(\*)?\s*(\d+)\.\s+(.+?)\s+\[vol:").unwrap();
+    let re = regex::Regex::new(r"(\*)?\s*(\d+)\.\s+(.+?)\s+\[vol:\s*([\d.]+)(\s+MUTED)?").unwrap();
     let mut devices = Vec::new();
     let mut in_audio = false;
     let mut in_section = false;
 
     for line in output.lines() {
-        if line.starts_with("Audio") {
-            in_audio = true;
+        let label = strip_wpctl_tree(line).trim_end();
+        if label.is_empty() {
             continue;
         }
-        if in_audio && !line.starts_with(' ') && !line.is_empty() {
-            in_audio = false;
+        // Top-level blocks ("Audio", "Video", "Settings") start in column 0
+        if label.len() == line.trim_end().len() {
+            in_audio = label.eq_ignore_ascii_case("audio");
             in_section = false;
             continue;
         }
@@ -61,17 +100,9 @@ fn parse_wpctl_section(output: &str, section: &str) -> Vec<AudioDevice> {
             continue;
         }
 
-        let trimmed = line.trim();
-        let section_markers = [
-            format!("\u{251c}\u{2500} {}:", section),
-            format!("\u{2514}\u{2500} {}:", section),
-        ];
-        if section_markers.iter().any(|m| trimmed.starts_with(m.as_str())) {
-            in_section = true;
-            continue;
-        }
-        if in_section && (trimmed.starts_with("\u{251c}\u{2500} ") || trimmed.starts_with("\u{2514}\u{2500} ")) {
-            in_section = false;
+        // Subsection headers ("Sinks:", "Sources:", ...) switch sections
+        if let Some(name) = label.strip_suffix(':') {
+            in_section = name.trim().eq_ignore_ascii_case(section);
             continue;
         }
         if !in_section {
@@ -83,12 +114,71 @@ fn parse_wpctl_section(output: &str, section: &str) -> Vec<AudioDevice> {
                 id: caps[2].to_string(),
                 name: caps[3].trim().to_string(),
                 default: caps.get(1).map_or(false, |m| m.as_str() == "*"),
+                volume: caps[4].parse().unwrap_or(1.0),
+                muted: caps.get(5).is_some(),
             });
         }
     }
     devices
 }
 
+/// Fallback for when `wpctl status` can't be parsed: read sinks/sources from
+/// `pw-dump`'s JSON. `media_class` is "Audio/Sink" or "Audio/Source".
+#[cfg(not(target_os = "macos"))]
+fn parse_pw_dump(dump: &serde_json::Value, media_class: &str) -> Vec<AudioDevice> {
+    let Some(objects) = dump.as_array() else {
+        return Vec::new();
+    };
+
+    // Default devices are named in the "default" metadata object
+    let default_key = if media_class == "Audio/Sink" {
+        "default.audio.sink"
+    } else {
+        "default.audio.source"
+    };
+    let default_name = objects
+        .iter()
+        .filter(|o| o["type"] == "PipeWire:Interface:Metadata")
+        .filter_map(|o| o["metadata"].as_array())
+        .flatten()
+        .find(|entry| entry["key"] == default_key)
+        .and_then(|entry| entry["value"]["name"].as_str());
+
+    objects
+        .iter()
+        .filter(|o| o["type"] == "PipeWire:Interface:Node")
+        .filter(|o| o["info"]["props"]["media.class"] == media_class)
+        .filter_map(|o| {
+            let props = &o["info"]["props"];
+            let name = props["node.description"]
+                .as_str()
+                .or_else(|| props["node.name"].as_str())?;
+            // channelVolumes are linear; wpctl shows their cube root
+            let params = o["info"]["params"]["Props"].as_array().and_then(|p| p.first());
+            let volumes: Vec<f64> = params
+                .and_then(|p| p["channelVolumes"].as_array())
+                .map(|v| v.iter().filter_map(|v| v.as_f64()).collect())
+                .unwrap_or_default();
+            let volume = if volumes.is_empty() {
+                1.0
+            } else {
+                (volumes.iter().sum::<f64>() / volumes.len() as f64).cbrt() as f32
+            };
+            Some(AudioDevice {
+                id: o["id"].as_u64()?.to_string(),
+                name: name.to_string(),
+                default: default_name.is_some() && props["node.name"].as_str() == default_name,
+                volume,
+                muted: params.and_then(|p| p["mute"].as_bool()).unwrap_or(false),
+            })
+        })
+        .collect()
+}
+
+#[cfg(target_os = "macos")]
+use macos_audio::get_audio_devices;
+
+#[cfg(not(target_os = "macos"))]
 fn get_audio_devices() -> AudioDevices {
     let output = Command::new("wpctl")
         .arg("status")
@@ -97,9 +187,45 @@ fn get_audio_devices() -> AudioDevices {
         .and_then(|o| String::from_utf8(o.stdout).ok())
         .unwrap_or_default();
 
-    AudioDevices {
+    let devices = AudioDevices {
         inputs: parse_wpctl_section(&output, "Sources"),
         outputs: parse_wpctl_section(&output, "Sinks"),
+    };
+    if !devices.inputs.is_empty() || !devices.outputs.is_empty() {
+        return devices;
+    }
+
+    // Unrecognised wpctl output (other version/locale) — ask PipeWire directly
+    let dump = Command::new("pw-dump")
+        .output()
+        .ok()
+        .and_then(|o| serde_json::from_slice::<serde_json::Value>(&o.stdout).ok());
+    match dump {
+        Some(dump) => AudioDevices {
+            inputs: parse_pw_dump(&dump, "Audio/Source"),
+            outputs: parse_pw_dump(&dump, "Audio/Sink"),
+        },
+        None => devices,
+    }
+}
+
+/// Snapshot of the native engines so the UI can recover after a reload.
+#[derive(Serialize)]
+struct EngineState {
+    voice: voice::types::VoiceStatus,
+    #[cfg(any(target_os = "linux", windows))]
+    screen: screen::ScreenStatus,
+}
+
+#[tauri::command]
+async fn get_engine_state(app: tauri::AppHandle) -> EngineState {
+    let voice = app.state::<voice::VoiceState>().inner().lock().await.status();
+    #[cfg(any(target_os = "linux", windows))]
+    let screen = app.state::<screen::ScreenState>().inner().lock().await.status();
+    EngineState {
+        voice,
+        #[cfg(any(target_os = "linux", windows))]
+        screen,
     }
 }
 
@@ -108,6 +234,53 @@ fn list_audio_devices() -> AudioDevices {
     get_audio_devices()
 }
 
+/// Sinks whose monitors can be captured as screen-share audio.
+#[cfg(target_os = "linux")]
+#[tauri::command]
+fn list_screen_audio_sources() -> Vec<AudioDevice> {
+    get_audio_devices().outputs
+}
+
+/// Largest volume `set_device_volume` accepts (150%, like the GNOME slider's boost).
+#[cfg(not(target_os = "macos"))]
+const MAX_DEVICE_VOLUME: f32 = 1.5;
+
+#[cfg(not(target_os = "macos"))]
+#[tauri::command]
+fn get_device_volume(id: String) -> Option<DeviceVolume> {
+    let output = Command::new("wpctl").args(["get-volume", &id]).output().ok()?;
+    let text = String::from_utf8(output.stdout).ok()?;
+    // "Volume: 0.40" with " [MUTED]" appended when muted
+    let rest = text.trim().strip_prefix("Volume:")?;
+    let volume = rest.split_whitespace().next()?.parse().ok()?;
+    Some(DeviceVolume {
+        volume,
+        muted: rest.contains("[MUTED]"),
+    })
+}
+
+#[cfg(not(target_os = "macos"))]
+#[tauri::command]
+fn set_device_volume(id: String, volume: f32) -> bool {
+    let volume = volume.clamp(0.0, MAX_DEVICE_VOLUME);
+    Command::new("wpctl")
+        .args(["set-volume", &id, &format!("{:.2}", volume)])
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false)
+}
+
+#[cfg(not(target_os = "macos"))]
+#[tauri::command]
+fn set_device_mute(id: String, muted: bool) -> bool {
+    Command::new("wpctl")
+        .args(["set-mute", &id, if muted { "1" } else { "0" }])
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false)
+}
+
+#[cfg(not(target_os = "macos"))]
 #[tauri::command]
 fn set_default_audio_device(id: String) -> bool {
     Command::new("wpctl")
@@ -117,16 +290,116 @@ fn set_default_audio_device(id: String) -> bool {
         .unwrap_or(false)
 }
 
-#[cfg(target_os = "linux")]
+#[cfg(target_os = "macos")]
 #[tauri::command]
-fn read_clipboard_image() -> Option<String> {
-    let mut clipboard = match arboard::Clipboard::new() {
-        Ok(c) => c,
-        Err(e) => {
-            eprintln!("[clipboard] failed to open: {e}");
-            return None;
+fn get_device_volume(id: String) -> Option<DeviceVolume> {
+    macos_audio::get_device_volume(&id)
+}
+
+#[cfg(target_os = "macos")]
+#[tauri::command]
+fn set_device_volume(id: String, volume: f32) -> bool {
+    macos_audio::set_device_volume(&id, volume)
+}
+
+#[cfg(target_os = "macos")]
+#[tauri::command]
+fn set_device_mute(id: String, muted: bool) -> bool {
+    macos_audio::set_device_mute(&id, muted)
+}
+
+#[cfg(target_os = "macos")]
+#[tauri::command]
+fn set_default_audio_device(id: String, direction: Option<macos_audio::Direction>) -> bool {
+    macos_audio::set_default_device(&id, direction)
+}
+
+#[cfg(target_os = "linux")]
+#[derive(Serialize)]
+struct ClipboardImage {
+    /// Base64-encoded image bytes.
+    data: String,
+    mime: String,
+}
+
+/// Encode clipboard pixels as PNG (default), JPEG or WebP. WebP is always
+/// lossless, so `quality` only affects JPEG.
+/// Returns the encoded bytes and their MIME type.
+#[cfg(target_os = "linux")]
+fn encode_clipboard_image(
+    rgba: image::RgbaImage,
+    format: &str,
+    quality: u8,
+) -> Option<(Vec<u8>, &'static str)> {
+    let mut buf = Vec::new();
+    match format {
+        "jpeg" | "jpg" => {
+            // JPEG has no alpha channel — flatten to RGB first
+            let rgb = image::DynamicImage::ImageRgba8(rgba).to_rgb8();
+            let encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(&mut buf, quality);
+            encoder
+                .write_image(rgb.as_raw(), rgb.width(), rgb.height(), image::ExtendedColorType::Rgb8)
+                .ok()?;
+            Some((buf, "image/jpeg"))
         }
-    };
+        "webp" => {
+            // The image crate only ships a lossless WebP encoder, so quality is ignored
+            let encoder = image::codecs::webp::WebPEncoder::new_lossless(&mut buf);
+            encoder
+                .write_image(rgba.as_raw(), rgba.width(), rgba.height(), image::ExtendedColorType::Rgba8)
+                .ok()?;
+            Some((buf, "image/webp"))
+        }
+        _ => {
+            let encoder = image::codecs::png::PngEncoder::new(&mut buf);
+            encoder
+                .write_image(rgba.as_raw(), rgba.width(), rgba.height(), image::ExtendedColorType::Rgba8)
+                .ok()?;
+            Some((buf, "image/png"))
+        }
+    }
+}
+
+/// The app's clipboard connection. On X11 and Wayland the app serves what
+/// it copied for as long as this lives, so it's kept for the app's lifetime
+/// instead of being dropped right after a set.
+#[cfg(target_os = "linux")]
+type ClipboardState = std::sync::Mutex<Option<arboard::Clipboard>>;
+
+/// The clipboard in `slot`, opened on first use.
+#[cfg(target_os = "linux")]
+fn open_clipboard(slot: &mut Option<arboard::Clipboard>) -> Option<&mut arboard::Clipboard> {
+    if slot.is_none() {
+        match arboard::Clipboard::new() {
+            Ok(c) => *slot = Some(c),
+            Err(e) => {
+                eprintln!("[clipboard] failed to open: {e}");
+                return None;
+            }
+        }
+    }
+    slot.as_mut()
+}
+
+/// Read an image from the clipboard as base64. `format` is "png" (default),
+/// "jpeg" or "webp"; `quality` (1–100, default 85) applies to JPEG. WebP is
+/// encoded lossless, so asking for it with a quality is rejected rather
+/// than silently ignored.
+#[cfg(target_os = "linux")]
+#[tauri::command]
+fn read_clipboard_image(
+    clipboard: tauri::State<'_, ClipboardState>,
+    format: Option<String>,
+    quality: Option<u8>,
+) -> Option<ClipboardImage> {
+    let format = format.as_deref().unwrap_or("png").to_lowercase();
+    if format == "webp" && quality.is_some() {
+        eprintln!("[clipboard] WebP is encoded lossless; quality only applies to JPEG");
+        return None;
+    }
+
+    let mut clipboard = clipboard.lock().unwrap();
+    let clipboard = open_clipboard(&mut clipboard)?;
 
     // Try reading raw image data from clipboard (e.g. Print Screen, gnome-screenshot)
     match clipboard.get_image() {
@@ -137,33 +410,166 @@ fn read_clipboard_image() -> Option<String> {
                 img.bytes.into_owned(),
             )?;
 
-            let mut buf = Vec::new();
-            let encoder = image::codecs::png::PngEncoder::new(&mut buf);
-            encoder
-                .write_image(rgba.as_raw(), rgba.width(), rgba.height(), image::ExtendedColorType::Rgba8)
-                .ok()?;
+            let quality = quality.unwrap_or(85).clamp(1, 100);
+            let (buf, mime) = encode_clipboard_image(rgba, &format, quality)?;
 
-            return Some(base64::engine::general_purpose::STANDARD.encode(&buf));
+            return Some(ClipboardImage {
+                data: base64::engine::general_purpose::STANDARD.encode(&buf),
+                mime: mime.to_string(),
+            });
         }
         Err(_) => {}
     }
 
-    // Fallback: clipboard text might be a file path to an image
+    // Fallback: clipboard text might be file paths / file:// URIs (one per
+    // line when several files were copied) — use the first image
     // (WebKitGTK often pastes image file paths instead of image data)
     if let Ok(text) = clipboard.get_text() {
-        let path = text.trim();
-        let path = path.strip_prefix("file://").unwrap_or(path);
-        let p = std::path::Path::new(path);
-        if let Some(ext) = p.extension().and_then(|e| e.to_str()) {
-            if matches!(ext.to_lowercase().as_str(), "png" | "jpg" | "jpeg" | "gif" | "webp" | "bmp") {
-                if let Ok(data) = std::fs::read(p) {
-                    return Some(base64::engine::general_purpose::STANDARD.encode(&data));
-                }
+        return text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .find_map(|line| read_clipboard_file(&clipboard_path(line)));
+    }
+
+    None
+}
+
+/// Put a base64 PNG on the clipboard as image data. Some WebKitGTK/compositor
+/// combinations don't keep image data once set; callers can save the file and
+/// fall back to `write_clipboard_text` with its path.
+#[cfg(target_os = "linux")]
+#[tauri::command]
+fn write_clipboard_image(clipboard: tauri::State<'_, ClipboardState>, base64_png: String) -> bool {
+    let png = match base64::engine::general_purpose::STANDARD.decode(base64_png.trim()) {
+        Ok(png) => png,
+        Err(e) => {
+            eprintln!("[clipboard] invalid base64: {e}");
+            return false;
+        }
+    };
+    let rgba = match image::load_from_memory_with_format(&png, image::ImageFormat::Png) {
+        Ok(img) => img.to_rgba8(),
+        Err(e) => {
+            eprintln!("[clipboard] invalid PNG: {e}");
+            return false;
+        }
+    };
+
+    let mut clipboard = clipboard.lock().unwrap();
+    let Some(clipboard) = open_clipboard(&mut clipboard) else {
+        return false;
+    };
+    let image = arboard::ImageData {
+        width: rgba.width() as usize,
+        height: rgba.height() as usize,
+        bytes: rgba.into_raw().into(),
+    };
+    match clipboard.set_image(image) {
+        Ok(()) => true,
+        Err(e) => {
+            eprintln!("[clipboard] failed to set image: {e}");
+            false
+        }
+    }
+}
+
+/// Put text on the clipboard — e.g. the path of a saved screenshot when image
+/// data doesn't stick.
+#[cfg(target_os = "linux")]
+#[tauri::command]
+fn write_clipboard_text(clipboard: tauri::State<'_, ClipboardState>, text: String) -> bool {
+    let mut clipboard = clipboard.lock().unwrap();
+    let Some(clipboard) = open_clipboard(&mut clipboard) else {
+        return false;
+    };
+    match clipboard.set_text(text) {
+        Ok(()) => true,
+        Err(e) => {
+            eprintln!("[clipboard] failed to set text: {e}");
+            false
+        }
+    }
+}
+
+/// Turn a clipboard line into a path. file:// URIs are percent-decoded
+/// (file managers encode spaces as %20); bare paths are used as-is.
+#[cfg(target_os = "linux")]
+fn clipboard_path(line: &str) -> std::path::PathBuf {
+    use std::os::unix::ffi::OsStrExt;
+
+    let Some(uri_path) = line.strip_prefix("file://") else {
+        return std::path::PathBuf::from(line);
+    };
+    let uri_path = uri_path.strip_prefix("localhost").unwrap_or(uri_path);
+
+    let bytes = uri_path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3).and_then(|h| std::str::from_utf8(h).ok());
+        match (bytes[i], hex.and_then(|h| u8::from_str_radix(h, 16).ok())) {
+            (b'%', Some(b)) => {
+                decoded.push(b);
+                i += 3;
+            }
+            (c, _) => {
+                decoded.push(c);
+                i += 1;
             }
         }
     }
+    std::path::PathBuf::from(std::ffi::OsStr::from_bytes(&decoded))
+}
 
-    None
+/// Read an image file for the clipboard fallback. Raster files are passed
+/// through as-is; SVG is rasterized to PNG since the webview doesn't render
+/// pasted SVG file data consistently.
+#[cfg(target_os = "linux")]
+fn read_clipboard_file(path: &std::path::Path) -> Option<ClipboardImage> {
+    let ext = path.extension()?.to_str()?.to_lowercase();
+    let mime = match ext.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "bmp" => "image/bmp",
+        "svg" => "image/svg+xml",
+        _ => return None,
+    };
+    let data = std::fs::read(path).ok()?;
+
+    let (data, mime) = if mime == "image/svg+xml" {
+        (rasterize_svg(&data)?, "image/png")
+    } else {
+        (data, mime)
+    };
+
+    Some(ClipboardImage {
+        data: base64::engine::general_purpose::STANDARD.encode(&data),
+        mime: mime.to_string(),
+    })
+}
+
+/// Longest side an SVG is rasterized at, so a huge declared size can't
+/// allocate gigabytes of pixmap.
+#[cfg(target_os = "linux")]
+const MAX_SVG_SIDE: f32 = 4096.0;
+
+/// Render an SVG at its intrinsic size, scaled down to fit MAX_SVG_SIDE,
+/// and encode it as PNG.
+#[cfg(target_os = "linux")]
+fn rasterize_svg(data: &[u8]) -> Option<Vec<u8>> {
+    let tree = resvg::usvg::Tree::from_data(data, &resvg::usvg::Options::default())
+        .map_err(|e| eprintln!("[clipboard] invalid SVG: {e}"))
+        .ok()?;
+    let size = tree.size();
+    let scale = (MAX_SVG_SIDE / size.width().max(size.height())).min(1.0);
+    let width = (size.width() * scale).ceil().max(1.0) as u32;
+    let height = (size.height() * scale).ceil().max(1.0) as u32;
+    let mut pixmap = resvg::tiny_skia::Pixmap::new(width, height)?;
+    resvg::render(&tree, resvg::tiny_skia::Transform::from_scale(scale, scale), &mut pixmap.as_mut());
+    pixmap.encode_png().ok()
 }
 
 fn main() {
@@ -173,43 +579,129 @@ fn main() {
         .plugin(tauri_plugin_opener::init())
         .manage(Arc::new(Mutex::new(VoiceEngine::new())) as voice::VoiceState);
 
-    #[cfg(target_os = "linux")]
+    #[cfg(any(target_os = "linux", windows))]
     let builder = builder
         .manage(Arc::new(Mutex::new(ScreenEngine::new())) as screen::ScreenState);
 
+    #[cfg(target_os = "linux")]
+    let builder = builder.manage(ClipboardState::default());
+
     builder
         .invoke_handler(tauri::generate_handler![
+            get_engine_state,
             list_audio_devices,
             set_default_audio_device,
+            get_device_volume,
+            set_device_volume,
+            set_device_mute,
             // Voice commands
             voice_start,
+            voice_prewarm,
             voice_stop,
             voice_handle_offer,
             voice_handle_ice,
+            voice_create_offer,
+            voice_renegotiate,
+            voice_handle_answer,
+            voice_get_stats,
+            voice_get_ice_gathering_state,
+            voice_configure_ice,
+            voice_set_reconnect_policy,
             voice_set_mute,
             voice_set_deafen,
             voice_set_master_volume,
+            voice_set_latency_mode,
+            voice_start_recording,
+            voice_stop_recording,
+            voice_play_sound,
+            voice_stop_sound,
+            voice_play_test_tone,
             voice_set_mic_gain,
+            voice_set_monitor,
+            voice_set_opus_bitrate,
+            voice_set_opus_complexity,
+            voice_set_mono,
+            voice_set_frame_ms,
+            voice_list_remote_tracks,
+            voice_set_peer_pan,
+            voice_set_remote_speaking_params,
+            voice_set_speaking_params,
             voice_list_devices,
             voice_set_input_device,
             voice_set_output_device,
+            voice_set_preferred_rate,
+            voice_list_hosts,
+            voice_set_audio_host,
+            voice_set_buffer_ms,
             // Clipboard image read (Linux only — WebKitGTK doesn't expose image clipboard data)
             #[cfg(target_os = "linux")]
             read_clipboard_image,
-            // Screen share commands (Linux only — PipeWire capture)
             #[cfg(target_os = "linux")]
-            screen_start,
+            write_clipboard_image,
             #[cfg(target_os = "linux")]
+            write_clipboard_text,
+            // Screen share commands (Linux: portal + PipeWire, Windows: DXGI Desktop Duplication)
+            #[cfg(any(target_os = "linux", windows))]
+            screen_start,
+            #[cfg(any(target_os = "linux", windows))]
             screen_stop,
-            #[cfg(target_os = "linux")]
+            #[cfg(any(target_os = "linux", windows))]
             screen_handle_offer,
-            #[cfg(target_os = "linux")]
+            #[cfg(any(target_os = "linux", windows))]
             screen_handle_ice,
+            #[cfg(any(target_os = "linux", windows))]
+            screen_get_stats,
+            #[cfg(any(target_os = "linux", windows))]
+            screen_get_ice_gathering_state,
+            #[cfg(any(target_os = "linux", windows))]
+            screen_get_encoder,
+            #[cfg(any(target_os = "linux", windows))]
+            screen_get_session_info,
+            #[cfg(any(target_os = "linux", windows))]
+            screen_create_offer,
+            #[cfg(any(target_os = "linux", windows))]
+            screen_handle_answer,
+            #[cfg(any(target_os = "linux", windows))]
+            voice_add_screen_track,
+            #[cfg(any(target_os = "linux", windows))]
+            screen_set_auto_crop,
+            #[cfg(any(target_os = "linux", windows))]
+            screen_set_region,
+            #[cfg(any(target_os = "linux", windows))]
+            screen_clear_region,
+            #[cfg(any(target_os = "linux", windows))]
+            screen_set_audio_muted,
+            #[cfg(any(target_os = "linux", windows))]
+            screen_set_audio_gain,
+            #[cfg(any(target_os = "linux", windows))]
+            screen_set_preview_fullres,
+            #[cfg(any(target_os = "linux", windows))]
+            screen_set_bitrate_limits,
+            #[cfg(any(target_os = "linux", windows))]
+            screen_set_preferred_codec,
+            #[cfg(any(target_os = "linux", windows))]
+            screen_set_h264_profile,
+            #[cfg(any(target_os = "linux", windows))]
+            screen_pause,
+            #[cfg(any(target_os = "linux", windows))]
+            screen_resume,
+            #[cfg(any(target_os = "linux", windows))]
+            screen_clear_saved_source,
+            #[cfg(any(target_os = "linux", windows))]
+            screen_set_cursor_mode,
+            #[cfg(any(target_os = "linux", windows))]
+            screen_set_audio_source,
+            #[cfg(target_os = "linux")]
+            list_screen_audio_sources,
+            #[cfg(windows)]
+            screen_list_monitors,
+            #[cfg(debug_assertions)]
+            voice_start_loopback,
         ])
-        .setup(|_app| {
+        .setup(|#[cfg_attr(not(target_os = "linux"), allow(unused_variables))] app| {
             #[cfg(target_os = "linux")]
             {
-                let window = _app.get_webview_window("main").unwrap();
+                let window = app.get_webview_window("main").unwrap();
                 // Enumerate local audio devices and build injection script
                 let devices = get_audio_devices();
                 let devices_json = serde_json::to_string(&devices).unwrap_or_default();
@@ -1,30 +1,282 @@
 use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
+#[cfg(target_os = "linux")]
 use ringbuf::{HeapRb, traits::{Producer, Consumer, Observer, Split}};
-use tokio::sync::watch;
+#[cfg(target_os = "linux")]
+pub use ashpd::desktop::screencast::{CursorMode, SourceType};
+#[cfg(windows)]
+pub use super::dxgi::CursorMode;
+use tokio::sync::{mpsc, watch};
 use webrtc::media::Sample;
 use webrtc::track::track_local::track_local_static_rtp::TrackLocalStaticRTP;
-use webrtc::track::track_local::track_local_static_sample::TrackLocalStaticSample;
+#[cfg(target_os = "linux")]
 use webrtc::track::track_local::TrackLocalWriter;
 
-const FRAME_DURATION: Duration = Duration::from_millis(16); // ~60 FPS
+const DEFAULT_FPS: u32 = 60;
+const MAX_FPS: u32 = 120;
 const BITRATE_KBPS: u32 = 5000;
+const MIN_BITRATE_KBPS: u32 = 500;
+const MAX_BITRATE_KBPS: u32 = 50_000;
+/// Smallest `max_width` accepted; anything narrower is unreadable.
+const MIN_ENCODE_WIDTH: u32 = 320;
+const DEFAULT_QUEUE_DEPTH: usize = 4;
+const MAX_QUEUE_DEPTH: usize = 16;
+const BITRATE_UPDATE_INTERVAL: Duration = Duration::from_secs(1);
+/// While paused, the last frame is re-sent this often to keep the stream alive.
+const PAUSED_FRAME_INTERVAL: Duration = Duration::from_millis(500);
 const PREVIEW_INTERVAL: Duration = Duration::from_millis(16); // ~60 FPS preview
 const PREVIEW_MAX_WIDTH: u32 = 960;
-
-use super::encoder::FrameData;
+const PREVIEW_QUALITY: u8 = 55;
+/// Full-resolution JPEGs are costly to encode, so they're sent less often.
+const FULLRES_PREVIEW_INTERVAL: Duration = Duration::from_millis(100);
+const FULLRES_PREVIEW_QUALITY: u8 = 90;
+/// Window over which capture-side frame drops are counted.
+const OVERLOAD_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+/// Consecutive windows with over 10% of frames dropped before the frame rate is lowered.
+const OVERLOAD_WINDOWS: u32 = 3;
+/// Drop-free windows before the frame rate steps back up.
+const RECOVER_WINDOWS: u32 = 10;
+/// Overload never paces below this.
+const MIN_PACED_FPS: u32 = 10;
+/// A new frame size must hold this long before the encoder is rebuilt for it,
+/// so a drag-resize doesn't rebuild it on every frame.
+const RESIZE_DEBOUNCE: Duration = Duration::from_millis(300);
+/// How often the encode loop reports its frame rate and output bitrate.
+const FRAME_STATS_INTERVAL: Duration = Duration::from_secs(1);
+/// How often the PipeWire loops check the stop flag when no buffers arrive.
+#[cfg(target_os = "linux")]
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// `stop` waits this long for the PipeWire threads before detaching them.
+const THREAD_JOIN_TIMEOUT: Duration = Duration::from_secs(1);
+/// RTP clock rate of every video codec we send.
+const VIDEO_CLOCK_RATE: u64 = 90_000;
+
+// A/V sync: `run_capture` takes one `Instant` when the session starts, and
+// both tracks stamp media against it, so their RTP clocks advance with the
+// same wall clock. webrtc's sender reports map each RTP clock to NTP time
+// when sent, which is what receivers line audio and video up by — the RTP
+// bases themselves are random per sender and don't need to match.
+//
+// Video: the sample track adds each sample's duration to its timestamp
+// after sending it, so the duration we pass decides where the *next* frame
+// lands. `VideoClock` aims it at (now + frame interval) on the shared clock,
+// which absorbs pacing jitter, dropped frames and stalls within one frame.
+//
+// Audio: `AudioClock` counts 48kHz samples since the shared start. Normally
+// it advances by exactly the samples encoded, so packets stay contiguous;
+// it jumps to the wall-clock position when audio first arrives and after a
+// stall, so missing time shows up as a gap instead of audio running late.
+// Smaller drift either way (the sound card's clock against ours) is slewed
+// back until the RTP clock is within one frame (20ms) of the shared clock.
+
+use super::encoder::{FrameData, VideoCodec};
+use super::frame_queue::{self, FrameSender};
+use super::peer::{VideoFeedback, VideoOutput};
+use super::preview::PreviewClients;
+#[cfg(target_os = "linux")]
+use crate::voice::gain::GainRamp;
+
+/// One stream the portal returned (a monitor or window the user selected).
+/// On Windows every monitor is a stream and `node_id` is its index.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct PortalStream {
+    pub node_id: u32,
+    pub width: u32,
+    pub height: u32,
+}
 
 pub struct PortalResult {
     pub node_id: u32,
     pub width: u32,
     pub height: u32,
+    #[cfg(target_os = "linux")]
     pub fd: std::os::fd::OwnedFd,
+    /// Everything the portal returned; `node_id` is `streams[stream_index]`.
+    pub streams: Vec<PortalStream>,
+    pub stream_index: usize,
+    /// Monitor or window, for the captured stream. None if the portal didn't say.
+    #[cfg(target_os = "linux")]
+    pub source_type: Option<SourceType>,
+    /// Token to reopen the same monitor/window next time without the picker.
+    pub restore_token: Option<String>,
+    /// Frames come from the `synthetic-capture` test pattern, not PipeWire.
+    pub synthetic: bool,
+    /// Desktop Duplication leaves the pointer out; draw it into frames.
+    #[cfg(windows)]
+    pub draw_cursor: bool,
+    /// GDI device name of the captured monitor. Duplication is reopened on
+    /// this monitor, not whatever now sits at `node_id`.
+    #[cfg(windows)]
+    pub device_name: String,
+}
+
+/// Messages from capture to the engine.
+pub enum CaptureEvent {
+    /// Region of the PipeWire frame that is actually being encoded (x, y, w, h).
+    /// All zeros while the captured window is not visible.
+    ContentRect(u32, u32, u32, u32),
+    /// The encoder couldn't keep up, so the frame rate was lowered to `fps`.
+    /// `dropped` frames were lost to the full frame queue in the last second.
+    EncoderOverloaded { fps: u32, dropped: u32 },
+    /// The first encoded frame was handed to the video track.
+    StreamReady,
+    /// Frames sent per second and encoded bitrate over the last interval,
+    /// with frames the reader delivered and dropped (queue full) meanwhile
+    /// and the rate the encoder is currently paced to.
+    FrameStats { fps: f32, bitrate_kbps: u32, delivered: u32, dropped: u32, target_fps: u32 },
+    /// Something failed that leaves the share black, frozen or silent.
+    Error { kind: ScreenErrorKind, message: String },
+    /// The captured window became hidden (minimized, other workspace) or
+    /// visible again. No frames are sent while it's hidden.
+    SourceVisible(bool),
+}
+
+/// Where a `CaptureEvent::Error` came from, so the UI can word it.
+#[derive(Debug, Clone, Copy, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScreenErrorKind {
+    /// The screencast session was ended outside the app.
+    Portal,
+    /// The PipeWire video or audio stream failed (DXGI duplication on Windows).
+    Pipewire,
+    /// The video encoder couldn't be created or stopped producing frames.
+    Encoder,
+    /// Encoded frames couldn't be sent to the peer connection.
+    Network,
+}
+
+/// Log a capture failure and pass it on to the engine.
+pub(super) fn report_error(events: &mpsc::UnboundedSender<CaptureEvent>, kind: ScreenErrorKind, message: String) {
+    eprintln!("[screen] {}", message);
+    let _ = events.send(CaptureEvent::Error { kind, message });
+}
+
+/// What the encode loop is currently producing.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct EncoderStatus {
+    pub backend: &'static str,
+    pub codec: VideoCodec,
+    pub profile: Option<&'static str>,
+    pub width: u32,
+    pub height: u32,
+    pub bitrate_kbps: u32,
+    /// Frames sent per second over the last stats interval (0 until the first).
+    pub fps: f32,
+}
+
+/// Per-session settings passed to `screen_start`. Omitted fields use the defaults.
+#[derive(Debug, Clone, Copy, serde::Deserialize)]
+#[serde(default)]
+pub struct ScreenStartOptions {
+    pub fps: u32,
+    /// Starting bitrate and ceiling for the adaptive bitrate, in kbps.
+    pub bitrate_kbps: u32,
+    /// Frames wider than this are downscaled before encode.
+    pub max_width: Option<u32>,
+    /// Captured frames that can wait for the encoder; the oldest is dropped
+    /// for a new one while it's full. 1 keeps latency lowest, more rides out
+    /// slow encodes.
+    pub queue_depth: usize,
+    /// Let the picker select several monitors/windows. Only one is captured;
+    /// the rest are listed in the result for a later `stream_index`.
+    /// Windows always lists every monitor.
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    pub multiple: bool,
+    /// Which of the portal's streams to capture. Falls back to the first
+    /// when out of range.
+    pub stream_index: usize,
+    /// Port to serve the preview on; a random one if unset or taken.
+    pub preview_port: Option<u16>,
+    /// Send system audio alongside the video. When off the share is
+    /// video-only: no audio track is negotiated and no sink is captured.
+    pub share_audio: bool,
+}
+
+impl Default for ScreenStartOptions {
+    fn default() -> Self {
+        Self {
+            fps: DEFAULT_FPS,
+            bitrate_kbps: BITRATE_KBPS,
+            max_width: None,
+            queue_depth: DEFAULT_QUEUE_DEPTH,
+            multiple: false,
+            stream_index: 0,
+            preview_port: None,
+            share_audio: true,
+        }
+    }
+}
+
+impl ScreenStartOptions {
+    /// Replace out-of-range values with the defaults.
+    pub fn validated(self) -> Self {
+        let defaults = Self::default();
+        let fps = if (1..=MAX_FPS).contains(&self.fps) {
+            self.fps
+        } else {
+            eprintln!("[screen] Invalid fps {}, using {}", self.fps, defaults.fps);
+            defaults.fps
+        };
+        let bitrate_kbps = if (MIN_BITRATE_KBPS..=MAX_BITRATE_KBPS).contains(&self.bitrate_kbps) {
+            self.bitrate_kbps
+        } else {
+            eprintln!("[screen] Invalid bitrate {}kbps, using {}kbps", self.bitrate_kbps, defaults.bitrate_kbps);
+            defaults.bitrate_kbps
+        };
+        let max_width = match self.max_width {
+            Some(w) if w < MIN_ENCODE_WIDTH => {
+                eprintln!("[screen] max_width {} below {}, not downscaling", w, MIN_ENCODE_WIDTH);
+                None
+            }
+            w => w,
+        };
+        let queue_depth = if (1..=MAX_QUEUE_DEPTH).contains(&self.queue_depth) {
+            self.queue_depth
+        } else {
+            eprintln!("[screen] Invalid queue depth {}, using {}", self.queue_depth, defaults.queue_depth);
+            defaults.queue_depth
+        };
+        Self { fps, bitrate_kbps, max_width, queue_depth, ..self }
+    }
+
+    fn frame_interval(&self) -> Duration {
+        Duration::from_micros(1_000_000 / self.fps as u64)
+    }
+}
+
+/// Floor/ceiling for the adaptive video bitrate, in kbps.
+pub struct BitrateLimits {
+    pub min_kbps: AtomicU32,
+    pub max_kbps: AtomicU32,
 }
 
 pub struct ScreenCapture {
     task_handle: Option<tokio::task::JoinHandle<()>>,
     stop_flag: Arc<AtomicBool>,
+    /// PipeWire video/audio threads of the running session, joined on stop.
+    threads: Arc<std::sync::Mutex<Vec<std::thread::JoinHandle<()>>>>,
+    /// Alpha-based window crop detection. When off, the full frame is always sent.
+    auto_crop: Arc<AtomicBool>,
+    /// Share only this (x, y, w, h) part of the captured frame.
+    region: Arc<std::sync::Mutex<Option<(u32, u32, u32, u32)>>>,
+    /// Stop sending shared-audio RTP while video keeps flowing.
+    audio_muted: Arc<AtomicBool>,
+    /// Linear gain applied to shared audio before Opus encode.
+    audio_gain: Arc<std::sync::Mutex<f32>>,
+    /// Freeze the shared image without tearing down the session.
+    paused: Arc<AtomicBool>,
+    /// Also feed full-resolution frames to the preview server's `/preview/full`.
+    fullres_preview: Arc<AtomicBool>,
+    bitrate_limits: Arc<BitrateLimits>,
+    /// Sink to monitor for shared audio (wpctl id or node name); None = default sink.
+    audio_source: Option<String>,
+    /// Set by the encode loop once an encoder is running.
+    encoder_status: Arc<std::sync::Mutex<Option<EncoderStatus>>>,
+    /// Size of the captured stream, set by the capture thread once PipeWire
+    /// has negotiated the format (the DXGI output size on Windows).
+    content_size: Arc<std::sync::Mutex<Option<(u32, u32)>>>,
+    pub event_rx: Option<mpsc::UnboundedReceiver<CaptureEvent>>,
 }
 
 impl ScreenCapture {
@@ -32,55 +284,235 @@ impl ScreenCapture {
         Self {
             task_handle: None,
             stop_flag: Arc::new(AtomicBool::new(false)),
+            threads: Arc::new(std::sync::Mutex::new(Vec::new())),
+            auto_crop: Arc::new(AtomicBool::new(true)),
+            region: Arc::new(std::sync::Mutex::new(None)),
+            audio_muted: Arc::new(AtomicBool::new(false)),
+            audio_gain: Arc::new(std::sync::Mutex::new(1.0)),
+            paused: Arc::new(AtomicBool::new(false)),
+            fullres_preview: Arc::new(AtomicBool::new(false)),
+            bitrate_limits: Arc::new(BitrateLimits {
+                min_kbps: AtomicU32::new(MIN_BITRATE_KBPS),
+                max_kbps: AtomicU32::new(BITRATE_KBPS),
+            }),
+            audio_source: None,
+            encoder_status: Arc::new(std::sync::Mutex::new(None)),
+            content_size: Arc::new(std::sync::Mutex::new(None)),
+            event_rx: None,
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn start(
         &mut self,
-        video_track: Arc<TrackLocalStaticSample>,
-        audio_track: Arc<TrackLocalStaticRTP>,
+        video_rx: watch::Receiver<VideoOutput>,
+        audio_track: Option<Arc<TrackLocalStaticRTP>>,
+        feedback: Arc<VideoFeedback>,
         preview_tx: watch::Sender<Option<Vec<u8>>>,
+        fullres_tx: watch::Sender<Option<Vec<u8>>>,
+        preview_clients: Arc<PreviewClients>,
         portal: PortalResult,
+        options: ScreenStartOptions,
     ) {
         // Create a fresh stop flag for this session — old threads keep their own flag (true)
         let stop = Arc::new(AtomicBool::new(false));
         self.stop_flag = stop.clone();
-        let handle = tokio::spawn(async move {
-            if let Err(e) = run_capture(video_track, audio_track, preview_tx, stop, portal).await {
-                eprintln!("[screen] Capture error: {}", e);
-            }
-        });
+
+        // Event channel for crop/content-rect changes
+        let (event_tx, event_rx) = mpsc::unbounded_channel();
+        self.event_rx = Some(event_rx);
+
+        let auto_crop = Arc::clone(&self.auto_crop);
+        let region = Arc::clone(&self.region);
+        let audio_muted = Arc::clone(&self.audio_muted);
+        let audio_gain = Arc::clone(&self.audio_gain);
+        // The session's bitrate is the new ceiling; screen_set_bitrate_limits
+        // can still move it while sharing.
+        let min_kbps = self.bitrate_limits.min_kbps.load(Ordering::Relaxed);
+        self.set_bitrate_limits(min_kbps, options.bitrate_kbps);
+        let bitrate_limits = Arc::clone(&self.bitrate_limits);
+        // A new session always starts live
+        self.paused.store(false, Ordering::Relaxed);
+        let paused = Arc::clone(&self.paused);
+        let fullres_preview = Arc::clone(&self.fullres_preview);
+        let encoder_status = Arc::clone(&self.encoder_status);
+        *self.content_size.lock().unwrap() = None;
+        let content_size = Arc::clone(&self.content_size);
+        let audio_source = self.audio_source.clone();
+        let threads = Arc::clone(&self.threads);
+        let handle = tokio::spawn(run_capture(video_rx, audio_track, audio_source, feedback, bitrate_limits, encoder_status, content_size, preview_tx, fullres_tx, preview_clients, fullres_preview, event_tx, auto_crop, region, audio_muted, audio_gain, paused, stop, threads, portal, options));
         self.task_handle = Some(handle);
     }
 
-    pub fn stop(&mut self) {
+    pub async fn stop(&mut self) {
         // Signal this session's loops to stop
         self.stop_flag.store(true, Ordering::Release);
         if let Some(handle) = self.task_handle.take() {
             handle.abort();
         }
+        // Wait for the PipeWire loops to quit so the next session doesn't
+        // start alongside a lingering stream. Polled on the blocking pool so
+        // the wait doesn't stall a runtime worker.
+        let threads: Vec<_> = self.threads.lock().unwrap().drain(..).collect();
+        let _ = tokio::task::spawn_blocking(move || {
+            let deadline = Instant::now() + THREAD_JOIN_TIMEOUT;
+            for handle in threads {
+                while !handle.is_finished() && Instant::now() < deadline {
+                    std::thread::sleep(Duration::from_millis(10));
+                }
+                if handle.is_finished() {
+                    let _ = handle.join();
+                } else {
+                    eprintln!("[screen] Capture thread still running after {:?}, detaching", THREAD_JOIN_TIMEOUT);
+                }
+            }
+        })
+        .await;
+        self.event_rx = None;
+        *self.encoder_status.lock().unwrap() = None;
+        *self.content_size.lock().unwrap() = None;
         eprintln!("[screen] Capture stop signaled");
     }
+
+    pub fn set_auto_crop(&self, enabled: bool) {
+        self.auto_crop.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Crop frames to (x, y, w, h) in captured-frame pixels, or back to the
+    /// whole frame with None. Applies to the next frame; the encoder follows
+    /// the new size after the resize debounce.
+    pub fn set_region(&self, region: Option<(u32, u32, u32, u32)>) {
+        *self.region.lock().unwrap() = region;
+    }
+
+    pub fn set_audio_muted(&self, muted: bool) {
+        self.audio_muted.store(muted, Ordering::Relaxed);
+    }
+
+    pub fn set_audio_gain(&self, gain: f32) {
+        *self.audio_gain.lock().unwrap() = gain;
+    }
+
+    pub fn set_fullres_preview(&self, enabled: bool) {
+        self.fullres_preview.store(enabled, Ordering::Relaxed);
+    }
+
+    /// While paused, PipeWire buffers are dropped and viewers (and the preview)
+    /// keep seeing the last frame. Resuming forces a keyframe.
+    pub fn set_paused(&self, paused: bool) {
+        self.paused.store(paused, Ordering::Relaxed);
+    }
+
+    /// Takes effect on the next session.
+    pub fn set_audio_source(&mut self, source: Option<String>) {
+        self.audio_source = source;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    pub fn encoder_status(&self) -> Option<EncoderStatus> {
+        self.encoder_status.lock().unwrap().clone()
+    }
+
+    /// Negotiated size of the captured stream, before any crop or downscale.
+    /// None until the capture thread knows it.
+    pub fn content_size(&self) -> Option<(u32, u32)> {
+        *self.content_size.lock().unwrap()
+    }
+
+    /// Applies from the next bitrate update (~1s) of the running session.
+    /// Both limits are clamped to `MAX_BITRATE_KBPS`, as for `ScreenStartOptions`.
+    pub fn set_bitrate_limits(&self, min_kbps: u32, max_kbps: u32) {
+        let min_kbps = min_kbps.clamp(100, MAX_BITRATE_KBPS);
+        self.bitrate_limits.min_kbps.store(min_kbps, Ordering::Relaxed);
+        self.bitrate_limits.max_kbps.store(max_kbps.clamp(min_kbps, MAX_BITRATE_KBPS), Ordering::Relaxed);
+    }
 }
 
+/// Next encoder bitrate from RTCP feedback: back off under loss (>10%), probe
+/// upward when the link is clean (<2%), never above the REMB estimate. Each
+/// step moves at most 25% so quality ramps rather than jumps.
+fn next_bitrate(current: u32, feedback: &VideoFeedback, limits: &BitrateLimits) -> u32 {
+    let loss = feedback.fraction_lost.load(Ordering::Relaxed) as f32 / 256.0;
+    let cur = current as f32;
+    let mut target = if loss > 0.10 {
+        cur * (1.0 - 0.5 * loss)
+    } else if loss < 0.02 {
+        cur * 1.08
+    } else {
+        cur
+    };
+    let remb = feedback.remb_kbps.load(Ordering::Relaxed);
+    if remb > 0 {
+        target = target.min(remb as f32);
+    }
+    let target = target.clamp(cur * 0.75, cur * 1.25) as u32;
+
+    let min = limits.min_kbps.load(Ordering::Relaxed);
+    let max = limits.max_kbps.load(Ordering::Relaxed).max(min);
+    target.clamp(min, max)
+}
+
+#[cfg(target_os = "linux")]
 /// Use xdg-desktop-portal to show a screen/window picker and start a PipeWire screencast.
 /// Runs synchronously from the caller's perspective (awaitable) so errors propagate immediately.
+/// With a `restore_token` from a previous session the portal reopens that source
+/// without the picker; if the token is no longer valid, the picker is shown instead.
+/// `options.multiple` allows selecting several sources; `options.stream_index`
+/// picks which one is captured.
 pub async fn portal_start_screencast(
+    restore_token: Option<&str>,
+    cursor_mode: CursorMode,
+    options: &ScreenStartOptions,
 ) -> Result<PortalResult, Box<dyn std::error::Error + Send + Sync>> {
-    use ashpd::desktop::screencast::{CursorMode, Screencast, SourceType};
+    if let Some(token) = restore_token {
+        match portal_screencast(Some(token), cursor_mode, options).await {
+            Ok(result) => return Ok(result),
+            Err(e) => {
+                // The user dismissed the dialog — don't show it a second time
+                if let Some(ashpd::Error::Response(ashpd::desktop::ResponseError::Cancelled)) =
+                    e.downcast_ref::<ashpd::Error>()
+                {
+                    return Err(e);
+                }
+                eprintln!("[screen] Saved source could not be restored ({}), showing picker", e);
+            }
+        }
+    }
+    portal_screencast(None, cursor_mode, options).await
+}
+
+#[cfg(target_os = "linux")]
+async fn portal_screencast(
+    restore_token: Option<&str>,
+    cursor_mode: CursorMode,
+    options: &ScreenStartOptions,
+) -> Result<PortalResult, Box<dyn std::error::Error + Send + Sync>> {
+    use ashpd::desktop::screencast::Screencast;
     use ashpd::desktop::PersistMode;
 
     let proxy = Screencast::new().await?;
     let session = proxy.create_session().await?;
 
+    // Not every compositor supports every mode (GNOME < 3.38 lacks hidden/metadata)
+    let available = proxy.available_cursor_modes().await.unwrap_or_default();
+    let cursor_mode = if available.contains(cursor_mode) {
+        cursor_mode
+    } else {
+        eprintln!("[screen] Cursor mode {:?} not supported by portal, using Embedded", cursor_mode);
+        CursorMode::Embedded
+    };
+
     proxy
         .select_sources(
             &session,
-            CursorMode::Embedded,
+            cursor_mode,
             SourceType::Monitor | SourceType::Window,
-            false,
-            None,
-            PersistMode::DoNot,
+            options.multiple,
+            restore_token,
+            PersistMode::Persistent,
         )
         .await?
         .response()?;
@@ -89,103 +521,282 @@ pub async fn portal_start_screencast(
         .start(&session, &ashpd::WindowIdentifier::default())
         .await?
         .response()?;
-    let stream = response.streams().first().ok_or("no streams returned")?;
-    let node_id = stream.pipe_wire_node_id();
-    let (w, h) = stream.size().unwrap_or((1920, 1080));
+    let streams: Vec<PortalStream> = response
+        .streams()
+        .iter()
+        .map(|stream| {
+            let (w, h) = stream.size().unwrap_or((1920, 1080));
+            PortalStream {
+                node_id: stream.pipe_wire_node_id(),
+                width: w as u32,
+                height: h as u32,
+            }
+        })
+        .collect();
+    if streams.is_empty() {
+        return Err("no streams returned".into());
+    }
+    let stream_index = if options.stream_index < streams.len() {
+        options.stream_index
+    } else {
+        eprintln!(
+            "[screen] Stream index {} out of range ({} streams), using 0",
+            options.stream_index,
+            streams.len()
+        );
+        0
+    };
+    let PortalStream { node_id, width, height } = streams[stream_index].clone();
+    let source_type = response.streams()[stream_index].source_type();
+    let restore_token = response.restore_token().map(str::to_string);
 
     let fd = proxy.open_pipe_wire_remote(&session).await?;
 
     eprintln!(
-        "[screen] Portal screencast: node={}, {}x{}",
+        "[screen] Portal screencast: node={}, {}x{}, {:?}, stream {}/{}, restorable={}",
         node_id,
-        w,
-        h
+        width,
+        height,
+        source_type,
+        stream_index + 1,
+        streams.len(),
+        restore_token.is_some()
     );
 
     Ok(PortalResult {
         node_id,
-        width: w as u32,
-        height: h as u32,
+        width,
+        height,
         fd,
+        streams,
+        stream_index,
+        source_type,
+        restore_token,
+        synthetic: false,
     })
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn run_capture(
-    track: Arc<TrackLocalStaticSample>,
-    audio_track: Arc<TrackLocalStaticRTP>,
+    mut video_rx: watch::Receiver<VideoOutput>,
+    audio_track: Option<Arc<TrackLocalStaticRTP>>,
+    audio_source: Option<String>,
+    feedback: Arc<VideoFeedback>,
+    bitrate_limits: Arc<BitrateLimits>,
+    encoder_status: Arc<std::sync::Mutex<Option<EncoderStatus>>>,
+    content_size: Arc<std::sync::Mutex<Option<(u32, u32)>>>,
     preview_tx: watch::Sender<Option<Vec<u8>>>,
+    fullres_tx: watch::Sender<Option<Vec<u8>>>,
+    preview_clients: Arc<PreviewClients>,
+    fullres_preview: Arc<AtomicBool>,
+    event_tx: mpsc::UnboundedSender<CaptureEvent>,
+    auto_crop: Arc<AtomicBool>,
+    region: Arc<std::sync::Mutex<Option<(u32, u32, u32, u32)>>>,
+    audio_muted: Arc<AtomicBool>,
+    audio_gain: Arc<std::sync::Mutex<f32>>,
+    paused: Arc<AtomicBool>,
     stop: Arc<AtomicBool>,
+    threads: Arc<std::sync::Mutex<Vec<std::thread::JoinHandle<()>>>>,
     portal: PortalResult,
-) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    // Spawn PipeWire audio capture (sink monitor) — failure is non-fatal
-    let audio_stop = stop.clone();
-    std::thread::spawn(move || {
-        if let Err(e) = pipewire_audio_capture_loop(audio_track, audio_stop) {
-            log::warn!("[screen] Audio capture error (non-fatal): {:?}", e);
-        }
-        eprintln!("[screen] Audio capture thread exited");
-    });
+    options: ScreenStartOptions,
+) {
+    // Shared capture-start clock for audio and video timestamps
+    let clock_start = Instant::now();
+
+    // Spawn PipeWire audio capture (sink monitor) — failure is non-fatal.
+    // Windows shares are video-only.
+    #[cfg(windows)]
+    let _ = (audio_track, audio_source, audio_muted, audio_gain);
+    #[cfg(target_os = "linux")]
+    if let Some(audio_track) = audio_track {
+        let audio_stop = stop.clone();
+        let audio_events = event_tx.clone();
+        let audio_thread = std::thread::spawn(move || {
+            if let Err(e) = pipewire_audio_capture_loop(audio_track, audio_source, audio_muted, audio_gain, clock_start, audio_stop) {
+                log::warn!("[screen] Audio capture error (non-fatal): {:?}", e);
+                let _ = audio_events.send(CaptureEvent::Error {
+                    kind: ScreenErrorKind::Pipewire,
+                    message: format!("Shared audio capture failed: {}", e),
+                });
+            }
+            eprintln!("[screen] Audio capture thread exited");
+        });
+        threads.lock().unwrap().push(audio_thread);
+    } else {
+        eprintln!("[screen] Video-only share, not capturing audio");
+    }
 
     // Spawn PipeWire frame reader on a dedicated thread
-    let (frame_tx, mut frame_rx) = tokio::sync::mpsc::channel::<FrameData>(4);
+    let (frame_tx, mut frame_rx) = frame_queue::channel(options.queue_depth);
+    // Queued frames the reader dropped for newer ones (encoder behind)
+    let dropped = Arc::new(AtomicU32::new(0));
+    let enc_dropped = Arc::clone(&dropped);
+    let enc_events = event_tx.clone();
+    let join_events = event_tx.clone();
 
     let pw_stop = stop.clone();
-    std::thread::spawn(move || {
-        if let Err(e) = pipewire_capture_loop(portal.fd, portal.node_id, portal.width, portal.height, frame_tx, pw_stop) {
-            eprintln!("[screen] PipeWire capture error: {:?}", e);
+    let pw_paused = paused.clone();
+    if portal.synthetic {
+        #[cfg(feature = "synthetic-capture")]
+        {
+            *content_size.lock().unwrap() = Some((portal.width, portal.height));
+            let fps = options.fps;
+            let synthetic_thread = std::thread::spawn(move || {
+                super::synthetic::frame_loop(portal.width, portal.height, fps, frame_tx, dropped, pw_paused, pw_stop);
+            });
+            threads.lock().unwrap().push(synthetic_thread);
         }
-        eprintln!("[screen] PipeWire thread exited");
-    });
+    } else {
+        #[cfg(target_os = "linux")]
+        {
+            let pw_events = event_tx.clone();
+            let video_thread = std::thread::spawn(move || {
+                if let Err(e) = pipewire_capture_loop(portal.fd, portal.node_id, portal.width, portal.height, portal.source_type, frame_tx, dropped, event_tx, auto_crop, region, content_size, pw_paused, pw_stop) {
+                    report_error(&pw_events, ScreenErrorKind::Pipewire, format!("PipeWire capture error: {}", e));
+                }
+                eprintln!("[screen] PipeWire thread exited");
+            });
+            threads.lock().unwrap().push(video_thread);
+        }
+        // Monitors have no transparent area, so auto-crop doesn't apply
+        #[cfg(windows)]
+        {
+            let _ = auto_crop;
+            let fps = options.fps;
+            eprintln!(
+                "[screen] Capturing monitor {} {} ({}x{})",
+                portal.node_id + 1,
+                portal.device_name,
+                portal.width,
+                portal.height
+            );
+            let video_thread = std::thread::spawn(move || {
+                let dxgi_events = event_tx.clone();
+                if let Err(e) = super::dxgi::capture_loop(&portal.device_name, fps, portal.draw_cursor, frame_tx, dropped, event_tx, region, content_size, pw_paused, pw_stop) {
+                    report_error(&dxgi_events, ScreenErrorKind::Pipewire, format!("DXGI capture error: {}", e));
+                }
+                eprintln!("[screen] DXGI thread exited");
+            });
+            threads.lock().unwrap().push(video_thread);
+        }
+    }
 
-    // Step 3: H.264 encode loop on spawn_blocking (Encoder is not Send)
-    let track_clone = Arc::clone(&track);
+    // Step 3: video encode loop on spawn_blocking (Encoder is not Send).
+    // Each stage reports its own failures with their kind as they happen.
     let enc_stop = stop.clone();
-    tokio::task::spawn_blocking(move || {
+    let encode_loop = tokio::task::spawn_blocking(move || {
         use super::encoder::create_encoder;
 
         let rt = tokio::runtime::Handle::current();
 
         // Wait for first frame to get actual dimensions
         let first_frame = match rt.block_on(frame_rx.recv()) {
-            Some(f) => f,
+            Some(f) => downscale_frame(f, options.max_width),
             None => return,
         };
 
-        let w = (first_frame.width as usize) & !1;
-        let h = (first_frame.height as usize) & !1;
+        let mut w = (first_frame.width as usize) & !1;
+        let mut h = (first_frame.height as usize) & !1;
 
         eprintln!("[screen] First frame: {}x{}, bgra={}, data_len={}",
             first_frame.width, first_frame.height, first_frame.is_bgra, first_frame.data.len());
 
-        let mut encoder = match create_encoder(w as u32, h as u32, BITRATE_KBPS) {
+        let fps = options.fps;
+        let mut frame_interval = options.frame_interval();
+
+        let mut bitrate_kbps = bitrate_limits.max_kbps.load(Ordering::Relaxed);
+        let mut output = video_rx.borrow_and_update().clone();
+        // Cleared if a GPU encoder fails its first frame; later rebuilds stay on software
+        let mut allow_hardware = true;
+        // Whether the current encoder has produced a frame yet
+        let mut encoder_proven = false;
+        let mut encoder = match create_encoder(output.codec, w as u32, h as u32, bitrate_kbps, fps, allow_hardware, output.h264) {
             Ok(e) => e,
             Err(e) => {
-                eprintln!("[screen] Encoder init failed: {:?}", e);
+                report_error(&enc_events, ScreenErrorKind::Encoder, format!("Encoder init failed: {}", e));
                 return;
             }
         };
 
-        eprintln!("[screen] Encode loop started ({}x{})", w, h);
+        eprintln!("[screen] Encode loop started ({}x{} @ {}fps)", w, h, fps);
 
         let mut last_preview = Instant::now() - PREVIEW_INTERVAL;
+        let mut last_fullres = Instant::now() - FULLRES_PREVIEW_INTERVAL;
+        // Set while a preview JPEG is being made; a tick that finds it set is
+        // skipped rather than piling up threads behind a slow encode
+        let preview_busy = Arc::new(AtomicBool::new(false));
+        let fullres_busy = Arc::new(AtomicBool::new(false));
         let mut frame_count: u32 = 0;
-        const IDR_INTERVAL: u32 = 120; // Force IDR keyframe every ~2s at 60fps
+        let idr_interval = fps * 2; // Force IDR keyframe every ~2s
+        let mut last_encode: Option<Instant> = None;
+        let mut paced_count: u32 = 0;
         let mut fps_timer = Instant::now();
         let mut fps_count: u32 = 0;
+        let mut bitrate_timer = Instant::now();
+        // Overload detection: lower the paced rate while the reader keeps
+        // dropping frames, raise it again once it stops
+        let mut paced_fps = fps;
+        let mut overload_timer = Instant::now();
+        let mut received: u32 = 0;
+        let mut overloaded_windows: u32 = 0;
+        let mut clear_windows: u32 = 0;
+        let mut was_paused = false;
+        // Frame size that differs from the encoder's, and when it first appeared
+        let mut resize_pending: Option<((usize, usize), Instant)> = None;
+        // Last encoded frame, re-sent while paused
+        let mut last_frame: Option<FrameData> = None;
+        // Sent frames/bytes since the last FrameStats report
+        let mut stream_ready = false;
+        let mut stats_timer = Instant::now();
+        let mut stats_frames: u32 = 0;
+        let mut stats_bytes: usize = 0;
+        let mut stats_delivered: u32 = 0;
+        let mut stats_dropped: u32 = 0;
+        // Sent fps from the last FrameStats report, for the status snapshot
+        let mut sent_fps: f32 = 0.0;
+        let mut video_clock = VideoClock::new(clock_start);
+        // Per-frame failures are reported once per run of failures
+        let mut encode_failing = false;
+        let mut send_failing = false;
 
         // Process the first frame
         let mut pending = Some(first_frame);
 
         loop {
+            let mut frame_duration = frame_interval;
             let frame = if let Some(f) = pending.take() {
                 f
             } else {
-                match rt.block_on(frame_rx.recv()) {
-                    Some(f) => f,
-                    None => break, // channel closed
+                match rt.block_on(tokio::time::timeout(PAUSED_FRAME_INTERVAL, frame_rx.recv())) {
+                    Ok(Some(f)) => downscale_frame(f, options.max_width),
+                    Ok(None) => break, // channel closed
+                    Err(_) => {
+                        // No new frames — while paused, repeat the frozen frame
+                        // so the connection doesn't look dead to viewers
+                        match last_frame.take() {
+                            Some(f) if paused.load(Ordering::Relaxed) => {
+                                frame_duration = PAUSED_FRAME_INTERVAL;
+                                f
+                            }
+                            f => {
+                                last_frame = f;
+                                if enc_stop.load(Ordering::Relaxed) {
+                                    break;
+                                }
+                                continue;
+                            }
+                        }
+                    }
                 }
             };
 
+            // Snap viewers back cleanly after a pause
+            let is_paused = paused.load(Ordering::Relaxed);
+            if was_paused && !is_paused {
+                encoder.force_keyframe();
+            }
+            was_paused = is_paused;
+
             if enc_stop.load(Ordering::Relaxed) {
                 eprintln!("[screen] Encode loop: stop flag set, exiting");
                 break;
@@ -194,82 +805,323 @@ async fn run_capture(
             let fw = (frame.width as usize) & !1;
             let fh = (frame.height as usize) & !1;
 
-            // Skip frames whose dimensions don't match the encoder (crop changed,
-            // window resized, etc.) — avoids feeding wrong-sized data to encoder.
+            // The size changed (window resized, crop moved). Before anything is
+            // encoded, follow it at once; mid-stream, once it has held for
+            // RESIZE_DEBOUNCE. The new encoder starts on a keyframe carrying the
+            // new size in-band (SPS / VP8-9 frame header), so nothing needs
+            // renegotiating with the SFU.
+            if fw != w || fh != h {
+                let settled = frame_count == 0
+                    || match resize_pending {
+                        Some((size, since)) if size == (fw, fh) => since.elapsed() >= RESIZE_DEBOUNCE,
+                        _ => {
+                            resize_pending = Some(((fw, fh), Instant::now()));
+                            false
+                        }
+                    };
+                if settled {
+                    resize_pending = None;
+                    match create_encoder(output.codec, fw as u32, fh as u32, bitrate_kbps, fps, allow_hardware, output.h264) {
+                        Ok(e) => {
+                            eprintln!("[screen] Frame size changed {}x{} -> {}x{}, encoder re-initialized", w, h, fw, fh);
+                            encoder = e;
+                            encoder_proven = false;
+                            encoder.force_keyframe();
+                            w = fw;
+                            h = fh;
+                        }
+                        Err(e) => {
+                            report_error(&enc_events, ScreenErrorKind::Encoder, format!("Encoder re-init failed: {}", e));
+                            break;
+                        }
+                    }
+                }
+            } else {
+                resize_pending = None;
+            }
+
+            // Until then, skip frames whose dimensions don't match the encoder
+            // rather than feed it wrong-sized data.
             if fw != w || fh != h {
                 continue;
             }
 
             // Send JPEG preview thumbnail periodically — off-thread to avoid
-            // blocking the encode loop
-            if last_preview.elapsed() >= PREVIEW_INTERVAL {
+            // blocking the encode loop. Skipped while no client is streaming it.
+            if preview_clients.preview.load(Ordering::Relaxed) > 0
+                && last_preview.elapsed() >= PREVIEW_INTERVAL
+                && !preview_busy.swap(true, Ordering::AcqRel)
+            {
                 last_preview = Instant::now();
                 let preview_data = frame.data.clone();
                 let is_bgra = frame.is_bgra;
                 let ptx = preview_tx.clone();
+                let busy = Arc::clone(&preview_busy);
                 std::thread::spawn(move || {
-                    if let Some(jpeg_bytes) = make_preview_jpeg(&preview_data, fw, fh, is_bgra) {
+                    if let Some(jpeg_bytes) = make_preview_jpeg(&preview_data, fw, fh, is_bgra, PREVIEW_MAX_WIDTH, PREVIEW_QUALITY) {
                         ptx.send_replace(Some(jpeg_bytes));
                     }
+                    busy.store(false, Ordering::Release);
                 });
             }
+            // Full-size frames exactly as encoded, for checking fine detail
+            if fullres_preview.load(Ordering::Relaxed)
+                && preview_clients.fullres.load(Ordering::Relaxed) > 0
+                && last_fullres.elapsed() >= FULLRES_PREVIEW_INTERVAL
+                && !fullres_busy.swap(true, Ordering::AcqRel)
+            {
+                last_fullres = Instant::now();
+                let preview_data = frame.data.clone();
+                let is_bgra = frame.is_bgra;
+                let ftx = fullres_tx.clone();
+                let busy = Arc::clone(&fullres_busy);
+                std::thread::spawn(move || {
+                    if let Some(jpeg_bytes) = make_preview_jpeg(&preview_data, fw, fh, is_bgra, u32::MAX, FULLRES_PREVIEW_QUALITY) {
+                        ftx.send_replace(Some(jpeg_bytes));
+                    }
+                    busy.store(false, Ordering::Release);
+                });
+            }
+
+            received += 1;
+            stats_delivered += 1;
+            if overload_timer.elapsed() >= OVERLOAD_CHECK_INTERVAL {
+                overload_timer = Instant::now();
+                let lost = enc_dropped.swap(0, Ordering::Relaxed);
+                stats_dropped += lost;
+                if lost * 10 > received + lost {
+                    overloaded_windows += 1;
+                    clear_windows = 0;
+                } else {
+                    overloaded_windows = 0;
+                    clear_windows = if lost == 0 { clear_windows + 1 } else { 0 };
+                }
+                received = 0;
+
+                let next_fps = if overloaded_windows >= OVERLOAD_WINDOWS && paced_fps > MIN_PACED_FPS {
+                    overloaded_windows = 0;
+                    (paced_fps * 3 / 4).max(MIN_PACED_FPS)
+                } else if clear_windows >= RECOVER_WINDOWS && paced_fps < fps {
+                    clear_windows = 0;
+                    (paced_fps * 5 / 4).min(fps)
+                } else {
+                    paced_fps
+                };
+                if next_fps != paced_fps {
+                    eprintln!("[screen] Encoder {} — pacing {} -> {} fps",
+                        if next_fps < paced_fps { "overloaded" } else { "recovered" }, paced_fps, next_fps);
+                    if next_fps < paced_fps {
+                        let _ = enc_events.send(CaptureEvent::EncoderOverloaded { fps: next_fps, dropped: lost });
+                    }
+                    paced_fps = next_fps;
+                    frame_interval = Duration::from_micros(1_000_000 / paced_fps as u64);
+                }
+            }
+
+            // Frame pacing: PipeWire delivers at the compositor's rate, so
+            // frames arriving sooner than 1/fps after the last encode are
+            // dropped here (the preview above still sees them). 10% slack
+            // keeps jitter from halving the rate.
+            if let Some(t) = last_encode {
+                if frame_duration == frame_interval && t.elapsed() < frame_interval.mul_f32(0.9) {
+                    paced_count += 1;
+                    continue;
+                }
+            }
+
+            // The SFU's offer picked a different codec — switch encoder and track
+            if video_rx.has_changed().unwrap_or(false) {
+                output = video_rx.borrow_and_update().clone();
+                match create_encoder(output.codec, w as u32, h as u32, bitrate_kbps, fps, allow_hardware, output.h264) {
+                    Ok(e) => {
+                        encoder = e;
+                        encoder_proven = false;
+                    }
+                    Err(e) => {
+                        report_error(&enc_events, ScreenErrorKind::Encoder, format!("{:?} encoder init failed: {}", output.codec, e));
+                        break;
+                    }
+                }
+            }
 
-            // Force periodic IDR keyframes so late-joining viewers can decode
+            // Force periodic IDR keyframes so late-joining viewers can decode,
+            // and answer PLI/FIR from the SFU right away
             frame_count += 1;
-            if frame_count % IDR_INTERVAL == 0 {
+            if feedback.keyframe_request.swap(false, Ordering::AcqRel) || frame_count % idr_interval == 0 {
                 encoder.force_keyframe();
             }
 
+            // Follow the SFU's bandwidth feedback. Encoders that rebuild their
+            // session to change rate (NVENC, libvpx) ignore small changes so
+            // they don't churn out keyframes.
+            if bitrate_timer.elapsed() >= BITRATE_UPDATE_INTERVAL {
+                bitrate_timer = Instant::now();
+                let next = next_bitrate(bitrate_kbps, &feedback, &bitrate_limits);
+                let min_step = if encoder.live_bitrate() { 1 } else { (bitrate_kbps / 20).max(1) };
+                if next.abs_diff(bitrate_kbps) >= min_step {
+                    eprintln!("[screen] Bitrate {} -> {} kbps", bitrate_kbps, next);
+                    encoder.set_bitrate(next);
+                    bitrate_kbps = next;
+                }
+            }
+
+            // Status snapshot for get_engine_state
+            if let Ok(mut status) = encoder_status.lock() {
+                *status = Some(EncoderStatus {
+                    backend: encoder.backend(),
+                    codec: output.codec,
+                    profile: encoder.codec_info().profile,
+                    width: w as u32,
+                    height: h as u32,
+                    bitrate_kbps,
+                    fps: sent_fps,
+                });
+            }
+
             // FPS counter
             fps_count += 1;
             if fps_timer.elapsed() >= Duration::from_secs(5) {
                 let elapsed = fps_timer.elapsed().as_secs_f64();
-                eprintln!("[screen] Encode FPS: {:.1}", fps_count as f64 / elapsed);
+                eprintln!(
+                    "[screen] Encode FPS: {:.1} (target {}, {} frames paced out)",
+                    fps_count as f64 / elapsed,
+                    paced_fps,
+                    paced_count
+                );
                 fps_count = 0;
+                paced_count = 0;
                 fps_timer = Instant::now();
             }
 
-            match encoder.encode(&FrameData {
-                data: frame.data,
-                width: frame.width,
-                height: frame.height,
-                is_bgra: frame.is_bgra,
-            }) {
+            last_encode = Some(Instant::now());
+            let mut result = encoder.encode(&frame);
+            // A GPU encoder that opened but can't encode a single frame won't
+            // start working later — switch to software for the rest of the session
+            if let Err(e) = &result {
+                if !encoder_proven && encoder.is_hardware() {
+                    eprintln!("[screen] {} failed on its first frame ({:?}), falling back to software", encoder.backend(), e);
+                    allow_hardware = false;
+                    match create_encoder(output.codec, w as u32, h as u32, bitrate_kbps, fps, allow_hardware, output.h264) {
+                        Ok(e) => encoder = e,
+                        Err(e) => {
+                            report_error(&enc_events, ScreenErrorKind::Encoder, format!("Software encoder init failed: {}", e));
+                            break;
+                        }
+                    }
+                    result = encoder.encode(&frame);
+                }
+            }
+            encoder_proven |= result.is_ok();
+            last_frame = Some(frame);
+            match result {
                 Ok(data) => {
+                    encode_failing = false;
                     if !data.is_empty() {
                         let sample = Sample {
                             data: bytes::Bytes::from(data),
-                            duration: FRAME_DURATION,
+                            duration: video_clock.sample_duration(frame_duration),
                             ..Default::default()
                         };
-                        let track = Arc::clone(&track_clone);
-                        rt.block_on(async {
-                            if let Err(e) = track.write_sample(&sample).await {
-                                log::warn!("[screen] write_sample: {}", e);
+                        let len = sample.data.len();
+                        let track = Arc::clone(&output.track);
+                        let sent = rt.block_on(track.write_sample(&sample));
+                        match &sent {
+                            Err(e) if !send_failing => {
+                                send_failing = true;
+                                report_error(&enc_events, ScreenErrorKind::Network, format!("write_sample: {}", e));
                             }
-                        });
+                            Err(e) => log::warn!("[screen] write_sample: {}", e),
+                            Ok(()) => send_failing = false,
+                        }
+                        if sent.is_ok() {
+                            if !stream_ready {
+                                stream_ready = true;
+                                let _ = enc_events.send(CaptureEvent::StreamReady);
+                            }
+                            stats_frames += 1;
+                            stats_bytes += len;
+                        }
                     }
                 }
+                Err(e) if !encode_failing => {
+                    encode_failing = true;
+                    report_error(&enc_events, ScreenErrorKind::Encoder, format!("Encode error: {}", e));
+                }
                 Err(e) => {
                     eprintln!("[screen] Encode error: {:?}", e);
                 }
             }
+
+            if stats_timer.elapsed() >= FRAME_STATS_INTERVAL {
+                let elapsed = stats_timer.elapsed().as_secs_f64();
+                sent_fps = (stats_frames as f64 / elapsed) as f32;
+                let _ = enc_events.send(CaptureEvent::FrameStats {
+                    fps: sent_fps,
+                    bitrate_kbps: (stats_bytes as f64 * 8.0 / 1000.0 / elapsed) as u32,
+                    delivered: stats_delivered,
+                    dropped: stats_dropped,
+                    target_fps: paced_fps,
+                });
+                stats_timer = Instant::now();
+                stats_frames = 0;
+                stats_bytes = 0;
+                stats_delivered = 0;
+                stats_dropped = 0;
+            }
         }
 
         eprintln!("[screen] Encode loop exited");
-    })
-    .await?;
+    });
 
-    Ok(())
+    if let Err(e) = encode_loop.await {
+        report_error(&join_events, ScreenErrorKind::Encoder, format!("Encode loop panicked: {}", e));
+    }
+}
+
+/// Quit `mainloop` once `stop` is set, even if the stream has gone quiet
+/// (paused, hidden window, silent sink) and no callback would notice. Keep the
+/// returned timer alive for as long as the loop runs.
+#[cfg(target_os = "linux")]
+fn quit_on_stop(mainloop: &pipewire::main_loop::MainLoop, stop: Arc<AtomicBool>) -> pipewire::loop_::TimerSource<'_> {
+    let quit = mainloop.downgrade();
+    let timer = mainloop.loop_().add_timer(move |_| {
+        if stop.load(Ordering::Relaxed) {
+            quit_main_loop(&quit);
+        }
+    });
+    let _ = timer.update_timer(Some(STOP_POLL_INTERVAL), Some(STOP_POLL_INTERVAL));
+    timer
+}
+
+/// Stop `mainloop.run()` from inside a stream callback. A no-op once the loop
+/// has been dropped.
+#[cfg(target_os = "linux")]
+fn quit_main_loop(mainloop: &pipewire::main_loop::WeakMainLoop) {
+    if let Some(mainloop) = mainloop.upgrade() {
+        mainloop.quit();
+    }
 }
 
+/// DRM modifier for plain row-major buffers.
+#[cfg(target_os = "linux")]
+const DRM_FORMAT_MOD_LINEAR: u64 = 0;
+
 /// PipeWire main loop: connect to screencast stream, read frames, send via channel.
+#[cfg(target_os = "linux")]
+#[allow(clippy::too_many_arguments)]
 fn pipewire_capture_loop(
     pw_fd: std::os::fd::OwnedFd,
     node_id: u32,
     width: u32,
     height: u32,
-    frame_tx: tokio::sync::mpsc::Sender<FrameData>,
+    source_type: Option<SourceType>,
+    frame_tx: FrameSender,
+    dropped: Arc<AtomicU32>,
+    event_tx: mpsc::UnboundedSender<CaptureEvent>,
+    auto_crop: Arc<AtomicBool>,
+    region: Arc<std::sync::Mutex<Option<(u32, u32, u32, u32)>>>,
+    content_size: Arc<std::sync::Mutex<Option<(u32, u32)>>>,
+    paused: Arc<AtomicBool>,
     stop: Arc<AtomicBool>,
 ) -> Result<(), Box<dyn std::error::Error>> {
     pipewire::init();
@@ -294,50 +1146,96 @@ fn pipewire_capture_loop(
     )
     .map_err(|_| "failed to create PipeWire stream")?;
 
-    // Raw pointer to mainloop for quitting from within callbacks.
-    // Safety: mainloop lives on this thread's stack and outlives all callbacks
-    // (callbacks only run during mainloop.run() which blocks in this function).
-    let mainloop_ptr = &mainloop as *const pipewire::main_loop::MainLoop;
-
-    /// Wrapper to allow raw pointer in CaptureState.
-    /// Safety: only accessed from the PipeWire thread (same thread that owns mainloop).
-    struct MainLoopQuit(*const pipewire::main_loop::MainLoop);
-    unsafe impl Send for MainLoopQuit {}
-    unsafe impl Sync for MainLoopQuit {}
-
     struct CaptureState {
-        tx: tokio::sync::mpsc::Sender<FrameData>,
+        tx: FrameSender,
         /// Actual content width in pixels (from portal, not from stride).
         content_w: u32,
         /// Actual content height in pixels.
         content_h: u32,
+        /// Set once param_changed has delivered the negotiated format. Until
+        /// then content_w/h may be the portal's fallback guess.
+        format_ready: bool,
+        /// Where the negotiated content_w/h are published for the engine.
+        content_size: Arc<std::sync::Mutex<Option<(u32, u32)>>>,
         is_bgra: bool,
+        /// False for BGRx/RGBx, whose fourth byte is padding, not alpha.
+        has_alpha: bool,
+        /// Only window captures black out the area around the window with
+        /// alpha=0; monitor captures may carry arbitrary alpha.
+        source_type: Option<SourceType>,
+        /// The negotiated format/modifier is one we can read. When false,
+        /// buffers are dropped (the reason was logged in param_changed).
+        supported: bool,
+        /// Logged once when a buffer's stride can't hold a packed row.
+        logged_bad_stride: bool,
         stop: Arc<AtomicBool>,
         stopped: bool,
-        quit: MainLoopQuit,
+        /// Weak so the callbacks can quit the loop without keeping it alive
+        quit: pipewire::main_loop::WeakMainLoop,
+        dropped: Arc<AtomicU32>,
+        auto_crop: Arc<AtomicBool>,
+        region: Arc<std::sync::Mutex<Option<(u32, u32, u32, u32)>>>,
+        paused: Arc<AtomicBool>,
         cached_crop: CropResult,
         crop_counter: u32,
+        /// Last content rect reported to the engine (x, y, w, h).
+        last_rect: Option<(u32, u32, u32, u32)>,
+        /// False while the window is hidden and frames are skipped.
+        source_visible: bool,
+        events: mpsc::UnboundedSender<CaptureEvent>,
         logged_first: bool,
     }
 
+    let stop_watch = Arc::clone(&stop);
     let state = CaptureState {
         tx: frame_tx,
         content_w: width,
         content_h: height,
+        format_ready: false,
+        content_size,
         is_bgra: true,
+        has_alpha: true,
+        source_type,
+        supported: true,
+        logged_bad_stride: false,
         stop,
         stopped: false,
-        quit: MainLoopQuit(mainloop_ptr),
+        quit: mainloop.downgrade(),
+        dropped,
+        auto_crop,
+        region,
+        paused,
         cached_crop: CropResult::FullFrame,
         crop_counter: 0,
+        last_rect: None,
+        source_visible: true,
+        events: event_tx,
         logged_first: false,
     };
 
     let _listener = stream
         .add_local_listener_with_user_data(state)
+        .state_changed(|_stream, state, old, new| {
+            use pipewire::stream::StreamState;
+            match new {
+                StreamState::Error(msg) => {
+                    report_error(&state.events, ScreenErrorKind::Pipewire, format!("PipeWire stream error: {}", msg));
+                }
+                // The portal session was closed (e.g. the compositor's "stop
+                // sharing" button) and its node went away
+                StreamState::Unconnected
+                    if matches!(old, StreamState::Streaming | StreamState::Paused)
+                        && !state.stop.load(Ordering::Relaxed) =>
+                {
+                    report_error(&state.events, ScreenErrorKind::Portal, "Screencast ended by the system".to_string());
+                }
+                _ => {}
+            }
+        })
         .param_changed(|_stream, state, id, param| {
             use libspa::param::ParamType;
             use libspa::param::format::FormatProperties;
+            use libspa::param::video::VideoFormat;
             use libspa::pod::deserialize::PodDeserializer;
             use libspa::pod::Value;
 
@@ -346,13 +1244,24 @@ fn pipewire_capture_loop(
                 return;
             }
 
-            // Parse the pod to extract the actual VideoSize (width x height)
+            // Parse the pod to extract the actual VideoSize (width x height),
+            // pixel format and, for DMABUF sources, the buffer modifier
+            let mut format = None;
+            let mut modifier = None;
             if let Some(pod) = param {
                 if let Ok((_, Value::Object(obj))) =
                     PodDeserializer::deserialize_from::<Value>(pod.as_bytes())
                 {
                     for prop in &obj.properties {
-                        if prop.key == FormatProperties::VideoSize.as_raw() {
+                        if prop.key == FormatProperties::VideoFormat.as_raw() {
+                            if let Value::Id(id) = &prop.value {
+                                format = Some(VideoFormat::from_raw(id.0));
+                            }
+                        } else if prop.key == FormatProperties::VideoModifier.as_raw() {
+                            if let Value::Long(m) = &prop.value {
+                                modifier = Some(*m as u64);
+                            }
+                        } else if prop.key == FormatProperties::VideoSize.as_raw() {
                             if let Value::Rectangle(rect) = &prop.value {
                                 eprintln!(
                                     "[screen] Negotiated video size: {}x{} (was {}x{})",
@@ -367,10 +1276,35 @@ fn pipewire_capture_loop(
                 }
             }
 
+            // Only packed 32-bit formats are readable; the x variants carry
+            // padding where alpha would be
+            (state.is_bgra, state.has_alpha, state.supported) = match format {
+                Some(VideoFormat::BGRA) | None => (true, true, true),
+                Some(VideoFormat::BGRx) => (true, false, true),
+                Some(VideoFormat::RGBA) => (false, true, true),
+                Some(VideoFormat::RGBx) => (false, false, true),
+                Some(other) => {
+                    eprintln!("[screen] Unsupported PipeWire format {:?}, not capturing", other);
+                    (true, true, false)
+                }
+            };
+            // Tiled/compressed layouts can't be read row by row from mapped memory
+            if let Some(m) = modifier.filter(|&m| m != DRM_FORMAT_MOD_LINEAR) {
+                eprintln!(
+                    "[screen] Unsupported buffer modifier {:#018x} for {:?}, not capturing",
+                    m,
+                    format.unwrap_or(VideoFormat::BGRA)
+                );
+                state.supported = false;
+            }
+
+            state.format_ready = true;
+            *state.content_size.lock().unwrap() = Some((state.content_w, state.content_h));
             eprintln!(
-                "[screen] PipeWire format finalized: {}x{}",
+                "[screen] PipeWire format finalized: {}x{} {:?}",
                 state.content_w,
-                state.content_h
+                state.content_h,
+                format.unwrap_or(VideoFormat::BGRA)
             );
         })
         .process(|stream, state| {
@@ -381,13 +1315,20 @@ fn pipewire_capture_loop(
             // Check stop flag — quit PipeWire main loop so the thread can exit cleanly
             if state.stop.load(Ordering::Relaxed) {
                 state.stopped = true;
-                unsafe { (&*state.quit.0).quit(); }
+                quit_main_loop(&state.quit);
                 return;
             }
 
             let Some(mut buffer) = stream.dequeue_buffer() else {
                 return;
             };
+
+            // Don't size the encoder from a guessed resolution; drop buffers
+            // while paused so nothing new reaches the encoder or preview
+            if !state.format_ready || !state.supported || state.paused.load(Ordering::Relaxed) {
+                return;
+            }
+
             let datas = buffer.datas_mut();
             if datas.is_empty() {
                 return;
@@ -417,15 +1358,25 @@ fn pipewire_capture_loop(
             // Use the smaller of portal height and buffer rows
             let h = content_h.min(buf_h);
             let w = content_w;
-            if w == 0 || h == 0 || stride < w * 4 {
+            if w == 0 || h == 0 {
+                return;
+            }
+            if stride < w * 4 {
+                if !state.logged_bad_stride {
+                    state.logged_bad_stride = true;
+                    eprintln!(
+                        "[screen] Stride {} too small for {}px packed rows, dropping frames",
+                        stride, w
+                    );
+                }
                 return;
             }
 
             if !state.logged_first {
                 state.logged_first = true;
                 eprintln!(
-                    "[screen] First buffer: content={}x{}, stride={}, buf_rows={}, size={}",
-                    w, h, stride, buf_h, size
+                    "[screen] First buffer: content={}x{}, stride={}, buf_rows={}, size={}, source={:?}",
+                    w, h, stride, buf_h, size, state.source_type
                 );
             }
 
@@ -436,12 +1387,49 @@ fn pipewire_capture_loop(
 
             // Detect crop via alpha channel (GNOME window capture blacks out
             // non-window areas with alpha=0). Only re-scan periodically.
+            // Compositors that send opaque frames (or content with real
+            // transparency) can opt out and always get the full frame.
+            // Formats without alpha have garbage there, and monitors have
+            // nothing to crop to, so only window captures with alpha qualify.
             state.crop_counter += 1;
-            if state.crop_counter % 30 == 1 {
+            if !state.auto_crop.load(Ordering::Relaxed)
+                || !state.has_alpha
+                || state.source_type != Some(SourceType::Window)
+            {
+                state.cached_crop = CropResult::FullFrame;
+            } else if state.crop_counter % 30 == 1 {
                 state.cached_crop = detect_alpha_crop(raw, w, h, stride);
             }
 
-            let (frame, fw, fh) = match state.cached_crop {
+            // A user-chosen region replaces the alpha crop, unless the
+            // window isn't visible at all
+            let crop = match (*state.region.lock().unwrap(), state.cached_crop) {
+                (_, CropResult::Empty) => CropResult::Empty,
+                (Some(region), _) => clamp_region(region, w, h),
+                (None, crop) => crop,
+            };
+
+            // Tell the frontend which part of the frame is being shared
+            let rect = match crop {
+                CropResult::Empty => (0, 0, 0, 0),
+                CropResult::Cropped(cx, cy, cw, ch) => {
+                    (cx as u32, cy as u32, (cw & !1) as u32, (ch & !1) as u32)
+                }
+                CropResult::FullFrame => (0, 0, (w & !1) as u32, (h & !1) as u32),
+            };
+            if state.last_rect != Some(rect) {
+                state.last_rect = Some(rect);
+                let (x, y, rw, rh) = rect;
+                let _ = state.events.send(CaptureEvent::ContentRect(x, y, rw, rh));
+            }
+
+            let visible = !matches!(crop, CropResult::Empty);
+            if visible != state.source_visible {
+                state.source_visible = visible;
+                let _ = state.events.send(CaptureEvent::SourceVisible(visible));
+            }
+
+            let (frame, fw, fh) = match crop {
                 CropResult::Empty => {
                     // Window not visible (minimized / other workspace) — skip frame
                     return;
@@ -486,18 +1474,23 @@ fn pipewire_capture_loop(
                 }
             };
 
-            match state.tx.try_send(FrameData {
+            match state.tx.send(FrameData {
                 data: frame,
                 width: fw,
                 height: fh,
                 is_bgra: state.is_bgra,
             }) {
-                Err(tokio::sync::mpsc::error::TrySendError::Closed(_)) => {
+                Err(frame_queue::Closed) => {
                     // Receiver dropped — quit the main loop
                     state.stopped = true;
-                    unsafe { (&*state.quit.0).quit(); }
+                    quit_main_loop(&state.quit);
+                }
+                Ok(Some(_)) => {
+                    // Encoder is behind — the oldest queued frame made room,
+                    // the encode loop lowers the frame rate if it keeps happening
+                    state.dropped.fetch_add(1, Ordering::Relaxed);
                 }
-                _ => {} // Ok or Full — drop excess frames
+                Ok(None) => {}
             }
         })
         .register()
@@ -569,6 +1562,7 @@ fn pipewire_capture_loop(
         )
         .map_err(|_| "failed to connect PipeWire stream")?;
 
+    let _stop_timer = quit_on_stop(&mainloop, stop_watch);
     eprintln!("[screen] PipeWire main loop starting (node_id={})", node_id);
     mainloop.run();
     eprintln!("[screen] PipeWire main loop ended");
@@ -578,13 +1572,34 @@ fn pipewire_capture_loop(
 
 // --- Screen audio capture (PipeWire sink monitor → Opus → RTP) ---
 
+#[cfg(target_os = "linux")]
 const OPUS_SAMPLE_RATE: u32 = 48000;
+#[cfg(target_os = "linux")]
 const OPUS_CHANNELS: usize = 2;
+#[cfg(target_os = "linux")]
 const OPUS_FRAME_SAMPLES: usize = 960; // 20ms at 48kHz
+#[cfg(target_os = "linux")]
+const OPUS_MAX_PACKET: usize = 4000; // libopus's recommended encode buffer
+/// Audio this far behind the shared clock is a stall: the RTP clock skips
+/// ahead by the missing time so the receiver doesn't play later audio early.
+/// Smaller lags are slewed away (see AUDIO_MAX_SLEW) to keep packets
+/// contiguous.
+#[cfg(target_os = "linux")]
+const AUDIO_STALL_GAP: Duration = Duration::from_millis(100);
+/// Most the audio RTP clock is pulled per frame when it has drifted more
+/// than a frame from the shared clock: 1ms per 20ms frame, far faster than
+/// any sound card drifts and too small to hear as a timing glitch.
+#[cfg(target_os = "linux")]
+const AUDIO_MAX_SLEW: u64 = OPUS_SAMPLE_RATE as u64 / 1000;
 
 /// Capture system audio output via PipeWire sink monitor, encode as Opus, write RTP.
+#[cfg(target_os = "linux")]
 fn pipewire_audio_capture_loop(
     track: Arc<TrackLocalStaticRTP>,
+    source: Option<String>,
+    muted: Arc<AtomicBool>,
+    gain: Arc<std::sync::Mutex<f32>>,
+    clock_start: Instant,
     stop: Arc<AtomicBool>,
 ) -> Result<(), Box<dyn std::error::Error>> {
     pipewire::init();
@@ -599,17 +1614,28 @@ fn pipewire_audio_capture_loop(
         .connect(None)
         .map_err(|_| "failed to connect to PipeWire daemon")?;
 
-    let stream = pipewire::stream::Stream::new(
-        &core,
-        "screen-audio-capture",
-        pipewire::properties::properties! {
-            *pipewire::keys::MEDIA_TYPE => "Audio",
-            *pipewire::keys::MEDIA_CATEGORY => "Capture",
-            *pipewire::keys::MEDIA_ROLE => "Music",
-            "stream.capture.sink" => "true",
+    let mut props = pipewire::properties::properties! {
+        *pipewire::keys::MEDIA_TYPE => "Audio",
+        *pipewire::keys::MEDIA_CATEGORY => "Capture",
+        *pipewire::keys::MEDIA_ROLE => "Music",
+        "stream.capture.sink" => "true",
+    };
+
+    // Numeric ids (from wpctl) go to connect(); anything else is a node name
+    let target_id = match source.as_deref() {
+        Some(s) => match s.parse::<u32>() {
+            Ok(id) => Some(id),
+            Err(_) => {
+                props.insert("target.object", s);
+                None
+            }
         },
-    )
-    .map_err(|_| "failed to create PipeWire audio stream")?;
+        None => None,
+    };
+    eprintln!("[screen] Audio capture source: {}", source.as_deref().unwrap_or("default sink"));
+
+    let stream = pipewire::stream::Stream::new(&core, "screen-audio-capture", props)
+        .map_err(|_| "failed to create PipeWire audio stream")?;
 
     // Shared state for negotiated format
     let negotiated_rate = Arc::new(AtomicU32::new(48000));
@@ -620,17 +1646,12 @@ fn pipewire_audio_capture_loop(
     let (producer, consumer) = rb.split();
     let producer = Arc::new(std::sync::Mutex::new(producer));
 
-    let mainloop_ptr = &mainloop as *const pipewire::main_loop::MainLoop;
-
-    struct MainLoopQuit(*const pipewire::main_loop::MainLoop);
-    unsafe impl Send for MainLoopQuit {}
-    unsafe impl Sync for MainLoopQuit {}
-
     struct AudioState {
         producer: Arc<std::sync::Mutex<ringbuf::HeapProd<f32>>>,
         stop: Arc<AtomicBool>,
         stopped: bool,
-        quit: MainLoopQuit,
+        /// Weak so the callbacks can quit the loop without keeping it alive
+        quit: pipewire::main_loop::WeakMainLoop,
         rate: Arc<AtomicU32>,
         channels: Arc<AtomicU32>,
         logged_format: bool,
@@ -640,7 +1661,7 @@ fn pipewire_audio_capture_loop(
         producer: producer.clone(),
         stop: stop.clone(),
         stopped: false,
-        quit: MainLoopQuit(mainloop_ptr),
+        quit: mainloop.downgrade(),
         rate: negotiated_rate.clone(),
         channels: negotiated_channels.clone(),
         logged_format: false,
@@ -690,7 +1711,7 @@ fn pipewire_audio_capture_loop(
 
             if state.stop.load(Ordering::Relaxed) {
                 state.stopped = true;
-                unsafe { (&*state.quit.0).quit(); }
+                quit_main_loop(&state.quit);
                 return;
             }
 
@@ -792,7 +1813,7 @@ fn pipewire_audio_capture_loop(
     stream
         .connect(
             pipewire::spa::utils::Direction::Input,
-            None, // default sink monitor
+            target_id, // None = default sink monitor
             pipewire::stream::StreamFlags::AUTOCONNECT
                 | pipewire::stream::StreamFlags::MAP_BUFFERS,
             &mut [pod],
@@ -803,21 +1824,29 @@ fn pipewire_audio_capture_loop(
     let encode_stop = stop.clone();
     let rate_ref = negotiated_rate;
     let channels_ref = negotiated_channels;
-    std::thread::spawn(move || {
-        opus_encode_loop(track, consumer, encode_stop, rate_ref, channels_ref);
+    let encode_thread = std::thread::spawn(move || {
+        opus_encode_loop(track, consumer, muted, gain, clock_start, encode_stop, rate_ref, channels_ref);
     });
 
+    let _stop_timer = quit_on_stop(&mainloop, stop);
     eprintln!("[screen] PipeWire audio main loop starting (sink monitor)");
     mainloop.run();
     eprintln!("[screen] PipeWire audio main loop ended");
+    let _ = encode_thread.join();
 
     Ok(())
 }
 
-/// Opus encode loop: reads f32 from ring buffer, encodes, writes RTP.
+/// Opus encode loop: reads f32 from ring buffer, encodes, writes RTP
+/// timestamped from `clock_start` (see `AudioClock`).
+#[cfg(target_os = "linux")]
+#[allow(clippy::too_many_arguments)]
 fn opus_encode_loop(
     track: Arc<TrackLocalStaticRTP>,
     mut consumer: ringbuf::HeapCons<f32>,
+    muted: Arc<AtomicBool>,
+    gain: Arc<std::sync::Mutex<f32>>,
+    clock_start: Instant,
     stop: Arc<AtomicBool>,
     negotiated_rate: Arc<AtomicU32>,
     negotiated_channels: Arc<AtomicU32>,
@@ -870,8 +1899,10 @@ fn opus_encode_loop(
     let _ = encoder.set_inband_fec(true);
     let _ = encoder.set_dtx(true);
 
-    let mut opus_buf = vec![0u8; 4000];
+    let mut opus_buf = vec![0u8; OPUS_MAX_PACKET];
     let mut pcm_buf: Vec<f32> = Vec::new();
+    // 48kHz stereo waiting to be cut into Opus frames
+    let mut stereo_buf: Vec<f32> = Vec::new();
 
     let device_frame_samples = if needs_resample {
         let input_frames =
@@ -882,8 +1913,9 @@ fn opus_encode_loop(
         OPUS_FRAME_SAMPLES * device_channels
     };
 
-    let mut timestamp: u32 = 0;
+    let mut clock = AudioClock::new(clock_start);
     let mut sequence: u16 = 0;
+    let mut gain_ramp = GainRamp::new(*gain.lock().unwrap(), OPUS_SAMPLE_RATE, OPUS_CHANNELS);
 
     eprintln!(
         "[screen] Audio encode loop started (device: {}Hz/{}ch, resample: {})",
@@ -898,6 +1930,7 @@ fn opus_encode_loop(
         std::thread::sleep(Duration::from_millis(5));
 
         // Drain from ring buffer
+        let drained_from = pcm_buf.len();
         while consumer.occupied_len() > 0 {
             if let Some(sample) = consumer.try_pop() {
                 pcm_buf.push(sample);
@@ -906,21 +1939,44 @@ fn opus_encode_loop(
             }
         }
 
+        // New audio: line the clock up with when the oldest sample still
+        // buffered was captured (first audio, or resuming after a stall)
+        if pcm_buf.len() > drained_from {
+            let buffered = (stereo_buf.len() / OPUS_CHANNELS) as u64
+                + (pcm_buf.len() / device_channels.max(1)) as u64 * OPUS_SAMPLE_RATE as u64
+                    / device_rate.max(1) as u64;
+            clock.sync(Instant::now(), buffered);
+        }
+
         while pcm_buf.len() >= device_frame_samples {
             let frame: Vec<f32> = pcm_buf.drain(..device_frame_samples).collect();
 
-            // Convert to stereo at 48kHz
-            let stereo_48k = if needs_resample {
-                let stereo = to_stereo(&frame, device_channels);
-                resampler.as_mut().unwrap().process(&stereo)
-            } else {
-                to_stereo(&frame, device_channels)
-            };
+            // Convert to stereo at 48kHz. The resampler doesn't return exactly
+            // one Opus frame per call, so collect and re-slice below.
+            let stereo = to_stereo(&frame, device_channels);
+            match resampler.as_mut() {
+                Some(rs) => stereo_buf.extend(rs.process(&stereo)),
+                None => stereo_buf.extend(stereo),
+            }
+        }
+
+        while stereo_buf.len() >= OPUS_FRAME_SAMPLES * OPUS_CHANNELS {
+            let stereo_48k: Vec<f32> = stereo_buf.drain(..OPUS_FRAME_SAMPLES * OPUS_CHANNELS).collect();
+            // The RTP clock counts 48kHz samples actually encoded, not device samples
+            let frame_samples = (stereo_48k.len() / OPUS_CHANNELS) as u32;
+
+            let timestamp = clock.advance(frame_samples);
 
-            // Opus encode (expects interleaved i16)
+            // Muted: keep the track alive but send nothing (like voice mute)
+            if muted.load(Ordering::Relaxed) {
+                continue;
+            }
+
+            // Opus encode (expects interleaved i16), gain applied first
+            let target_gain = *gain.lock().unwrap();
             let pcm_i16: Vec<i16> = stereo_48k
                 .iter()
-                .map(|&s| (s.clamp(-1.0, 1.0) * 32767.0) as i16)
+                .map(|&s| ((s * gain_ramp.next(target_gain)).clamp(-1.0, 1.0) * 32767.0) as i16)
                 .collect();
 
             let encoded_len = match encoder.encode(&pcm_i16, &mut opus_buf) {
@@ -940,6 +1996,8 @@ fn opus_encode_loop(
                     payload_type: 111,
                     sequence_number: sequence,
                     timestamp,
+                    // Placeholder: write_rtp stamps each binding's negotiated SSRC
+                    // (random per RTCRtpSender, so voice and screen audio never share one)
                     ssrc: 0,
                     ..Default::default()
                 },
@@ -947,7 +2005,6 @@ fn opus_encode_loop(
             };
 
             sequence = sequence.wrapping_add(1);
-            timestamp = timestamp.wrapping_add(OPUS_FRAME_SAMPLES as u32);
 
             rt.block_on(async {
                 if let Err(e) = track.write_rtp(&rtp_packet).await {
@@ -962,7 +2019,96 @@ fn opus_encode_loop(
     eprintln!("[screen] Audio encode loop exited");
 }
 
+/// Video RTP time against the session's shared clock, in 90kHz ticks.
+struct VideoClock {
+    start: Instant,
+    /// Ticks since `start` that the next sample will be stamped with.
+    next: u64,
+}
+
+impl VideoClock {
+    fn new(start: Instant) -> Self {
+        Self { start, next: 0 }
+    }
+
+    /// Duration for the sample being sent now, chosen so the next sample is
+    /// stamped `interval` from now on the shared clock. Never negative: a
+    /// frame that comes early is stamped late rather than out of order.
+    fn sample_duration(&mut self, interval: Duration) -> Duration {
+        let target = ticks(self.start.elapsed() + interval, VIDEO_CLOCK_RATE).max(self.next);
+        let step = target - self.next;
+        self.next = target;
+        // Round up: webrtc truncates the duration back to ticks, and a
+        // one-tick loss per frame would add up to seconds over a long share
+        Duration::from_nanos((step * 1_000_000_000).div_ceil(VIDEO_CLOCK_RATE))
+    }
+}
+
+/// Shared-audio RTP time: 48kHz samples since the session's shared clock.
+#[cfg(target_os = "linux")]
+struct AudioClock {
+    start: Instant,
+    /// Samples since `start` at the next frame's first sample.
+    next: u64,
+    /// Wall-clock position minus `next` at the last sync, less the slew
+    /// applied since.
+    drift: i64,
+    synced: bool,
+}
+
+#[cfg(target_os = "linux")]
+impl AudioClock {
+    fn new(start: Instant) -> Self {
+        Self { start, next: 0, drift: 0, synced: false }
+    }
+
+    /// Audio arrived at `now` with `buffered` samples not yet sent. Jumps to
+    /// the wall-clock position of the oldest of them on the first call, and
+    /// whenever the clock has fallen more than AUDIO_STALL_GAP behind;
+    /// otherwise records the drift for `advance` to slew away.
+    fn sync(&mut self, now: Instant, buffered: u64) {
+        let wall = ticks(now.duration_since(self.start), OPUS_SAMPLE_RATE as u64).saturating_sub(buffered);
+        let gap = ticks(AUDIO_STALL_GAP, OPUS_SAMPLE_RATE as u64);
+        if self.synced && wall <= self.next + gap {
+            self.drift = wall as i64 - self.next as i64;
+            return;
+        }
+        if self.synced {
+            eprintln!(
+                "[screen] Audio stalled for {}ms, skipping ahead",
+                (wall - self.next) * 1000 / OPUS_SAMPLE_RATE as u64
+            );
+        }
+        self.next = wall;
+        self.drift = 0;
+        self.synced = true;
+    }
+
+    /// RTP timestamp for a frame of `samples`, moving the clock past it.
+    /// While the drift is over a frame either way, the step is stretched or
+    /// shortened by up to AUDIO_MAX_SLEW, so the clock settles within one
+    /// frame of the shared clock without timestamps ever repeating.
+    fn advance(&mut self, samples: u32) -> u32 {
+        let timestamp = self.next as u32; // RTP timestamps wrap at 2^32
+        let frame = samples as i64;
+        let slew = if self.drift.abs() > frame {
+            self.drift.clamp(-(AUDIO_MAX_SLEW as i64), AUDIO_MAX_SLEW as i64)
+        } else {
+            0
+        };
+        self.drift -= slew;
+        self.next = (self.next as i64 + frame + slew) as u64;
+        timestamp
+    }
+}
+
+/// Whole `rate`-Hz ticks in `d`.
+fn ticks(d: Duration, rate: u64) -> u64 {
+    (d.as_nanos() * rate as u128 / 1_000_000_000) as u64
+}
+
 /// Convert any channel count to stereo interleaved.
+#[cfg(target_os = "linux")]
 fn to_stereo(samples: &[f32], channels: usize) -> Vec<f32> {
     if channels == 2 {
         return samples.to_vec();
@@ -987,16 +2133,33 @@ fn to_stereo(samples: &[f32], channels: usize) -> Vec<f32> {
 
 /// Result of alpha-based crop detection.
 #[derive(Clone, Copy)]
-enum CropResult {
+pub(super) enum CropResult {
     /// All corners opaque — full monitor capture, send entire frame.
     FullFrame,
     /// Window capture — crop to this (x, y, w, h) region.
     Cropped(usize, usize, usize, usize),
     /// Fully transparent — window is minimized / off-screen, skip frame.
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
     Empty,
 }
 
+/// Fit a requested (x, y, w, h) region inside a `w`×`h` frame, with even
+/// dimensions for the encoder. A region that ends up empty (entirely off the
+/// frame) falls back to the full frame.
+pub(super) fn clamp_region(region: (u32, u32, u32, u32), w: usize, h: usize) -> CropResult {
+    let (rx, ry, rw, rh) = region;
+    let x = (rx as usize).min(w);
+    let y = (ry as usize).min(h);
+    let cw = (rw as usize).min(w - x) & !1;
+    let ch = (rh as usize).min(h - y) & !1;
+    if cw == 0 || ch == 0 || (x == 0 && y == 0 && cw == w & !1 && ch == h & !1) {
+        return CropResult::FullFrame;
+    }
+    CropResult::Cropped(x, y, cw, ch)
+}
+
 /// Detect bounding box of opaque pixels (alpha > 0) in a BGRA buffer with given stride.
+#[cfg(target_os = "linux")]
 fn detect_alpha_crop(data: &[u8], w: usize, h: usize, stride: usize) -> CropResult {
     if w == 0 || h == 0 {
         return CropResult::Empty;
@@ -1017,26 +2180,19 @@ fn detect_alpha_crop(data: &[u8], w: usize, h: usize, stride: usize) -> CropResu
         return CropResult::FullFrame;
     }
 
-    // Full scan to find bounding box of non-transparent pixels
-    let mut top = h;
-    let mut bottom = 0;
-    let mut left = w;
-    let mut right = 0;
-
-    for y in 0..h {
-        for x in 0..w {
-            if alpha_at(x, y) > 0 {
-                if y < top { top = y; }
-                bottom = y;
-                if x < left { left = x; }
-                if x > right { right = x; }
-            }
-        }
-    }
+    // Walk inward from each edge and stop at the first row/column holding a
+    // non-transparent pixel (shadows and rounded corners count). Same box as a
+    // full scan, but only the transparent margin is read — not the interior.
+    let row_visible = |y: usize| (0..w).any(|x| alpha_at(x, y) > 0);
+    let top = match (0..h).find(|&y| row_visible(y)) {
+        Some(y) => y,
+        None => return CropResult::Empty, // fully transparent — window not visible
+    };
+    let bottom = (top..h).rev().find(|&y| row_visible(y)).unwrap_or(top);
 
-    if top > bottom || left > right {
-        return CropResult::Empty; // fully transparent — window not visible
-    }
+    let col_visible = |x: usize| (top..=bottom).any(|y| alpha_at(x, y) > 0);
+    let left = (0..w).find(|&x| col_visible(x)).unwrap_or(0);
+    let right = (left..w).rev().find(|&x| col_visible(x)).unwrap_or(left);
 
     let cw = right - left + 1;
     let ch = bottom - top + 1;
@@ -1049,14 +2205,72 @@ fn detect_alpha_crop(data: &[u8], w: usize, h: usize, stride: usize) -> CropResu
     CropResult::Cropped(left, top, cw, ch)
 }
 
-/// Downscale frame and encode as JPEG, returning raw JPEG bytes.
-fn make_preview_jpeg(data: &[u8], w: usize, h: usize, is_bgra: bool) -> Option<Vec<u8>> {
+/// Area-average (box filter) downscale of a captured frame to at most
+/// `max_width`, keeping the aspect ratio and even dimensions. Averaging every
+/// source pixel keeps text legible where nearest-neighbor would drop strokes.
+/// Frames already narrow enough pass through untouched.
+fn downscale_frame(frame: FrameData, max_width: Option<u32>) -> FrameData {
+    let max_width = match max_width {
+        Some(mw) if frame.width > mw => mw,
+        _ => return frame,
+    };
+    let w = frame.width as usize;
+    let h = frame.height as usize;
+    let tw = (max_width as usize) & !1;
+    let th = ((h * tw / w) & !1).max(2);
+
+    FrameData {
+        data: box_downscale(&frame.data, w, h, tw, th),
+        width: tw as u32,
+        height: th as u32,
+        is_bgra: frame.is_bgra,
+    }
+}
+
+/// Area-average downscale of 4-byte pixels to `tw`x`th`: each output pixel
+/// is the mean of the source pixels it covers. A 4K→480px frame takes ~18ms
+/// on a slow single core, inside the preview cadence.
+fn box_downscale(data: &[u8], w: usize, h: usize, tw: usize, th: usize) -> Vec<u8> {
+    // Source column span of each target column; at least one pixel wide
+    let x_spans: Vec<(usize, usize)> = (0..tw)
+        .map(|x| (x * w / tw, ((x + 1) * w / tw).max(x * w / tw + 1)))
+        .collect();
+
+    let mut out = vec![0u8; tw * th * 4];
+    let mut sums = vec![0u32; tw * 4];
+    for (y, dst_row) in out.chunks_exact_mut(tw * 4).enumerate() {
+        let y0 = y * h / th;
+        let y1 = ((y + 1) * h / th).max(y0 + 1);
+        sums.fill(0);
+        for sy in y0..y1 {
+            let row = &data[sy * w * 4..(sy + 1) * w * 4];
+            for (sum, &(x0, x1)) in sums.chunks_exact_mut(4).zip(&x_spans) {
+                for px in row[x0 * 4..x1 * 4].chunks_exact(4) {
+                    for (s, &p) in sum.iter_mut().zip(px) {
+                        *s += p as u32;
+                    }
+                }
+            }
+        }
+        let rows = (y1 - y0) as u32;
+        for ((dst, sum), &(x0, x1)) in dst_row.chunks_exact_mut(4).zip(sums.chunks_exact(4)).zip(&x_spans) {
+            let count = rows * (x1 - x0) as u32;
+            for (d, &s) in dst.iter_mut().zip(sum) {
+                *d = ((s + count / 2) / count) as u8;
+            }
+        }
+    }
+    out
+}
+
+/// Downscale frame to at most `max_width` and encode as JPEG, returning raw JPEG bytes.
+fn make_preview_jpeg(data: &[u8], w: usize, h: usize, is_bgra: bool, max_width: u32, quality: u8) -> Option<Vec<u8>> {
     use image::codecs::jpeg::JpegEncoder;
     use std::io::Cursor;
 
     // Downscale dimensions
-    let scale = if w as u32 > PREVIEW_MAX_WIDTH {
-        PREVIEW_MAX_WIDTH as f32 / w as f32
+    let scale = if w as u32 > max_width {
+        max_width as f32 / w as f32
     } else {
         1.0
     };
@@ -1066,28 +2280,14 @@ fn make_preview_jpeg(data: &[u8], w: usize, h: usize, is_bgra: bool) -> Option<V
         return None;
     }
 
-    // Nearest-neighbor downscale + BGRA→RGB / RGBA→RGB
-    let mut rgb = vec![0u8; tw * th * 3];
-    for row in 0..th {
-        let src_row = row * h / th;
-        for col in 0..tw {
-            let src_col = col * w / tw;
-            let src_px = (src_row * w + src_col) * 4;
-            let dst_px = (row * tw + col) * 3;
-            if is_bgra {
-                rgb[dst_px] = data[src_px + 2];     // R
-                rgb[dst_px + 1] = data[src_px + 1]; // G
-                rgb[dst_px + 2] = data[src_px];      // B
-            } else {
-                rgb[dst_px] = data[src_px];           // R
-                rgb[dst_px + 1] = data[src_px + 1];   // G
-                rgb[dst_px + 2] = data[src_px + 2];   // B
-            }
-        }
-    }
+    let rgb = if tw == w && th == h {
+        swizzle_rgb(data, w, h, is_bgra)
+    } else {
+        swizzle_rgb(&box_downscale(data, w, h, tw, th), tw, th, is_bgra)
+    };
 
     let mut buf = Cursor::new(Vec::new());
-    let encoder = JpegEncoder::new_with_quality(&mut buf, 55);
+    let encoder = JpegEncoder::new_with_quality(&mut buf, quality);
     if image::ImageEncoder::write_image(
         encoder,
         &rgb,
@@ -1101,3 +2301,14 @@ fn make_preview_jpeg(data: &[u8], w: usize, h: usize, is_bgra: bool) -> Option<V
     Some(buf.into_inner())
 }
 
+/// BGRA/RGBA → packed RGB at the same size.
+fn swizzle_rgb(data: &[u8], w: usize, h: usize, is_bgra: bool) -> Vec<u8> {
+    let (ri, bi) = if is_bgra { (2, 0) } else { (0, 2) };
+    let mut rgb = vec![0u8; w * h * 3];
+    for (dst, px) in rgb.chunks_exact_mut(3).zip(data.chunks_exact(4)) {
+        dst[0] = px[ri];
+        dst[1] = px[1];
+        dst[2] = px[bi];
+    }
+    rgb
+}
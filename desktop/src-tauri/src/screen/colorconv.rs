@@ -0,0 +1,106 @@
+//! BGRA/RGBA → YUV 4:2:0 conversion shared by the encoders, using fixed-point
+//! BT.601 (limited range) coefficients.
+//!
+//! The channel order is a const parameter so each inner loop is monomorphic
+//! with constant offsets; the Y plane loop walks `chunks_exact` rows without
+//! index arithmetic, which LLVM auto-vectorizes.
+
+/// Convert a 4-byte-per-pixel frame to I420 (Y plane, then U, then V).
+pub fn to_i420(src: &[u8], width: usize, height: usize, is_bgra: bool) -> Vec<u8> {
+    let y_size = width * height;
+    let uv_size = (width / 2) * (height / 2);
+    let mut yuv = vec![0u8; y_size + uv_size * 2];
+    let (y_plane, uv_planes) = yuv.split_at_mut(y_size);
+    let (u_plane, v_plane) = uv_planes.split_at_mut(uv_size);
+
+    let put_uv = |i: usize, u: u8, v: u8| {
+        u_plane[i] = u;
+        v_plane[i] = v;
+    };
+    if is_bgra {
+        convert::<2, 0>(src, width, height, y_plane, put_uv);
+    } else {
+        convert::<0, 2>(src, width, height, y_plane, put_uv);
+    }
+    yuv
+}
+
+/// Convert a 4-byte-per-pixel frame to NV12 (Y plane, then interleaved U,V)
+/// into `nv12`, which must hold `width * height * 3 / 2` bytes.
+#[cfg_attr(not(feature = "vaapi"), allow(dead_code))]
+pub fn to_nv12(src: &[u8], nv12: &mut [u8], width: usize, height: usize, is_bgra: bool) {
+    let (y_plane, uv_plane) = nv12.split_at_mut(width * height);
+
+    let put_uv = |i: usize, u: u8, v: u8| {
+        uv_plane[i * 2] = u;
+        uv_plane[i * 2 + 1] = v;
+    };
+    if is_bgra {
+        convert::<2, 0>(src, width, height, y_plane, put_uv);
+    } else {
+        convert::<0, 2>(src, width, height, y_plane, put_uv);
+    }
+}
+
+/// Fill `y_plane` and hand each 2x2-subsampled chroma pair to `put_uv` with
+/// its index in a `width/2 * height/2` plane. `R` and `B` are the byte
+/// offsets of red and blue within a pixel; green is always 1.
+fn convert<const R: usize, const B: usize>(
+    src: &[u8],
+    width: usize,
+    height: usize,
+    y_plane: &mut [u8],
+    mut put_uv: impl FnMut(usize, u8, u8),
+) {
+    let stride = width * 4;
+
+    // Y plane: one sample per pixel
+    for (y_row, src_row) in y_plane
+        .chunks_exact_mut(width)
+        .zip(src.chunks_exact(stride))
+        .take(height)
+    {
+        for (y, px) in y_row.iter_mut().zip(src_row.chunks_exact(4)) {
+            *y = luma(px[R] as i32, px[1] as i32, px[B] as i32);
+        }
+    }
+
+    // U/V: 2x2 subsampled
+    let uv_width = width / 2;
+    for row in 0..height / 2 {
+        let top = &src[row * 2 * stride..][..stride];
+        let bottom = &src[(row * 2 + 1) * stride..][..stride];
+        for col in 0..uv_width {
+            let px = col * 8;
+            let sum = |c: usize| {
+                top[px + c] as i32
+                    + top[px + 4 + c] as i32
+                    + bottom[px + c] as i32
+                    + bottom[px + 4 + c] as i32
+            };
+            // Average the 2x2 block (>> 2)
+            let r = sum(R) >> 2;
+            let g = sum(1) >> 2;
+            let b = sum(B) >> 2;
+            put_uv(row * uv_width + col, chroma_u(r, g, b), chroma_v(r, g, b));
+        }
+    }
+}
+
+/// Y = 16 + (66*R + 129*G + 25*B + 128) >> 8
+#[inline(always)]
+fn luma(r: i32, g: i32, b: i32) -> u8 {
+    (((66 * r + 129 * g + 25 * b + 128) >> 8) + 16) as u8
+}
+
+/// U = 128 + (-38*R - 74*G + 112*B + 128) >> 8
+#[inline(always)]
+fn chroma_u(r: i32, g: i32, b: i32) -> u8 {
+    (((-38 * r - 74 * g + 112 * b + 128) >> 8) + 128) as u8
+}
+
+/// V = 128 + (112*R - 94*G - 18*B + 128) >> 8
+#[inline(always)]
+fn chroma_v(r: i32, g: i32, b: i32) -> u8 {
+    (((112 * r - 94 * g - 18 * b + 128) >> 8) + 128) as u8
+}
@@ -0,0 +1,490 @@
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use tokio::sync::mpsc;
+use windows::core::Interface;
+use windows::Win32::Foundation::HMODULE;
+use windows::Win32::Graphics::Direct3D::D3D_DRIVER_TYPE_UNKNOWN;
+use windows::Win32::Graphics::Direct3D11::{
+    D3D11CreateDevice, ID3D11Device, ID3D11DeviceContext, ID3D11Texture2D, D3D11_CPU_ACCESS_READ,
+    D3D11_CREATE_DEVICE_BGRA_SUPPORT, D3D11_MAPPED_SUBRESOURCE, D3D11_MAP_READ, D3D11_SDK_VERSION,
+    D3D11_TEXTURE2D_DESC, D3D11_USAGE_STAGING,
+};
+use windows::Win32::Graphics::Dxgi::{
+    CreateDXGIFactory1, IDXGIAdapter1, IDXGIFactory1, IDXGIOutput1, IDXGIOutputDuplication, IDXGIResource,
+    DXGI_ERROR_ACCESS_LOST, DXGI_ERROR_NOT_FOUND, DXGI_ERROR_WAIT_TIMEOUT, DXGI_OUTDUPL_FRAME_INFO,
+    DXGI_OUTDUPL_POINTER_SHAPE_INFO, DXGI_OUTDUPL_POINTER_SHAPE_TYPE_MASKED_COLOR,
+    DXGI_OUTDUPL_POINTER_SHAPE_TYPE_MONOCHROME, DXGI_OUTPUT_DESC,
+};
+
+use super::capture::{
+    clamp_region, report_error, CaptureEvent, CropResult, PortalResult, PortalStream, ScreenErrorKind,
+    ScreenStartOptions,
+};
+use super::encoder::FrameData;
+use super::frame_queue::{Closed, FrameSender};
+
+/// How long `AcquireNextFrame` waits for a desktop update, so `stop` is
+/// still noticed while the screen is idle.
+const ACQUIRE_TIMEOUT_MS: u32 = 100;
+/// Retry interval while duplication can't be reopened (secure desktop, mode change).
+const REOPEN_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Cursor modes, standing in for the portal's. Desktop Duplication leaves the
+/// pointer out of the image; embedded draws it back in, the others don't.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CursorMode {
+    Hidden,
+    Embedded,
+    Metadata,
+}
+
+/// An attached monitor, for choosing the `stream_index` to share.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct MonitorInfo {
+    pub index: u32,
+    /// GDI device name ("\\.\DISPLAY1").
+    pub name: String,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub primary: bool,
+}
+
+/// An output attached to the desktop and the adapter driving it.
+struct Output {
+    adapter: IDXGIAdapter1,
+    output: IDXGIOutput1,
+    desc: DXGI_OUTPUT_DESC,
+}
+
+/// GDI device name ("\\.\DISPLAY1") of an output.
+fn device_name(desc: &DXGI_OUTPUT_DESC) -> String {
+    let len = desc.DeviceName.iter().position(|&c| c == 0).unwrap_or(desc.DeviceName.len());
+    String::from_utf16_lossy(&desc.DeviceName[..len])
+}
+
+/// Every output attached to the desktop, across all adapters. Indices into
+/// this list are the monitor indices the commands use.
+fn outputs() -> windows::core::Result<Vec<Output>> {
+    let factory: IDXGIFactory1 = unsafe { CreateDXGIFactory1()? };
+    let mut outputs = Vec::new();
+    for a in 0.. {
+        let adapter = match unsafe { factory.EnumAdapters1(a) } {
+            Ok(adapter) => adapter,
+            Err(e) if e.code() == DXGI_ERROR_NOT_FOUND => break,
+            Err(e) => return Err(e),
+        };
+        for o in 0.. {
+            let output = match unsafe { adapter.EnumOutputs(o) } {
+                Ok(output) => output,
+                Err(e) if e.code() == DXGI_ERROR_NOT_FOUND => break,
+                Err(e) => return Err(e),
+            };
+            let desc = unsafe { output.GetDesc()? };
+            if desc.AttachedToDesktop.as_bool() {
+                outputs.push(Output { adapter: adapter.clone(), output: output.cast()?, desc });
+            }
+        }
+    }
+    Ok(outputs)
+}
+
+pub fn list_monitors() -> Result<Vec<MonitorInfo>, Box<dyn std::error::Error + Send + Sync>> {
+    Ok(outputs()?
+        .iter()
+        .enumerate()
+        .map(|(i, o)| {
+            let rect = o.desc.DesktopCoordinates;
+            MonitorInfo {
+                index: i as u32,
+                name: device_name(&o.desc),
+                x: rect.left,
+                y: rect.top,
+                width: (rect.right - rect.left) as u32,
+                height: (rect.bottom - rect.top) as u32,
+                // The primary monitor sits at the desktop origin
+                primary: rect.left == 0 && rect.top == 0,
+            }
+        })
+        .collect())
+}
+
+/// Windows stand-in for the portal picker: every monitor is a stream, and
+/// `options.stream_index` picks the one to capture (the first if out of range).
+pub fn start_screencast(
+    cursor_mode: CursorMode,
+    options: &ScreenStartOptions,
+) -> Result<PortalResult, Box<dyn std::error::Error + Send + Sync>> {
+    let monitors = list_monitors()?;
+    let streams: Vec<PortalStream> = monitors
+        .iter()
+        .map(|m| PortalStream { node_id: m.index, width: m.width, height: m.height })
+        .collect();
+    if streams.is_empty() {
+        return Err("no monitors attached".into());
+    }
+    let stream_index = if options.stream_index < streams.len() {
+        options.stream_index
+    } else {
+        eprintln!(
+            "[screen] Stream index {} out of range ({} monitors), using 0",
+            options.stream_index,
+            streams.len()
+        );
+        0
+    };
+    let PortalStream { node_id, width, height } = streams[stream_index].clone();
+
+    eprintln!(
+        "[screen] DXGI screencast: monitor {}/{} {}, {}x{}, cursor {:?}",
+        stream_index + 1,
+        streams.len(),
+        monitors[stream_index].name,
+        width,
+        height,
+        cursor_mode
+    );
+
+    Ok(PortalResult {
+        node_id,
+        width,
+        height,
+        streams,
+        stream_index,
+        restore_token: None,
+        synthetic: false,
+        draw_cursor: cursor_mode == CursorMode::Embedded,
+        device_name: monitors[stream_index].name.clone(),
+    })
+}
+
+/// Last pointer position and shape duplication reported.
+#[derive(Default)]
+struct Pointer {
+    visible: bool,
+    x: i32,
+    y: i32,
+    shape: Vec<u8>,
+    info: DXGI_OUTDUPL_POINTER_SHAPE_INFO,
+}
+
+/// A duplication session on one monitor, with the latest desktop image.
+struct Duplication {
+    device: ID3D11Device,
+    context: ID3D11DeviceContext,
+    duplication: IDXGIOutputDuplication,
+    /// CPU-readable copy target, recreated when the desktop size changes.
+    staging: Option<(ID3D11Texture2D, u32, u32)>,
+    /// Tightly packed BGRA, without the pointer.
+    image: Vec<u8>,
+    width: usize,
+    height: usize,
+    pointer: Pointer,
+}
+
+impl Duplication {
+    /// Duplicate the monitor named `name`. DXGI_ERROR_NOT_FOUND if it's no
+    /// longer attached.
+    fn open(name: &str) -> windows::core::Result<Self> {
+        let output = outputs()?
+            .into_iter()
+            .find(|o| device_name(&o.desc) == name)
+            .ok_or_else(|| windows::core::Error::from(DXGI_ERROR_NOT_FOUND))?;
+
+        let mut device = None;
+        let mut context = None;
+        unsafe {
+            D3D11CreateDevice(
+                &output.adapter,
+                D3D_DRIVER_TYPE_UNKNOWN,
+                HMODULE::default(),
+                D3D11_CREATE_DEVICE_BGRA_SUPPORT,
+                None,
+                D3D11_SDK_VERSION,
+                Some(&mut device),
+                None,
+                Some(&mut context),
+            )?;
+        }
+        let device = device.expect("D3D11CreateDevice succeeded without a device");
+        let context = context.expect("D3D11CreateDevice succeeded without a context");
+
+        // Plain DuplicateOutput always hands out B8G8R8A8 frames, even on HDR desktops
+        let duplication = unsafe { output.output.DuplicateOutput(&device)? };
+
+        Ok(Self {
+            device,
+            context,
+            duplication,
+            staging: None,
+            image: Vec::new(),
+            width: 0,
+            height: 0,
+            pointer: Pointer::default(),
+        })
+    }
+
+    /// Wait for the next desktop update and take in its image and pointer
+    /// changes. True if there's something new to send: a new image, or a
+    /// pointer move when the pointer is drawn.
+    fn acquire(&mut self, draw_cursor: bool) -> windows::core::Result<bool> {
+        let mut info = DXGI_OUTDUPL_FRAME_INFO::default();
+        let mut resource = None;
+        match unsafe { self.duplication.AcquireNextFrame(ACQUIRE_TIMEOUT_MS, &mut info, &mut resource) } {
+            Ok(()) => {}
+            Err(e) if e.code() == DXGI_ERROR_WAIT_TIMEOUT => return Ok(false),
+            Err(e) => return Err(e),
+        }
+
+        let result = self.take_frame(&info, resource, draw_cursor);
+        // Always hand the frame back, or duplication stops delivering
+        let released = unsafe { self.duplication.ReleaseFrame() };
+        let updated = result?;
+        released?;
+        Ok(updated)
+    }
+
+    fn take_frame(
+        &mut self,
+        info: &DXGI_OUTDUPL_FRAME_INFO,
+        resource: Option<IDXGIResource>,
+        draw_cursor: bool,
+    ) -> windows::core::Result<bool> {
+        let mut updated = false;
+        // LastPresentTime stays zero when only the pointer changed
+        if info.LastPresentTime != 0 {
+            if let Some(resource) = resource {
+                self.copy_image(&resource.cast()?)?;
+                updated = true;
+            }
+        }
+        if draw_cursor {
+            if info.LastMouseUpdateTime != 0 {
+                self.pointer.visible = info.PointerPosition.Visible.as_bool();
+                self.pointer.x = info.PointerPosition.Position.x;
+                self.pointer.y = info.PointerPosition.Position.y;
+                updated = true;
+            }
+            if info.PointerShapeBufferSize > 0 {
+                self.pointer.shape.resize(info.PointerShapeBufferSize as usize, 0);
+                let mut required = 0u32;
+                unsafe {
+                    self.duplication.GetFramePointerShape(
+                        info.PointerShapeBufferSize,
+                        self.pointer.shape.as_mut_ptr().cast(),
+                        &mut required,
+                        &mut self.pointer.info,
+                    )?;
+                }
+            }
+        }
+        Ok(updated && !self.image.is_empty())
+    }
+
+    /// Copy the desktop texture into `image` through the staging texture.
+    fn copy_image(&mut self, texture: &ID3D11Texture2D) -> windows::core::Result<()> {
+        let mut desc = D3D11_TEXTURE2D_DESC::default();
+        unsafe { texture.GetDesc(&mut desc) };
+
+        let staging = match &self.staging {
+            Some((staging, w, h)) if (*w, *h) == (desc.Width, desc.Height) => staging.clone(),
+            _ => {
+                let staging_desc = D3D11_TEXTURE2D_DESC {
+                    Usage: D3D11_USAGE_STAGING,
+                    BindFlags: 0,
+                    CPUAccessFlags: D3D11_CPU_ACCESS_READ.0 as u32,
+                    MiscFlags: 0,
+                    ..desc
+                };
+                let mut staging = None;
+                unsafe { self.device.CreateTexture2D(&staging_desc, None, Some(&mut staging))? };
+                let staging = staging.expect("CreateTexture2D succeeded without a texture");
+                self.staging = Some((staging.clone(), desc.Width, desc.Height));
+                staging
+            }
+        };
+        let (w, h) = (desc.Width as usize, desc.Height as usize);
+
+        let mut mapped = D3D11_MAPPED_SUBRESOURCE::default();
+        unsafe {
+            self.context.CopyResource(&staging, texture);
+            self.context.Map(&staging, 0, D3D11_MAP_READ, 0, Some(&mut mapped))?;
+        }
+        let pitch = mapped.RowPitch as usize;
+        let src = unsafe { std::slice::from_raw_parts(mapped.pData as *const u8, pitch * h) };
+        let row_bytes = w * 4;
+        self.image.resize(row_bytes * h, 0);
+        for (row, dst) in self.image.chunks_exact_mut(row_bytes).enumerate() {
+            dst.copy_from_slice(&src[row * pitch..row * pitch + row_bytes]);
+        }
+        unsafe { self.context.Unmap(&staging, 0) };
+
+        self.width = w;
+        self.height = h;
+        Ok(())
+    }
+}
+
+/// Blend the pointer into a BGRA frame whose top-left is at (`ox`, `oy`) on
+/// the monitor.
+fn draw_pointer(frame: &mut [u8], w: usize, h: usize, ox: usize, oy: usize, pointer: &Pointer) {
+    if !pointer.visible || pointer.shape.is_empty() {
+        return;
+    }
+    let info = &pointer.info;
+    let pitch = info.Pitch as usize;
+    let monochrome = info.Type == DXGI_OUTDUPL_POINTER_SHAPE_TYPE_MONOCHROME.0 as u32;
+    let masked = info.Type == DXGI_OUTDUPL_POINTER_SHAPE_TYPE_MASKED_COLOR.0 as u32;
+    // Monochrome shapes are an AND mask stacked on an XOR mask, 1 bit per pixel
+    let shape_h = if monochrome { info.Height as usize / 2 } else { info.Height as usize };
+
+    for sy in 0..shape_h {
+        let y = pointer.y as i64 + sy as i64 - oy as i64;
+        if y < 0 || y >= h as i64 {
+            continue;
+        }
+        for sx in 0..info.Width as usize {
+            let x = pointer.x as i64 + sx as i64 - ox as i64;
+            if x < 0 || x >= w as i64 {
+                continue;
+            }
+            let offset = (y as usize * w + x as usize) * 4;
+            let dst = &mut frame[offset..offset + 3];
+            if monochrome {
+                let bit = 0x80 >> (sx % 8);
+                let and = pointer.shape[sy * pitch + sx / 8] & bit != 0;
+                let xor = pointer.shape[(sy + shape_h) * pitch + sx / 8] & bit != 0;
+                for c in dst {
+                    let v = if and { *c } else { 0 };
+                    *c = if xor { !v } else { v };
+                }
+                continue;
+            }
+            let src = &pointer.shape[sy * pitch + sx * 4..sy * pitch + sx * 4 + 4];
+            if masked {
+                // Alpha 0 replaces the pixel, 0xff XORs the color into it
+                for (d, s) in dst.iter_mut().zip(&src[..3]) {
+                    *d = if src[3] == 0 { *s } else { *d ^ *s };
+                }
+            } else {
+                let a = src[3] as u32;
+                for (d, s) in dst.iter_mut().zip(&src[..3]) {
+                    *d = ((*s as u32 * a + *d as u32 * (255 - a)) / 255) as u8;
+                }
+            }
+        }
+    }
+}
+
+/// Reopen duplication after access was lost, retrying while it's refused
+/// (the secure desktop keeps it until UAC or the lock screen is dismissed).
+/// None once `stop` is set, or the monitor is gone; another monitor taking
+/// its place in the list doesn't count.
+fn reopen(name: &str, events: &mpsc::UnboundedSender<CaptureEvent>, stop: &AtomicBool) -> Option<Duplication> {
+    while !stop.load(Ordering::Relaxed) {
+        std::thread::sleep(REOPEN_INTERVAL);
+        match Duplication::open(name) {
+            Ok(duplication) => return Some(duplication),
+            Err(e) if e.code() == DXGI_ERROR_NOT_FOUND => {
+                report_error(events, ScreenErrorKind::Portal, format!("Monitor {} was disconnected", name));
+                return None;
+            }
+            Err(_) => {}
+        }
+    }
+    None
+}
+
+/// Capture the monitor named `name` with Desktop Duplication and feed its frames to
+/// the encode loop, at most `fps` a second, until `stop` is set. Like the
+/// PipeWire loop it applies the user's region, reports the shared rect and
+/// captured size, and sends nothing while paused.
+#[allow(clippy::too_many_arguments)]
+pub fn capture_loop(
+    name: &str,
+    fps: u32,
+    draw_cursor: bool,
+    frame_tx: FrameSender,
+    dropped: Arc<AtomicU32>,
+    events: mpsc::UnboundedSender<CaptureEvent>,
+    region: Arc<std::sync::Mutex<Option<(u32, u32, u32, u32)>>>,
+    content_size: Arc<std::sync::Mutex<Option<(u32, u32)>>>,
+    paused: Arc<AtomicBool>,
+    stop: Arc<AtomicBool>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let mut duplication = Duplication::open(name)?;
+    eprintln!("[screen] DXGI duplication started on {}", name);
+
+    // Duplication coalesces desktop updates, so waiting out the frame
+    // interval just means the next acquire returns the latest image
+    let interval = Duration::from_micros(1_000_000 / fps.max(1) as u64);
+    let mut next = Instant::now();
+    let mut last_rect = None;
+
+    while !stop.load(Ordering::Relaxed) {
+        match next.checked_duration_since(Instant::now()) {
+            Some(wait) => std::thread::sleep(wait),
+            None => next = Instant::now(),
+        }
+        next += interval;
+
+        let updated = match duplication.acquire(draw_cursor) {
+            Ok(updated) => updated,
+            Err(e) if e.code() == DXGI_ERROR_ACCESS_LOST => {
+                // Display mode change, secure desktop, or a fullscreen app taking over
+                eprintln!("[screen] DXGI: access lost, reopening duplication");
+                let _ = events.send(CaptureEvent::SourceVisible(false));
+                match reopen(name, &events, &stop) {
+                    Some(reopened) => duplication = reopened,
+                    None => break,
+                }
+                let _ = events.send(CaptureEvent::SourceVisible(true));
+                continue;
+            }
+            Err(e) => return Err(e.into()),
+        };
+        if !updated || paused.load(Ordering::Relaxed) {
+            continue;
+        }
+
+        let (w, h) = (duplication.width, duplication.height);
+        // Changes with display mode switches, picked up after a reopen
+        *content_size.lock().unwrap() = Some((w as u32, h as u32));
+        let (cx, cy, cw, ch) = match region.lock().unwrap().map(|r| clamp_region(r, w, h)) {
+            Some(CropResult::Cropped(x, y, cw, ch)) => (x, y, cw, ch),
+            _ => (0, 0, w & !1, h & !1),
+        };
+        if cw == 0 || ch == 0 {
+            continue;
+        }
+        let rect = (cx as u32, cy as u32, cw as u32, ch as u32);
+        if last_rect != Some(rect) {
+            last_rect = Some(rect);
+            let _ = events.send(CaptureEvent::ContentRect(rect.0, rect.1, rect.2, rect.3));
+        }
+
+        let mut data = vec![0u8; cw * ch * 4];
+        for (row, dst) in data.chunks_exact_mut(cw * 4).enumerate() {
+            let src = ((cy + row) * w + cx) * 4;
+            dst.copy_from_slice(&duplication.image[src..src + cw * 4]);
+        }
+        if draw_cursor {
+            draw_pointer(&mut data, cw, ch, cx, cy, &duplication.pointer);
+        }
+
+        // Same back-pressure as PipeWire: drop the oldest queued frame if the encoder is behind
+        match frame_tx.send(FrameData { data, width: cw as u32, height: ch as u32, is_bgra: true }) {
+            Err(Closed) => break,
+            Ok(Some(_)) => {
+                dropped.fetch_add(1, Ordering::Relaxed);
+            }
+            Ok(None) => {}
+        }
+    }
+
+    eprintln!("[screen] DXGI capture stopped");
+    Ok(())
+}
@@ -1,6 +1,10 @@
 use openh264::encoder::{Encoder, EncoderConfig, RateControlMode, UsageType};
 use openh264::formats::YUVBuffer;
 use openh264::OpenH264API;
+use openh264_sys2::{SBitrateInfo, ENCODER_OPTION_BITRATE, SPATIAL_LAYER_ALL};
+use serde::Serialize;
+
+use super::colorconv::to_i420;
 
 pub struct FrameData {
     pub data: Vec<u8>,
@@ -9,12 +13,224 @@ pub struct FrameData {
     pub is_bgra: bool,
 }
 
+/// Video codecs a screen share can negotiate with the SFU.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VideoCodec {
+    H264,
+    Vp8,
+    Vp9,
+}
+
+/// What an encoder's output bitstream is, so it can be matched to the track.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct CodecInfo {
+    pub codec: VideoCodec,
+    /// H.264 profile name ("baseline", "main") or VP9 profile number. None when
+    /// the driver picks it.
+    pub profile: Option<&'static str>,
+    /// NAL units with start codes, which the H.264 RTP payloader expects, as
+    /// opposed to length-prefixed AVCC. False for VP8/VP9.
+    pub annex_b: bool,
+}
+
+impl VideoCodec {
+    /// Parse a codec name as used by commands and SDP rtpmap lines ("h264", "VP8", ...).
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "h264" => Some(Self::H264),
+            "vp8" => Some(Self::Vp8),
+            "vp9" => Some(Self::Vp9),
+            _ => None,
+        }
+    }
+
+    /// Name in SDP rtpmap lines.
+    pub fn sdp_name(self) -> &'static str {
+        match self {
+            Self::H264 => "H264",
+            Self::Vp8 => "VP8",
+            Self::Vp9 => "VP9",
+        }
+    }
+
+    /// Whether this build has an encoder for the codec.
+    pub fn is_encodable(self) -> bool {
+        ENCODABLE_CODECS.contains(&self)
+    }
+}
+
+/// Codecs this build can encode, in preference order. VP8/VP9 need the
+/// `vpx` feature.
+#[cfg(feature = "vpx")]
+pub const ENCODABLE_CODECS: &[VideoCodec] = &[VideoCodec::H264, VideoCodec::Vp8, VideoCodec::Vp9];
+#[cfg(not(feature = "vpx"))]
+pub const ENCODABLE_CODECS: &[VideoCodec] = &[VideoCodec::H264];
+
+/// H.264 profiles a share can advertise. openh264 always emits Constrained
+/// Baseline, which decoders for any of these accept; the GPU backends encode
+/// the selected profile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum H264Profile {
+    ConstrainedBaseline,
+    Baseline,
+    Main,
+    High,
+}
+
+impl H264Profile {
+    /// Parse a profile name as used by commands ("constrained-baseline", "main", ...).
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "constrained-baseline" => Some(Self::ConstrainedBaseline),
+            "baseline" => Some(Self::Baseline),
+            "main" => Some(Self::Main),
+            "high" => Some(Self::High),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::ConstrainedBaseline => "constrained-baseline",
+            Self::Baseline => "baseline",
+            Self::Main => "main",
+            Self::High => "high",
+        }
+    }
+
+    /// Profile of an fmtp `profile-level-id` ("42e01f", "64001f", ...). Baseline
+    /// with constraint_set1 is Constrained Baseline.
+    pub fn from_profile_level_id(id: &str) -> Option<Self> {
+        let idc = u8::from_str_radix(id.get(0..2)?, 16).ok()?;
+        let iop = u8::from_str_radix(id.get(2..4)?, 16).ok()?;
+        match idc {
+            0x42 if iop & 0x40 != 0 => Some(Self::ConstrainedBaseline),
+            0x42 => Some(Self::Baseline),
+            0x4d => Some(Self::Main),
+            0x64 => Some(Self::High),
+            _ => None,
+        }
+    }
+
+    /// profile_idc and constraint flags, the first two bytes of `profile-level-id`.
+    fn profile_iop(self) -> (u8, u8) {
+        match self {
+            Self::ConstrainedBaseline => (0x42, 0xe0),
+            Self::Baseline => (0x42, 0x00),
+            Self::Main => (0x4d, 0x00),
+            Self::High => (0x64, 0x00),
+        }
+    }
+}
+
+/// level_idc values allowed for H.264 shares (3.1 = 31), with each level's
+/// MaxMBPS and MaxFS (macroblocks per second and per frame) from Table A-1.
+const H264_LEVELS: [(u8, u32, u32); 7] = [
+    (31, 108_000, 3_600),
+    (40, 245_760, 8_192),
+    (41, 245_760, 8_192),
+    (42, 522_240, 8_704),
+    (50, 589_824, 22_080),
+    (51, 983_040, 36_864),
+    (52, 2_073_600, 36_864),
+];
+
+/// H.264 level as level_idc, one of `H264_LEVELS`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct H264Level(u8);
+
+impl H264Level {
+    /// Parse a level name as used by commands ("3.1", "4", "5.2", ...).
+    pub fn from_name(name: &str) -> Option<Self> {
+        let (major, minor) = name.split_once('.').unwrap_or((name, "0"));
+        if minor.len() != 1 {
+            return None;
+        }
+        let idc = major.parse::<u8>().ok()?.checked_mul(10)?.checked_add(minor.parse::<u8>().ok()?)?;
+        H264_LEVELS.iter().any(|&(l, _, _)| l == idc).then_some(Self(idc))
+    }
+
+    /// Lowest level that fits `width`x`height` at `fps`; 5.2 if none does.
+    pub fn for_stream(width: u32, height: u32, fps: u32) -> Self {
+        let frame_mbs = width.div_ceil(16) * height.div_ceil(16);
+        let mbps = frame_mbs * fps.max(1);
+        H264_LEVELS
+            .iter()
+            .find(|&&(_, max_mbps, max_fs)| frame_mbs <= max_fs && mbps <= max_mbps)
+            .map_or(Self(52), |&(idc, _, _)| Self(idc))
+    }
+
+    pub fn idc(self) -> u8 {
+        self.0
+    }
+}
+
+/// Profile and level for H.264 shares, advertised in the track's fmtp line
+/// and requested from the encoder. Defaults to Constrained Baseline
+/// (`42e01f`), which every WebRTC receiver decodes, at whatever level the
+/// stream needs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct H264Settings {
+    pub profile: H264Profile,
+    /// None picks the level from the encoded resolution and frame rate.
+    pub level: Option<H264Level>,
+}
+
+impl Default for H264Settings {
+    fn default() -> Self {
+        Self {
+            profile: H264Profile::ConstrainedBaseline,
+            level: None,
+        }
+    }
+}
+
+impl H264Settings {
+    /// Hex `profile-level-id` for the SDP fmtp line. An automatic level is
+    /// advertised as 3.1; `level-asymmetry-allowed` lets the stream exceed it.
+    pub fn profile_level_id(self) -> String {
+        let (idc, iop) = self.profile.profile_iop();
+        let level = self.level.map_or(31, H264Level::idc);
+        format!("{:02x}{:02x}{:02x}", idc, iop, level)
+    }
+
+    /// Level to encode `width`x`height` at `fps` with: the chosen one, or
+    /// the lowest that fits.
+    pub fn level_for(self, width: u32, height: u32, fps: u32) -> H264Level {
+        self.level
+            .unwrap_or_else(|| H264Level::for_stream(width, height, fps))
+    }
+}
+
 pub trait ScreenEncoder {
-    /// Encode a frame. Returns H.264 NAL units (Annex B).
+    /// Encode a frame, in the format `codec_info` describes.
     fn encode(&mut self, frame: &FrameData) -> Result<Vec<u8>, Box<dyn std::error::Error>>;
 
     /// Force next frame to be an IDR keyframe.
     fn force_keyframe(&mut self);
+
+    /// Change the target bitrate mid-stream.
+    fn set_bitrate(&mut self, kbps: u32);
+
+    /// Whether `set_bitrate` retunes the running encoder. Backends that can't
+    /// rebuild their session instead, which costs a keyframe.
+    fn live_bitrate(&self) -> bool {
+        true
+    }
+
+    /// Short backend name for status reporting ("openh264", "vaapi", ...).
+    fn backend(&self) -> &'static str;
+
+    /// GPU backends can initialize and still fail on real frames (driver or
+    /// format trouble); the capture loop falls back to software if so.
+    fn is_hardware(&self) -> bool {
+        false
+    }
+
+    /// Codec and bitstream format of the encoded output.
+    fn codec_info(&self) -> CodecInfo;
 }
 
 struct SoftwareEncoder {
@@ -24,11 +240,11 @@ struct SoftwareEncoder {
 }
 
 impl SoftwareEncoder {
-    fn new(width: u32, height: u32, bitrate_kbps: u32) -> Result<Self, Box<dyn std::error::Error>> {
+    fn new(width: u32, height: u32, bitrate_kbps: u32, fps: u32) -> Result<Self, Box<dyn std::error::Error>> {
         let config = EncoderConfig::new()
-            .set_bitrate_bps(bitrate_kbps * 1000)
+            .set_bitrate_bps(bitrate_kbps.saturating_mul(1000))
             .usage_type(UsageType::ScreenContentRealTime)
-            .max_frame_rate(60.0)
+            .max_frame_rate(fps as f32)
             .enable_skip_frame(false)
             .rate_control_mode(RateControlMode::Bitrate);
         let encoder = Encoder::with_api_config(OpenH264API::from_source(), config)?;
@@ -45,11 +261,7 @@ impl ScreenEncoder for SoftwareEncoder {
         let fw = frame.width as usize;
         let fh = frame.height as usize;
 
-        let i420 = if frame.is_bgra {
-            bgra_to_i420(&frame.data, fw, fh)
-        } else {
-            rgba_to_i420(&frame.data, fw, fh)
-        };
+        let i420 = to_i420(&frame.data, fw, fh, frame.is_bgra);
 
         let yuv = YUVBuffer::from_vec(i420, self.width, self.height);
         let bitstream = self.encoder.encode(&yuv)?;
@@ -59,130 +271,95 @@ impl ScreenEncoder for SoftwareEncoder {
     fn force_keyframe(&mut self) {
         self.encoder.force_intra_frame();
     }
+
+    fn set_bitrate(&mut self, kbps: u32) {
+        // The safe wrapper only applies bitrate at init — go through SetOption
+        let mut info = SBitrateInfo {
+            iLayer: SPATIAL_LAYER_ALL,
+            iBitrate: i32::try_from(kbps.saturating_mul(1000)).unwrap_or(i32::MAX),
+        };
+        let rc = unsafe {
+            self.encoder
+                .raw_api()
+                .set_option(ENCODER_OPTION_BITRATE, std::ptr::addr_of_mut!(info).cast())
+        };
+        if rc != 0 {
+            eprintln!("[screen] openh264: set bitrate {}kbps failed ({})", kbps, rc);
+        }
+    }
+
+    fn backend(&self) -> &'static str {
+        "openh264"
+    }
+
+    fn codec_info(&self) -> CodecInfo {
+        CodecInfo {
+            codec: VideoCodec::H264,
+            profile: Some(H264Profile::ConstrainedBaseline.name()),
+            annex_b: true,
+        }
+    }
 }
 
+/// Create an encoder for `codec`, checking that its output fits the track
+/// (the H.264 payloader only understands Annex B). GPU backends compiled in
+/// are tried first unless `allow_hardware` is false; each one probes the
+/// device at runtime and is skipped if it can't open a session. `h264` is
+/// the profile/level GPU backends encode; ignored for VP8/VP9.
 pub fn create_encoder(
+    codec: VideoCodec,
     width: u32,
     height: u32,
     bitrate_kbps: u32,
+    fps: u32,
+    allow_hardware: bool,
+    h264: H264Settings,
 ) -> Result<Box<dyn ScreenEncoder>, Box<dyn std::error::Error>> {
-    #[cfg(feature = "nvenc")]
-    {
-        if let Some(enc) = super::nvenc::NvencEncoder::try_new(width, height, bitrate_kbps) {
-            return Ok(Box::new(enc));
-        }
-    }
-    #[cfg(feature = "vaapi")]
-    {
-        if let Some(enc) = super::vaapi::VaapiEncoder::try_new(width, height, bitrate_kbps) {
-            return Ok(Box::new(enc));
-        }
-    }
-    eprintln!("[screen] Using software encoder (openh264)");
-    Ok(Box::new(SoftwareEncoder::new(width, height, bitrate_kbps)?))
-}
-
-/// Convert BGRA pixels to I420 (YUV420p) using fixed-point BT.601 coefficients.
-/// Integer math avoids float ops — ~3-5x faster than the float version.
-fn bgra_to_i420(bgra: &[u8], width: usize, height: usize) -> Vec<u8> {
-    let y_size = width * height;
-    let uv_width = width / 2;
-    let uv_height = height / 2;
-    let uv_size = uv_width * uv_height;
-
-    let mut yuv = vec![0u8; y_size + uv_size * 2];
-    let (y_plane, uv_planes) = yuv.split_at_mut(y_size);
-    let (u_plane, v_plane) = uv_planes.split_at_mut(uv_size);
-
-    // Y plane: one sample per pixel
-    for row in 0..height {
-        let row_off = row * width;
-        for col in 0..width {
-            let px = (row_off + col) * 4;
-            let b = bgra[px] as i32;
-            let g = bgra[px + 1] as i32;
-            let r = bgra[px + 2] as i32;
-            // Y = 16 + (66*R + 129*G + 25*B + 128) >> 8
-            y_plane[row_off + col] = (((66 * r + 129 * g + 25 * b + 128) >> 8) + 16) as u8;
-        }
+    let encoder = open_encoder(codec, width, height, bitrate_kbps, fps, allow_hardware, h264)?;
+    let info = encoder.codec_info();
+    if info.codec != codec || (codec == VideoCodec::H264 && !info.annex_b) {
+        return Err(format!(
+            "{} produces {:?} (annex_b={}), track wants {:?}",
+            encoder.backend(),
+            info.codec,
+            info.annex_b,
+            codec
+        )
+        .into());
     }
+    eprintln!("[screen] Using {} encoder for {:?} ({}x{})", encoder.backend(), codec, width, height);
+    Ok(encoder)
+}
 
-    // U/V planes: 2x2 subsampled
-    for row in 0..uv_height {
-        let src_row = row * 2;
-        let uv_off = row * uv_width;
-        for col in 0..uv_width {
-            let src_col = col * 2;
-            let mut r_sum = 0i32;
-            let mut g_sum = 0i32;
-            let mut b_sum = 0i32;
-            for dy in 0..2 {
-                for dx in 0..2 {
-                    let px = ((src_row + dy) * width + (src_col + dx)) * 4;
-                    b_sum += bgra[px] as i32;
-                    g_sum += bgra[px + 1] as i32;
-                    r_sum += bgra[px + 2] as i32;
-                }
-            }
-            // Average the 2x2 block (>> 2)
-            let r = r_sum >> 2;
-            let g = g_sum >> 2;
-            let b = b_sum >> 2;
-            // U = 128 + (-38*R - 74*G + 112*B + 128) >> 8
-            u_plane[uv_off + col] = (((-38 * r - 74 * g + 112 * b + 128) >> 8) + 128) as u8;
-            // V = 128 + (112*R - 94*G - 18*B + 128) >> 8
-            v_plane[uv_off + col] = (((112 * r - 94 * g - 18 * b + 128) >> 8) + 128) as u8;
+#[cfg_attr(not(any(feature = "nvenc", feature = "vaapi")), allow(unused_variables))]
+fn open_encoder(
+    codec: VideoCodec,
+    width: u32,
+    height: u32,
+    bitrate_kbps: u32,
+    fps: u32,
+    allow_hardware: bool,
+    h264: H264Settings,
+) -> Result<Box<dyn ScreenEncoder>, Box<dyn std::error::Error>> {
+    if codec != VideoCodec::H264 {
+        #[cfg(feature = "vpx")]
+        {
+            return Ok(Box::new(super::vpx::VpxEncoder::try_new(codec, width, height, bitrate_kbps)?));
         }
+        #[cfg(not(feature = "vpx"))]
+        return Err(format!("{:?} encoding requires the vpx feature", codec).into());
     }
-
-    yuv
-}
-
-/// Convert RGBA pixels to I420 (YUV420p) using fixed-point BT.601 coefficients.
-fn rgba_to_i420(rgba: &[u8], width: usize, height: usize) -> Vec<u8> {
-    let y_size = width * height;
-    let uv_width = width / 2;
-    let uv_height = height / 2;
-    let uv_size = uv_width * uv_height;
-
-    let mut yuv = vec![0u8; y_size + uv_size * 2];
-    let (y_plane, uv_planes) = yuv.split_at_mut(y_size);
-    let (u_plane, v_plane) = uv_planes.split_at_mut(uv_size);
-
-    for row in 0..height {
-        let row_off = row * width;
-        for col in 0..width {
-            let px = (row_off + col) * 4;
-            let r = rgba[px] as i32;
-            let g = rgba[px + 1] as i32;
-            let b = rgba[px + 2] as i32;
-            y_plane[row_off + col] = (((66 * r + 129 * g + 25 * b + 128) >> 8) + 16) as u8;
+    #[cfg(feature = "nvenc")]
+    if allow_hardware {
+        if let Some(enc) = super::nvenc::NvencEncoder::try_new(width, height, bitrate_kbps, fps, h264) {
+            return Ok(Box::new(enc));
         }
     }
-
-    for row in 0..uv_height {
-        let src_row = row * 2;
-        let uv_off = row * uv_width;
-        for col in 0..uv_width {
-            let src_col = col * 2;
-            let mut r_sum = 0i32;
-            let mut g_sum = 0i32;
-            let mut b_sum = 0i32;
-            for dy in 0..2 {
-                for dx in 0..2 {
-                    let px = ((src_row + dy) * width + (src_col + dx)) * 4;
-                    r_sum += rgba[px] as i32;
-                    g_sum += rgba[px + 1] as i32;
-                    b_sum += rgba[px + 2] as i32;
-                }
-            }
-            let r = r_sum >> 2;
-            let g = g_sum >> 2;
-            let b = b_sum >> 2;
-            u_plane[uv_off + col] = (((-38 * r - 74 * g + 112 * b + 128) >> 8) + 128) as u8;
-            v_plane[uv_off + col] = (((112 * r - 94 * g - 18 * b + 128) >> 8) + 128) as u8;
+    #[cfg(feature = "vaapi")]
+    if allow_hardware {
+        if let Some(enc) = super::vaapi::VaapiEncoder::try_new(width, height, bitrate_kbps, fps, h264) {
+            return Ok(Box::new(enc));
         }
     }
-
-    yuv
+    Ok(Box::new(SoftwareEncoder::new(width, height, bitrate_kbps, fps)?))
 }
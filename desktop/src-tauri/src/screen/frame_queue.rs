@@ -0,0 +1,102 @@
+//! Queue from a capture loop to the encode loop that keeps the newest
+//! frames. When it's full the oldest queued frame makes room, so a slow
+//! encoder catches up on the latest picture rather than a stale one. A depth
+//! of 1 is a single slot the capture side overwrites.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::Notify;
+
+use super::encoder::FrameData;
+
+struct Shared {
+    state: Mutex<State>,
+    /// Woken on every send and when the sender goes away.
+    notify: Notify,
+}
+
+struct State {
+    frames: VecDeque<FrameData>,
+    depth: usize,
+    sender_gone: bool,
+    receiver_gone: bool,
+}
+
+/// A queue holding up to `depth` (at least 1) frames.
+pub fn channel(depth: usize) -> (FrameSender, FrameReceiver) {
+    let depth = depth.max(1);
+    let shared = Arc::new(Shared {
+        state: Mutex::new(State {
+            frames: VecDeque::with_capacity(depth),
+            depth,
+            sender_gone: false,
+            receiver_gone: false,
+        }),
+        notify: Notify::new(),
+    });
+    (FrameSender(Arc::clone(&shared)), FrameReceiver(shared))
+}
+
+/// The encode loop has gone; the capture loop should stop.
+#[derive(Debug)]
+pub struct Closed;
+
+pub struct FrameSender(Arc<Shared>);
+
+impl FrameSender {
+    /// Queue `frame` without blocking. Returns the frame dropped to make
+    /// room, if the queue was full.
+    pub fn send(&self, frame: FrameData) -> Result<Option<FrameData>, Closed> {
+        let mut state = self.0.state.lock().unwrap();
+        if state.receiver_gone {
+            return Err(Closed);
+        }
+        let dropped = if state.frames.len() >= state.depth {
+            state.frames.pop_front()
+        } else {
+            None
+        };
+        state.frames.push_back(frame);
+        drop(state);
+        self.0.notify.notify_one();
+        Ok(dropped)
+    }
+}
+
+impl Drop for FrameSender {
+    fn drop(&mut self) {
+        self.0.state.lock().unwrap().sender_gone = true;
+        self.0.notify.notify_one();
+    }
+}
+
+pub struct FrameReceiver(Arc<Shared>);
+
+impl FrameReceiver {
+    /// Oldest queued frame, waiting for one if the queue is empty. None once
+    /// the sender is gone and the queue has drained. Cancel-safe, so it can
+    /// be raced against a timeout.
+    pub async fn recv(&mut self) -> Option<FrameData> {
+        loop {
+            {
+                let mut state = self.0.state.lock().unwrap();
+                if let Some(frame) = state.frames.pop_front() {
+                    return Some(frame);
+                }
+                if state.sender_gone {
+                    return None;
+                }
+            }
+            self.0.notify.notified().await;
+        }
+    }
+}
+
+impl Drop for FrameReceiver {
+    fn drop(&mut self) {
+        let mut state = self.0.state.lock().unwrap();
+        state.receiver_gone = true;
+        state.frames.clear();
+    }
+}
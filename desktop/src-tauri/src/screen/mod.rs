@@ -1,27 +1,47 @@
 pub mod capture;
+mod colorconv;
 pub mod encoder;
+mod frame_queue;
 pub mod peer;
 pub mod preview;
 #[cfg(feature = "nvenc")]
 mod nvenc;
 #[cfg(feature = "vaapi")]
 mod vaapi;
+#[cfg(feature = "vpx")]
+mod vpx;
+#[cfg(feature = "synthetic-capture")]
+mod synthetic;
+#[cfg(windows)]
+mod dxgi;
 
 use std::sync::Arc;
 use serde::Serialize;
 use tauri::{AppHandle, Emitter};
 use tokio::sync::Mutex;
 
-use capture::ScreenCapture;
+use capture::{CaptureEvent, CursorMode, EncoderStatus, PortalStream, ScreenCapture, ScreenErrorKind, ScreenStartOptions};
+use encoder::{H264Level, H264Profile, H264Settings, VideoCodec};
 use peer::{ScreenPeer, ScreenPeerEvent};
 use preview::MjpegServer;
 use crate::voice::types::*;
+use crate::voice::VoiceState;
 
 pub struct ScreenEngine {
     peer: Option<ScreenPeer>,
     capture: ScreenCapture,
     event_handle: Option<tokio::task::JoinHandle<()>>,
     mjpeg_server: Option<MjpegServer>,
+    /// Set while `screen_start` is between the portal picker and setup completion.
+    starting: bool,
+    ice_servers: Vec<IceServerConfig>,
+    /// Codec offered first to the SFU; the offer may still pick another.
+    preferred_codec: VideoCodec,
+    /// H.264 profile/level the share advertises and encodes.
+    h264: H264Settings,
+    /// Portal restore token for the last shared monitor/window.
+    saved_source: Option<String>,
+    cursor_mode: CursorMode,
 }
 
 impl ScreenEngine {
@@ -31,11 +51,32 @@ impl ScreenEngine {
             capture: ScreenCapture::new(),
             event_handle: None,
             mjpeg_server: None,
+            starting: false,
+            ice_servers: Vec::new(),
+            preferred_codec: VideoCodec::H264,
+            h264: H264Settings::default(),
+            saved_source: None,
+            cursor_mode: CursorMode::Embedded,
         }
     }
 
-    fn stop(&mut self) {
-        self.capture.stop();
+    /// STUN/TURN servers used for the next screen peer connection.
+    pub fn set_ice_servers(&mut self, servers: Vec<IceServerConfig>) {
+        self.ice_servers = servers;
+    }
+
+    pub fn status(&self) -> ScreenStatus {
+        let running = self.peer.is_some();
+        ScreenStatus {
+            running,
+            paused: running && self.capture.is_paused(),
+            preview_port: self.mjpeg_server.as_ref().map(|s| s.port()),
+            encoder: self.capture.encoder_status(),
+        }
+    }
+
+    async fn stop(&mut self) {
+        self.capture.stop().await;
         if let Some(server) = self.mjpeg_server.take() {
             server.stop();
         }
@@ -56,61 +97,376 @@ pub type ScreenState = Arc<Mutex<ScreenEngine>>;
 #[derive(Serialize)]
 pub struct ScreenStartResult {
     pub preview_port: u16,
+    /// Pass back to `screen_start` to reuse this source without the picker.
+    pub restore_token: Option<String>,
+    /// Every stream the portal returned, for choosing a `stream_index`.
+    pub streams: Vec<PortalStream>,
+    /// Index into `streams` of the one being captured.
+    pub stream_index: usize,
+}
+
+/// Screen half of `get_engine_state`.
+#[derive(Serialize)]
+pub struct ScreenStatus {
+    pub running: bool,
+    pub paused: bool,
+    pub preview_port: Option<u16>,
+    /// None until the first frame has been encoded.
+    pub encoder: Option<EncoderStatus>,
+}
+
+/// `screen_get_session_info` — what the running share actually ended up with.
+#[derive(Serialize)]
+pub struct ScreenSessionInfo {
+    /// Size of the captured stream as negotiated with PipeWire (the monitor
+    /// size on Windows), before any crop or downscale. None until known.
+    pub content_width: Option<u32>,
+    pub content_height: Option<u32>,
+    /// Codec, profile, encoded size, bitrate and sent fps. None until the
+    /// first frame has been encoded.
+    pub encoder: Option<EncoderStatus>,
+}
+
+/// Region of the captured frame that holds the actual window content.
+#[derive(Serialize, Clone)]
+pub struct ContentRectEvent {
+    pub x: u32,
+    pub y: u32,
+    pub w: u32,
+    pub h: u32,
+}
+
+/// `screen:encoder_overloaded` — frames were being dropped, so the share now
+/// runs at `fps`. The UI can suggest a lower resolution or bitrate.
+#[derive(Serialize, Clone)]
+pub struct EncoderOverloadedEvent {
+    pub fps: u32,
+    pub dropped: u32,
+}
+
+/// `screen:frame_stats` — frames actually sent per second and the encoded
+/// bitrate, about once a second while the share runs. `delivered`/`dropped`
+/// count captured frames that reached the encoder or were dropped because
+/// it was behind; sustained drops lower `target_fps`.
+#[derive(Serialize, Clone)]
+pub struct FrameStatsEvent {
+    pub fps: f32,
+    pub bitrate_kbps: u32,
+    pub delivered: u32,
+    pub dropped: u32,
+    pub target_fps: u32,
+}
+
+/// `screen:error` — the share hit a failure the user should know about.
+#[derive(Serialize, Clone)]
+pub struct ScreenErrorEvent {
+    pub kind: ScreenErrorKind,
+    pub message: String,
+}
+
+/// `screen:source_visible` — false while the shared window is minimized or
+/// on another workspace, so the UI can ask the user to bring it back.
+#[derive(Serialize, Clone)]
+pub struct SourceVisibleEvent {
+    pub visible: bool,
 }
 
 #[tauri::command]
 pub async fn screen_start(
     app: AppHandle,
     state: tauri::State<'_, ScreenState>,
+    restore_token: Option<String>,
+    options: Option<ScreenStartOptions>,
 ) -> Result<ScreenStartResult, String> {
+    let options = options.unwrap_or_default().validated();
+
     // Stop any existing session
-    {
+    let (restore_token, cursor_mode) = {
         let mut engine = state.inner().lock().await;
-        engine.stop();
-    } // drop lock before portal (portal shows a picker dialog)
+        // A second click while the picker is open must not clobber the first start
+        if engine.starting {
+            return Err("screen share is already starting".to_string());
+        }
+        engine.starting = true;
+        engine.stop().await;
+        (restore_token.or_else(|| engine.saved_source.clone()), engine.cursor_mode)
+    }; // drop lock before portal (portal shows a picker dialog)
+
+    let result = start_session(&app, state.inner(), restore_token.as_deref(), cursor_mode, options).await;
+
+    // Clear the guard whether setup succeeded or not; tear down any
+    // half-initialized state on failure.
+    let mut engine = state.inner().lock().await;
+    engine.starting = false;
+    if result.is_err() {
+        engine.stop().await;
+    }
+    result
+}
+
+/// Portal picker + capture/peer setup for `screen_start`.
+async fn start_session(
+    app: &AppHandle,
+    state: &ScreenState,
+    restore_token: Option<&str>,
+    cursor_mode: CursorMode,
+    options: ScreenStartOptions,
+) -> Result<ScreenStartResult, String> {
+    // Shared audio comes from a PipeWire sink monitor; Windows shares are video-only.
+    // There's no picker there either, so nothing to restore.
+    #[cfg(windows)]
+    let options = ScreenStartOptions { share_audio: false, ..options };
+    #[cfg(windows)]
+    let _ = restore_token;
 
     // Run portal FIRST — if the user cancels, we return an error and the
     // frontend never sets isPresenting/sends screen_share_start.
-    let portal = capture::portal_start_screencast()
-        .await
-        .map_err(|e| e.to_string())?;
+    #[cfg(feature = "synthetic-capture")]
+    let synthetic = synthetic::source_from_env();
+    #[cfg(not(feature = "synthetic-capture"))]
+    let synthetic = None;
+    let portal = match synthetic {
+        Some(portal) => portal,
+        #[cfg(target_os = "linux")]
+        None => capture::portal_start_screencast(restore_token, cursor_mode, &options)
+            .await
+            .map_err(|e| e.to_string())?,
+        #[cfg(windows)]
+        None => dxgi::start_screencast(cursor_mode, &options).map_err(|e| e.to_string())?,
+    };
 
     // Re-acquire lock for the rest of setup
-    let mut engine = state.inner().lock().await;
+    let mut engine = state.lock().await;
+    let restore_token = portal.restore_token.clone();
+    let streams = portal.streams.clone();
+    let stream_index = portal.stream_index;
+    engine.saved_source = restore_token.clone();
 
-    // Create watch channel for preview frames
+    // Create watch channels for preview frames (thumbnail and full-res)
     let (preview_tx, preview_rx) = tokio::sync::watch::channel(None);
+    let (fullres_tx, fullres_rx) = tokio::sync::watch::channel(None);
 
     // Start MJPEG server
-    let mjpeg_server = MjpegServer::start(preview_rx)
+    let mjpeg_server = MjpegServer::start(preview_rx, fullres_rx, options.preview_port)
         .await
         .map_err(|e| e.to_string())?;
     let preview_port = mjpeg_server.port();
+    let preview_clients = mjpeg_server.clients();
     engine.mjpeg_server = Some(mjpeg_server);
 
     // Create peer and start capture
-    let (peer, peer_rx) = ScreenPeer::new().await.map_err(|e| e.to_string())?;
-    let video_track = Arc::clone(&peer.video_track);
-    let audio_track = Arc::clone(&peer.audio_track);
+    let (peer, peer_rx) = ScreenPeer::new(&engine.ice_servers, engine.preferred_codec, engine.h264, options.share_audio)
+        .await
+        .map_err(|e| e.to_string())?;
+    let video_rx = peer.video_output();
+    let audio_track = peer.audio_track.clone();
+    let feedback = Arc::clone(&peer.feedback);
 
-    engine.capture.start(video_track, audio_track, preview_tx, portal);
+    engine.capture.start(video_rx, audio_track, feedback, preview_tx, fullres_tx, preview_clients, portal, options);
+    let capture_rx = engine.capture.event_rx.take();
 
     // Spawn event forwarding loop
     let app_handle = app.clone();
     let event_handle = tokio::spawn(async move {
-        run_event_loop(app_handle, peer_rx).await;
+        run_event_loop(app_handle, peer_rx, capture_rx).await;
     });
     engine.event_handle = Some(event_handle);
     engine.peer = Some(peer);
 
     eprintln!("[screen] Screen engine started (preview port: {})", preview_port);
-    Ok(ScreenStartResult { preview_port })
+    Ok(ScreenStartResult {
+        preview_port,
+        restore_token,
+        streams,
+        stream_index,
+    })
 }
 
 #[tauri::command]
 pub async fn screen_stop(state: tauri::State<'_, ScreenState>) -> Result<(), String> {
     let mut engine = state.inner().lock().await;
-    engine.stop();
+    engine.stop().await;
+    Ok(())
+}
+
+/// Cursor mode for the next screen share: "embedded" (drawn into the frame),
+/// "hidden", or "metadata". Metadata mode delivers the cursor as separate
+/// PipeWire metadata, which isn't composited yet — viewers see no cursor,
+/// same as hidden. On Windows only embedded shows the cursor.
+#[tauri::command]
+pub async fn screen_set_cursor_mode(
+    state: tauri::State<'_, ScreenState>,
+    mode: String,
+) -> Result<(), String> {
+    let cursor_mode = match mode.as_str() {
+        "embedded" => CursorMode::Embedded,
+        "hidden" => CursorMode::Hidden,
+        "metadata" => CursorMode::Metadata,
+        _ => return Err(format!("unknown cursor mode: {}", mode)),
+    };
+    let mut engine = state.inner().lock().await;
+    engine.cursor_mode = cursor_mode;
+    Ok(())
+}
+
+/// Monitors attached to the desktop, for picking the `stream_index` to
+/// share. Windows only — on Linux the portal's picker chooses.
+#[cfg(windows)]
+#[tauri::command]
+pub async fn screen_list_monitors() -> Result<Vec<dxgi::MonitorInfo>, String> {
+    dxgi::list_monitors().map_err(|e| e.to_string())
+}
+
+/// Sink whose monitor is captured as shared audio: a wpctl id from
+/// `list_screen_audio_sources` or a node name. None = default sink.
+/// Takes effect on the next screen share.
+#[tauri::command]
+pub async fn screen_set_audio_source(
+    state: tauri::State<'_, ScreenState>,
+    node_id_or_name: Option<String>,
+) -> Result<(), String> {
+    let mut engine = state.inner().lock().await;
+    engine.capture.set_audio_source(node_id_or_name);
+    Ok(())
+}
+
+/// Forget the remembered source so the next `screen_start` shows the picker.
+#[tauri::command]
+pub async fn screen_clear_saved_source(state: tauri::State<'_, ScreenState>) -> Result<(), String> {
+    let mut engine = state.inner().lock().await;
+    engine.saved_source = None;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn screen_set_auto_crop(
+    state: tauri::State<'_, ScreenState>,
+    enabled: bool,
+) -> Result<(), String> {
+    let engine = state.inner().lock().await;
+    engine.capture.set_auto_crop(enabled);
+    Ok(())
+}
+
+/// Share only the (x, y, w, h) rectangle of the captured monitor/window, in
+/// captured-frame pixels. Clamped to the frame and rounded to even sizes;
+/// the preview shows the same region.
+#[tauri::command]
+pub async fn screen_set_region(
+    state: tauri::State<'_, ScreenState>,
+    x: u32,
+    y: u32,
+    w: u32,
+    h: u32,
+) -> Result<(), String> {
+    if w == 0 || h == 0 {
+        return Err("region must not be empty".to_string());
+    }
+    let engine = state.inner().lock().await;
+    engine.capture.set_region(Some((x, y, w, h)));
+    Ok(())
+}
+
+/// Go back to sharing the whole captured frame.
+#[tauri::command]
+pub async fn screen_clear_region(state: tauri::State<'_, ScreenState>) -> Result<(), String> {
+    let engine = state.inner().lock().await;
+    engine.capture.set_region(None);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn screen_set_audio_muted(
+    state: tauri::State<'_, ScreenState>,
+    muted: bool,
+) -> Result<(), String> {
+    let engine = state.inner().lock().await;
+    engine.capture.set_audio_muted(muted);
+    Ok(())
+}
+
+/// Linear gain for shared system audio (1.0 = unchanged). Applies live.
+#[tauri::command]
+pub async fn screen_set_audio_gain(
+    state: tauri::State<'_, ScreenState>,
+    gain: f32,
+) -> Result<(), String> {
+    let engine = state.inner().lock().await;
+    engine.capture.set_audio_gain(gain);
+    Ok(())
+}
+
+/// Also stream full-resolution frames (as encoded, ~10fps) on the preview
+/// server's `/preview/full`, for checking fine detail like text. Off by default.
+#[tauri::command]
+pub async fn screen_set_preview_fullres(
+    state: tauri::State<'_, ScreenState>,
+    enabled: bool,
+) -> Result<(), String> {
+    let engine = state.inner().lock().await;
+    engine.capture.set_fullres_preview(enabled);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn screen_pause(state: tauri::State<'_, ScreenState>) -> Result<(), String> {
+    let engine = state.inner().lock().await;
+    engine.capture.set_paused(true);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn screen_resume(state: tauri::State<'_, ScreenState>) -> Result<(), String> {
+    let engine = state.inner().lock().await;
+    engine.capture.set_paused(false);
+    Ok(())
+}
+
+/// Video codec to use when the SFU offers it ("h264", "vp8", "vp9"). VP8 and
+/// VP9 are refused in builds without the `vpx` feature. Takes effect on the
+/// next screen share.
+#[tauri::command]
+pub async fn screen_set_preferred_codec(
+    state: tauri::State<'_, ScreenState>,
+    codec: String,
+) -> Result<(), String> {
+    let codec = VideoCodec::from_name(&codec).ok_or(format!("unknown codec: {}", codec))?;
+    if !codec.is_encodable() {
+        return Err(format!("{:?} encoding requires the vpx feature", codec));
+    }
+    let mut engine = state.inner().lock().await;
+    engine.preferred_codec = codec;
+    Ok(())
+}
+
+/// H.264 profile ("constrained-baseline", "baseline", "main", "high") and
+/// level ("3.1", "4", "4.1", "4.2", "5", "5.1", "5.2") for receivers that
+/// need something other than the default Constrained Baseline. No level
+/// picks the lowest that fits the stream. An SFU that doesn't accept the
+/// profile gets Constrained Baseline. Takes effect on the next screen share.
+#[tauri::command]
+pub async fn screen_set_h264_profile(
+    state: tauri::State<'_, ScreenState>,
+    profile: String,
+    level: Option<String>,
+) -> Result<(), String> {
+    let profile = H264Profile::from_name(&profile).ok_or(format!("unknown H.264 profile: {}", profile))?;
+    let level = level
+        .map(|level| H264Level::from_name(&level).ok_or(format!("unsupported H.264 level: {}", level)))
+        .transpose()?;
+    let mut engine = state.inner().lock().await;
+    engine.h264 = H264Settings { profile, level };
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn screen_set_bitrate_limits(
+    state: tauri::State<'_, ScreenState>,
+    min_kbps: u32,
+    max_kbps: u32,
+) -> Result<(), String> {
+    let engine = state.inner().lock().await;
+    engine.capture.set_bitrate_limits(min_kbps, max_kbps);
     Ok(())
 }
 
@@ -126,6 +482,111 @@ pub async fn screen_handle_offer(
     Ok(SdpAnswer { sdp: answer_sdp })
 }
 
+/// Offer for the screen share connection, for servers that expect the client
+/// to initiate. The share must have been started; apply the server's reply
+/// with `screen_handle_answer`.
+#[tauri::command]
+pub async fn screen_create_offer(state: tauri::State<'_, ScreenState>) -> Result<SdpOffer, String> {
+    let engine = state.inner().lock().await;
+    let peer = engine.peer.as_ref().ok_or("no screen peer")?;
+    let sdp = peer.create_offer().await.map_err(|e| e.to_string())?;
+    Ok(SdpOffer { sdp })
+}
+
+#[tauri::command]
+pub async fn screen_handle_answer(
+    state: tauri::State<'_, ScreenState>,
+    sdp: String,
+) -> Result<(), String> {
+    let engine = state.inner().lock().await;
+    let peer = engine.peer.as_ref().ok_or("no screen peer")?;
+    peer.handle_answer(&sdp).await.map_err(|e| e.to_string())
+}
+
+/// Send the running share's video and audio (if shared) tracks on the voice
+/// connection as well, so one peer can carry both, and return the offer to renegotiate it
+/// with (answer via `voice_handle_answer`). Viewers' keyframe requests on the
+/// voice connection reach the encoder. The video track is the one negotiated
+/// on the screen peer right now — a later codec switch there isn't followed —
+/// and the screen peer keeps sending until it's stopped.
+#[tauri::command]
+pub async fn voice_add_screen_track(
+    voice_state: tauri::State<'_, VoiceState>,
+    screen_state: tauri::State<'_, ScreenState>,
+) -> Result<SdpOffer, String> {
+    let (video_track, audio_track, feedback) = {
+        let engine = screen_state.inner().lock().await;
+        let peer = engine.peer.as_ref().ok_or("screen share is not running")?;
+        let video_track = Arc::clone(&peer.video_output().borrow().track);
+        (video_track, peer.audio_track.clone(), Arc::clone(&peer.feedback))
+    };
+
+    let engine = voice_state.inner().lock().await;
+    let video_sender = engine
+        .add_track(video_track)
+        .await
+        .map_err(|e| e.to_string())?;
+    peer::forward_keyframe_requests(video_sender, feedback);
+    if let Some(audio_track) = audio_track {
+        let audio_sender = engine
+            .add_track(audio_track)
+            .await
+            .map_err(|e| e.to_string())?;
+        tokio::spawn(async move {
+            let mut buf = vec![0u8; 1500];
+            while audio_sender.read(&mut buf).await.is_ok() {}
+        });
+    }
+
+    let sdp = engine.create_offer().await.map_err(|e| e.to_string())?;
+    Ok(SdpOffer { sdp })
+}
+
+/// RTT, loss and byte counts for the screen share connection.
+#[tauri::command]
+pub async fn screen_get_stats(state: tauri::State<'_, ScreenState>) -> Result<PeerStats, String> {
+    let engine = state.inner().lock().await;
+    let peer = engine.peer.as_ref().ok_or("no screen peer")?;
+    Ok(peer.stats().await)
+}
+
+/// ICE gathering state of the screen share connection ("new", "gathering", "complete").
+#[tauri::command]
+pub async fn screen_get_ice_gathering_state(state: tauri::State<'_, ScreenState>) -> Result<String, String> {
+    let engine = state.inner().lock().await;
+    let peer = engine.peer.as_ref().ok_or("no screen peer")?;
+    Ok(peer.ice_gathering_state())
+}
+
+/// Backend of the running video encoder ("nvenc", "vaapi", "openh264",
+/// "libvpx"), to confirm hardware encoding is in use. None while no share
+/// is encoding.
+#[tauri::command]
+pub async fn screen_get_encoder(
+    state: tauri::State<'_, ScreenState>,
+) -> Result<Option<String>, String> {
+    let engine = state.inner().lock().await;
+    Ok(engine.capture.encoder_status().map(|status| status.backend.to_string()))
+}
+
+/// Negotiated capture size and encoder settings of the running share, for
+/// diagnostics and labelling it in the UI. None while not sharing.
+#[tauri::command]
+pub async fn screen_get_session_info(
+    state: tauri::State<'_, ScreenState>,
+) -> Result<Option<ScreenSessionInfo>, String> {
+    let engine = state.inner().lock().await;
+    if engine.peer.is_none() {
+        return Ok(None);
+    }
+    let content = engine.capture.content_size();
+    Ok(Some(ScreenSessionInfo {
+        content_width: content.map(|(w, _)| w),
+        content_height: content.map(|(_, h)| h),
+        encoder: engine.capture.encoder_status(),
+    }))
+}
+
 #[tauri::command]
 pub async fn screen_handle_ice(
     state: tauri::State<'_, ScreenState>,
@@ -150,18 +611,63 @@ pub async fn screen_handle_ice(
 async fn run_event_loop(
     app: AppHandle,
     mut peer_rx: tokio::sync::mpsc::UnboundedReceiver<ScreenPeerEvent>,
+    mut capture_rx: Option<tokio::sync::mpsc::UnboundedReceiver<CaptureEvent>>,
 ) {
-    while let Some(event) = peer_rx.recv().await {
-        match event {
-            ScreenPeerEvent::IceCandidate(candidate) => {
-                let _ = app.emit("screen:ice_candidate", &candidate);
+    loop {
+        tokio::select! {
+            Some(event) = peer_rx.recv() => {
+                match event {
+                    ScreenPeerEvent::IceCandidate(candidate) => {
+                        let _ = app.emit("screen:ice_candidate", &candidate);
+                    }
+                    ScreenPeerEvent::IceGatheringComplete => {
+                        let _ = app.emit("screen:ice_gathering_complete", ());
+                    }
+                    ScreenPeerEvent::ConnectionState(state) => {
+                        log::info!("[screen] Connection state: {}", state);
+                        if state == "failed" || state == "closed" {
+                            break;
+                        }
+                    }
+                }
             }
-            ScreenPeerEvent::ConnectionState(state) => {
-                log::info!("[screen] Connection state: {}", state);
-                if state == "failed" || state == "closed" {
-                    break;
+            Some(event) = async {
+                match capture_rx.as_mut() {
+                    Some(rx) => rx.recv().await,
+                    None => std::future::pending().await,
+                }
+            } => {
+                match event {
+                    CaptureEvent::ContentRect(x, y, w, h) => {
+                        let _ = app.emit(
+                            "screen:content_rect",
+                            &ContentRectEvent { x, y, w, h },
+                        );
+                    }
+                    CaptureEvent::EncoderOverloaded { fps, dropped } => {
+                        let _ = app.emit(
+                            "screen:encoder_overloaded",
+                            &EncoderOverloadedEvent { fps, dropped },
+                        );
+                    }
+                    CaptureEvent::Error { kind, message } => {
+                        let _ = app.emit("screen:error", &ScreenErrorEvent { kind, message });
+                    }
+                    CaptureEvent::SourceVisible(visible) => {
+                        let _ = app.emit("screen:source_visible", &SourceVisibleEvent { visible });
+                    }
+                    CaptureEvent::StreamReady => {
+                        let _ = app.emit("screen:stream_ready", ());
+                    }
+                    CaptureEvent::FrameStats { fps, bitrate_kbps, delivered, dropped, target_fps } => {
+                        let _ = app.emit(
+                            "screen:frame_stats",
+                            &FrameStatsEvent { fps, bitrate_kbps, delivered, dropped, target_fps },
+                        );
+                    }
                 }
             }
+            else => break,
         }
     }
     log::info!("[screen] Event loop ended");
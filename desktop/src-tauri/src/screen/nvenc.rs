@@ -7,7 +7,7 @@ use nvidia_video_codec_sdk::safe::{
 };
 use nvidia_video_codec_sdk::sys::nvEncodeAPI::*;
 
-use super::encoder::{FrameData, ScreenEncoder};
+use super::encoder::{CodecInfo, FrameData, H264Profile, H264Settings, ScreenEncoder, VideoCodec};
 
 pub struct NvencEncoder {
     // SAFETY: input_buffer and output_bitstream hold a reference to the Encoder
@@ -18,7 +18,13 @@ pub struct NvencEncoder {
     session: ManuallyDrop<Box<Session>>,
     width: u32,
     height: u32,
+    fps: u32,
+    h264: H264Settings,
     pitch: u32,
+    /// Frames since the last IDR; picture types are chosen here, not by NVENC.
+    frames_since_idr: u32,
+    /// Make the next frame an IDR (set by `force_keyframe`, and for the first).
+    force_idr: bool,
     _cuda_ctx: Arc<CudaContext>,
 }
 
@@ -34,7 +40,7 @@ impl Drop for NvencEncoder {
 }
 
 impl NvencEncoder {
-    pub fn try_new(width: u32, height: u32, bitrate_kbps: u32) -> Option<Self> {
+    pub fn try_new(width: u32, height: u32, bitrate_kbps: u32, fps: u32, h264: H264Settings) -> Option<Self> {
         // Initialize CUDA on device 0
         let cuda_ctx = CudaContext::new(0)
             .map_err(|e| eprintln!("[screen] NVENC: CUDA init failed: {:?}", e))
@@ -56,30 +62,39 @@ impl NvencEncoder {
             .ok()?;
 
         // Set CBR rate control, GOP length, and H.264 settings
-        let bitrate = bitrate_kbps * 1000;
+        let bitrate = bitrate_kbps.saturating_mul(1000);
         unsafe {
             let config = &mut preset_config.presetCfg;
-            config.gopLength = 60;
+            // NVENC's baseline has no FMO/ASO, so it doubles as Constrained Baseline
+            config.profileGUID = match h264.profile {
+                H264Profile::ConstrainedBaseline | H264Profile::Baseline => NV_ENC_H264_PROFILE_BASELINE_GUID,
+                H264Profile::Main => NV_ENC_H264_PROFILE_MAIN_GUID,
+                H264Profile::High => NV_ENC_H264_PROFILE_HIGH_GUID,
+            };
+            config.gopLength = fps; // one IDR per second
             config.frameIntervalP = 1;
 
             config.rcParams.rateControlMode = NV_ENC_PARAMS_RC_MODE::NV_ENC_PARAMS_RC_CBR;
             config.rcParams.averageBitRate = bitrate;
             config.rcParams.maxBitRate = bitrate;
-            config.rcParams.vbvBufferSize = bitrate / 60; // 1-frame VBV buffer
+            config.rcParams.vbvBufferSize = bitrate / fps; // 1-frame VBV buffer
             config.rcParams.vbvInitialDelay = config.rcParams.vbvBufferSize;
 
-            let h264 = &mut config.encodeCodecConfig.h264Config;
-            h264.idrPeriod = config.gopLength;
-            h264.set_repeatSPSPPS(1);
+            let h264_config = &mut config.encodeCodecConfig.h264Config;
+            h264_config.idrPeriod = config.gopLength;
+            h264_config.set_repeatSPSPPS(1);
+            // NV_ENC_LEVEL_H264_* values are level_idc
+            h264_config.level = h264.level_for(width, height, fps).idc() as u32;
         }
 
-        // Build initialization params
+        // Build initialization params. Picture type decision stays off: the
+        // safe API has no way to pass NV_ENC_PIC_FLAG_FORCEIDR, so `encode`
+        // picks IDR/P itself, which is what lets `force_keyframe` work.
         let mut init_params = EncoderInitParams::new(encode_guid, width, height);
         init_params
             .preset_guid(preset_guid)
             .tuning_info(tuning)
-            .framerate(60, 1)
-            .enable_picture_type_decision()
+            .framerate(fps, 1)
             .encode_config(&mut preset_config.presetCfg);
 
         // Start encoding session with ARGB format (= BGRA byte order on LE)
@@ -130,7 +145,11 @@ impl NvencEncoder {
             session: ManuallyDrop::new(session),
             width,
             height,
+            fps,
+            h264,
             pitch,
+            frames_since_idr: 0,
+            force_idr: true,
             _cuda_ctx: cuda_ctx,
         })
     }
@@ -173,11 +192,21 @@ impl ScreenEncoder for NvencEncoder {
             }
         }
 
+        // IDR when asked for and once per GOP (one second), P otherwise
+        let picture_type = if self.force_idr || self.frames_since_idr >= self.fps.max(1) {
+            self.force_idr = false;
+            self.frames_since_idr = 0;
+            NV_ENC_PIC_TYPE::NV_ENC_PIC_TYPE_IDR
+        } else {
+            NV_ENC_PIC_TYPE::NV_ENC_PIC_TYPE_P
+        };
+        self.frames_since_idr += 1;
+
         // Encode the picture
         self.session.encode_picture(
             &mut *self.input_buffer,
             &mut *self.output_bitstream,
-            EncodePictureParams::default(),
+            EncodePictureParams { picture_type, ..Default::default() },
         )?;
 
         // Read encoded H.264 bitstream
@@ -188,6 +217,36 @@ impl ScreenEncoder for NvencEncoder {
     }
 
     fn force_keyframe(&mut self) {
-        // No-op — gopLength=60 handles periodic IDR keyframes
+        self.force_idr = true;
+    }
+
+    fn set_bitrate(&mut self, kbps: u32) {
+        // nvEncReconfigureEncoder needs the raw encoder handle, which the safe
+        // session API keeps private — start a new session at the new rate.
+        // Keep the old one if that fails.
+        match NvencEncoder::try_new(self.width, self.height, kbps, self.fps, self.h264) {
+            Some(enc) => *self = enc,
+            None => eprintln!("[screen] NVENC: set bitrate {}kbps failed, keeping current session", kbps),
+        }
+    }
+
+    fn live_bitrate(&self) -> bool {
+        false
+    }
+
+    fn backend(&self) -> &'static str {
+        "nvenc"
+    }
+
+    fn is_hardware(&self) -> bool {
+        true
+    }
+
+    fn codec_info(&self) -> CodecInfo {
+        CodecInfo {
+            codec: VideoCodec::H264,
+            profile: Some(self.h264.profile.name()),
+            annex_b: true,
+        }
     }
 }
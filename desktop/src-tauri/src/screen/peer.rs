@@ -1,51 +1,190 @@
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 use std::sync::Arc;
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, watch};
 use webrtc::api::interceptor_registry::register_default_interceptors;
 use webrtc::api::media_engine::MediaEngine;
 use webrtc::api::APIBuilder;
 use webrtc::ice_transport::ice_candidate::RTCIceCandidateInit;
-use webrtc::ice_transport::ice_server::RTCIceServer;
 use webrtc::interceptor::registry::Registry;
 use webrtc::peer_connection::configuration::RTCConfiguration;
 use webrtc::peer_connection::sdp::session_description::RTCSessionDescription;
 use webrtc::peer_connection::RTCPeerConnection;
+use webrtc::rtcp::payload_feedbacks::full_intra_request::FullIntraRequest;
+use webrtc::rtcp::payload_feedbacks::picture_loss_indication::PictureLossIndication;
+use webrtc::rtcp::payload_feedbacks::receiver_estimated_maximum_bitrate::ReceiverEstimatedMaximumBitrate;
+use webrtc::rtcp::receiver_report::ReceiverReport;
 use webrtc::rtp_transceiver::rtp_codec::{RTCRtpCodecCapability, RTCRtpCodecParameters, RTPCodecType};
+use webrtc::rtp_transceiver::rtp_sender::RTCRtpSender;
+use webrtc::rtp_transceiver::rtp_transceiver_direction::RTCRtpTransceiverDirection;
 use webrtc::track::track_local::track_local_static_rtp::TrackLocalStaticRTP;
 use webrtc::track::track_local::track_local_static_sample::TrackLocalStaticSample;
 use webrtc::track::track_local::TrackLocal;
 
-use crate::voice::types::{IceCandidateIn, IceCandidateOut};
+use super::encoder::{H264Profile, H264Settings, VideoCodec, ENCODABLE_CODECS};
+use crate::voice::peer::{ice_servers, offered_codecs, peer_stats, require_codec};
+use crate::voice::types::{IceCandidateIn, IceCandidateOut, IceServerConfig, PeerStats};
+
+/// RTCP feedback from the SFU about the video track, read by the encode loop.
+#[derive(Default)]
+pub struct VideoFeedback {
+    /// Set when a viewer asks for a keyframe (PLI/FIR); cleared by the encode loop.
+    pub keyframe_request: AtomicBool,
+    /// Latest REMB bandwidth estimate in kbps (0 = none received yet).
+    pub remb_kbps: AtomicU32,
+    /// Latest receiver-report loss fraction, 0–255 (RFC 3550 `fraction_lost`).
+    pub fraction_lost: AtomicU32,
+}
+
+/// The negotiated video codec and the track its samples go to. Replaced when
+/// the SFU's offer doesn't include the codec the track was created with.
+#[derive(Clone)]
+pub struct VideoOutput {
+    pub codec: VideoCodec,
+    pub track: Arc<TrackLocalStaticSample>,
+    /// Profile/level the H.264 track advertises, for the encoder to match.
+    pub h264: H264Settings,
+}
+
+/// Payload type for Constrained Baseline when another H.264 profile is
+/// selected, so an SFU that only takes Constrained Baseline can still pick H.264.
+const H264_FALLBACK_PT: u8 = 103;
+
+/// RTP capability and payload type for each video codec, as registered in the
+/// SFU's screenME: H.264 (PT 102, which the SFU only takes as 42e01f), VP8
+/// (PT 96) and VP9 profile 0 (PT 98).
+fn video_codec_params(codec: VideoCodec, h264: H264Settings) -> RTCRtpCodecParameters {
+    let (mime_type, sdp_fmtp_line, payload_type) = match codec {
+        VideoCodec::H264 => (
+            "video/H264",
+            format!(
+                "level-asymmetry-allowed=1;packetization-mode=1;profile-level-id={}",
+                h264.profile_level_id()
+            ),
+            102,
+        ),
+        VideoCodec::Vp8 => ("video/VP8", String::new(), 96),
+        VideoCodec::Vp9 => ("video/VP9", "profile-id=0".to_string(), 98),
+    };
+    RTCRtpCodecParameters {
+        capability: RTCRtpCodecCapability {
+            mime_type: mime_type.to_string(),
+            clock_rate: 90000,
+            channels: 0,
+            sdp_fmtp_line,
+            ..Default::default()
+        },
+        payload_type,
+        ..Default::default()
+    }
+}
+
+/// Every video codec this build can encode, for registering on another
+/// connection that may carry the screen tracks. H.264 is listed at the
+/// default profile/level.
+pub fn video_codecs() -> Vec<RTCRtpCodecParameters> {
+    video_codecs_with(H264Settings::default())
+}
+
+fn video_codecs_with(h264: H264Settings) -> Vec<RTCRtpCodecParameters> {
+    let mut codecs: Vec<_> = ENCODABLE_CODECS
+        .iter()
+        .map(|&codec| video_codec_params(codec, h264))
+        .collect();
+    if h264.profile != H264Profile::ConstrainedBaseline {
+        let baseline = H264Settings { profile: H264Profile::ConstrainedBaseline, ..h264 };
+        codecs.insert(
+            1,
+            RTCRtpCodecParameters {
+                payload_type: H264_FALLBACK_PT,
+                ..video_codec_params(VideoCodec::H264, baseline)
+            },
+        );
+    }
+    codecs
+}
+
+/// Read RTCP from a video sender on another connection, passing its PLI/FIR
+/// to the encode loop. Bandwidth feedback stays with the screen peer's sender.
+pub fn forward_keyframe_requests(sender: Arc<RTCRtpSender>, feedback: Arc<VideoFeedback>) {
+    tokio::spawn(async move {
+        let mut buf = vec![0u8; 1500];
+        while let Ok((packets, _)) = sender.read(&mut buf).await {
+            if packets.iter().any(|p| {
+                let any = p.as_any();
+                any.is::<PictureLossIndication>() || any.is::<FullIntraRequest>()
+            }) {
+                feedback.keyframe_request.store(true, Ordering::Release);
+            }
+        }
+    });
+}
+
+fn new_video_track(codec: VideoCodec, h264: H264Settings) -> Arc<TrackLocalStaticSample> {
+    Arc::new(TrackLocalStaticSample::new(
+        video_codec_params(codec, h264).capability,
+        "video".to_string(),
+        "screen".to_string(),
+    ))
+}
+
+/// Whether any H.264 fmtp line in `sdp` has `profile`.
+fn has_h264_profile(sdp: &str, profile: H264Profile) -> bool {
+    sdp.lines()
+        .filter(|line| line.starts_with("a=fmtp:"))
+        .flat_map(|line| line.split([' ', ';']))
+        .filter_map(|param| param.strip_prefix("profile-level-id="))
+        .any(|id| H264Profile::from_profile_level_id(id) == Some(profile))
+}
+
+/// Video codecs listed in an SDP's rtpmap lines that this build can encode,
+/// in the offerer's order.
+fn offered_video_codecs(sdp: &str) -> Vec<VideoCodec> {
+    offered_codecs(sdp, "video")
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|name| VideoCodec::from_name(name))
+        .filter(|codec| codec.is_encodable())
+        .collect()
+}
+
+/// rtpmap names of `ENCODABLE_CODECS`, for `require_codec`.
+fn encodable_codec_names() -> Vec<&'static str> {
+    ENCODABLE_CODECS.iter().map(|codec| codec.sdp_name()).collect()
+}
 
 pub enum ScreenPeerEvent {
     IceCandidate(IceCandidateOut),
+    /// All local candidates have been gathered (end-of-candidates).
+    IceGatheringComplete,
     ConnectionState(String),
 }
 
 pub struct ScreenPeer {
     pc: Arc<RTCPeerConnection>,
-    pub video_track: Arc<TrackLocalStaticSample>,
-    pub audio_track: Arc<TrackLocalStaticRTP>,
+    video_sender: Arc<RTCRtpSender>,
+    video_tx: watch::Sender<VideoOutput>,
+    preferred_codec: VideoCodec,
+    h264: H264Settings,
+    /// None for a video-only share.
+    pub audio_track: Option<Arc<TrackLocalStaticRTP>>,
+    pub feedback: Arc<VideoFeedback>,
 }
 
 impl ScreenPeer {
-    pub async fn new() -> Result<(Self, mpsc::UnboundedReceiver<ScreenPeerEvent>), Box<dyn std::error::Error + Send + Sync>> {
+    pub async fn new(
+        ice_config: &[IceServerConfig],
+        preferred_codec: VideoCodec,
+        h264: H264Settings,
+        share_audio: bool,
+    ) -> Result<(Self, mpsc::UnboundedReceiver<ScreenPeerEvent>), Box<dyn std::error::Error + Send + Sync>> {
         let mut media_engine = MediaEngine::default();
 
-        // H.264 video codec — matches SFU's screenME (PT 102, 90kHz, Baseline)
-        media_engine.register_codec(
-            RTCRtpCodecParameters {
-                capability: RTCRtpCodecCapability {
-                    mime_type: "video/H264".to_string(),
-                    clock_rate: 90000,
-                    channels: 0,
-                    sdp_fmtp_line: "level-asymmetry-allowed=1;packetization-mode=1;profile-level-id=42e01f".to_string(),
-                    ..Default::default()
-                },
-                payload_type: 102,
-                ..Default::default()
-            },
-            RTPCodecType::Video,
-        )?;
+        // Video codecs — H.264 (PT 102, `h264` profile/level), VP8 (PT 96), VP9 (PT 98),
+        // all 90kHz; VP8/VP9 only with the vpx feature. The track starts with the
+        // preferred codec and follows the SFU's offer.
+        for codec in video_codecs_with(h264) {
+            media_engine.register_codec(codec, RTPCodecType::Video)?;
+        }
 
         // Opus audio codec — matches SFU's screen audio (PT 111, 48kHz, 2ch)
         media_engine.register_codec(
@@ -74,63 +213,77 @@ impl ScreenPeer {
             .build();
 
         let config = RTCConfiguration {
-            ice_servers: vec![RTCIceServer {
-                urls: vec!["stun:stun.l.google.com:19302".to_string()],
-                ..Default::default()
-            }],
+            ice_servers: ice_servers(ice_config),
             ..Default::default()
         };
 
         let pc = Arc::new(api.new_peer_connection(config).await?);
 
         // Create video track using TrackLocalStaticSample (handles RTP packetization)
-        let video_track = Arc::new(TrackLocalStaticSample::new(
-            RTCRtpCodecCapability {
-                mime_type: "video/H264".to_string(),
-                clock_rate: 90000,
-                channels: 0,
-                sdp_fmtp_line: "level-asymmetry-allowed=1;packetization-mode=1;profile-level-id=42e01f".to_string(),
-                ..Default::default()
-            },
-            "video".to_string(),
-            "screen".to_string(),
-        ));
+        let video_track = new_video_track(preferred_codec, h264);
 
         // Add video track as send-only
         let rtp_sender = pc
             .add_track(Arc::clone(&video_track) as Arc<dyn TrackLocal + Send + Sync>)
             .await?;
+        let video_sender = Arc::clone(&rtp_sender);
+        let (video_tx, _) = watch::channel(VideoOutput { codec: preferred_codec, track: video_track, h264 });
 
-        // Read RTCP packets (required by webrtc-rs to avoid blocking)
+        // Read RTCP packets (required by webrtc-rs to avoid blocking).
+        // PLI/FIR from the SFU (e.g. a viewer joining mid-stream) request an
+        // immediate keyframe instead of waiting for the periodic IDR.
+        // REMB and receiver-report loss drive the adaptive bitrate.
+        let feedback = Arc::new(VideoFeedback::default());
+        let rtcp_feedback = Arc::clone(&feedback);
         tokio::spawn(async move {
             let mut buf = vec![0u8; 1500];
-            while rtp_sender.read(&mut buf).await.is_ok() {}
+            while let Ok((packets, _)) = rtp_sender.read(&mut buf).await {
+                for packet in &packets {
+                    let any = packet.as_any();
+                    if any.is::<PictureLossIndication>() || any.is::<FullIntraRequest>() {
+                        rtcp_feedback.keyframe_request.store(true, Ordering::Release);
+                    } else if let Some(remb) = any.downcast_ref::<ReceiverEstimatedMaximumBitrate>() {
+                        rtcp_feedback
+                            .remb_kbps
+                            .store((remb.bitrate / 1000.0) as u32, Ordering::Relaxed);
+                    } else if let Some(rr) = any.downcast_ref::<ReceiverReport>() {
+                        if let Some(lost) = rr.reports.iter().map(|r| r.fraction_lost).max() {
+                            rtcp_feedback.fraction_lost.store(lost as u32, Ordering::Relaxed);
+                        }
+                    }
+                }
+            }
         });
 
-        // Create audio track using TrackLocalStaticRTP (we build RTP packets ourselves)
-        let audio_track = Arc::new(TrackLocalStaticRTP::new(
-            RTCRtpCodecCapability {
-                mime_type: "audio/opus".to_string(),
-                clock_rate: 48000,
-                channels: 2,
-                sdp_fmtp_line: "minptime=10;useinbandfec=1;usedtx=1;maxaveragebitrate=128000"
-                    .to_string(),
-                ..Default::default()
-            },
-            "audio".to_string(),
-            "screen".to_string(),
-        ));
+        let audio_track = if share_audio {
+            // Create audio track using TrackLocalStaticRTP (we build RTP packets ourselves)
+            let audio_track = Arc::new(TrackLocalStaticRTP::new(
+                RTCRtpCodecCapability {
+                    mime_type: "audio/opus".to_string(),
+                    clock_rate: 48000,
+                    channels: 2,
+                    sdp_fmtp_line: "minptime=10;useinbandfec=1;usedtx=1;maxaveragebitrate=128000"
+                        .to_string(),
+                    ..Default::default()
+                },
+                "audio".to_string(),
+                "screen".to_string(),
+            ));
 
-        // Add audio track as send-only
-        let audio_rtp_sender = pc
-            .add_track(Arc::clone(&audio_track) as Arc<dyn TrackLocal + Send + Sync>)
-            .await?;
+            // Add audio track as send-only
+            let audio_rtp_sender = pc
+                .add_track(Arc::clone(&audio_track) as Arc<dyn TrackLocal + Send + Sync>)
+                .await?;
 
-        // Drain RTCP for audio
-        tokio::spawn(async move {
-            let mut buf = vec![0u8; 1500];
-            while audio_rtp_sender.read(&mut buf).await.is_ok() {}
-        });
+            // Drain RTCP for audio
+            tokio::spawn(async move {
+                let mut buf = vec![0u8; 1500];
+                while audio_rtp_sender.read(&mut buf).await.is_ok() {}
+            });
+            Some(audio_track)
+        } else {
+            None
+        };
 
         // Event channel
         let (event_tx, event_rx) = mpsc::unbounded_channel();
@@ -147,6 +300,8 @@ impl ScreenPeer {
                         sdp_mid: json.sdp_mid,
                         sdp_mline_index: json.sdp_mline_index,
                     }));
+                } else {
+                    let _ = tx.send(ScreenPeerEvent::IceGatheringComplete);
                 }
             })
         }));
@@ -162,12 +317,31 @@ impl ScreenPeer {
             })
         }));
 
-        Ok((Self { pc, video_track, audio_track }, event_rx))
+        Ok((
+            Self { pc, video_sender, video_tx, preferred_codec, h264, audio_track, feedback },
+            event_rx,
+        ))
+    }
+
+    /// Current codec/track for the encode loop; updates if the offer forces a switch.
+    pub fn video_output(&self) -> watch::Receiver<VideoOutput> {
+        self.video_tx.subscribe()
     }
 
     pub async fn handle_offer(&self, sdp: &str) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
         let offer = RTCSessionDescription::offer(sdp.to_string())?;
         self.pc.set_remote_description(offer).await?;
+        require_codec(sdp, "video", &encodable_codec_names())?;
+        // Shared audio is optional, but if offered it has to be Opus
+        if offered_codecs(sdp, "audio").is_some() {
+            if self.audio_track.is_some() {
+                require_codec(sdp, "audio", &["opus"])?;
+            } else {
+                self.decline_audio().await;
+            }
+        }
+
+        self.follow_video_codec(sdp).await?;
 
         let answer = self.pc.create_answer(None).await?;
         self.pc.set_local_description(answer).await?;
@@ -181,6 +355,75 @@ impl ScreenPeer {
         Ok(local_desc.sdp)
     }
 
+    /// Answer the offer's audio m-line as inactive on a video-only share,
+    /// rather than the sendonly transceiver webrtc-rs makes up for it with
+    /// no track behind it.
+    async fn decline_audio(&self) {
+        for transceiver in self.pc.get_transceivers().await {
+            if transceiver.kind() == RTPCodecType::Audio {
+                transceiver.set_direction(RTCRtpTransceiverDirection::Inactive).await;
+            }
+        }
+    }
+
+    /// Generate an offer for the share and set it as the local description,
+    /// for servers that expect the client to offer. The answer goes to
+    /// `handle_answer`.
+    pub async fn create_offer(&self) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        let offer = self.pc.create_offer(None).await?;
+        self.pc.set_local_description(offer).await?;
+
+        let local_desc = self
+            .pc
+            .local_description()
+            .await
+            .ok_or("no local description")?;
+
+        Ok(local_desc.sdp)
+    }
+
+    /// Apply the server's answer to our offer, switching the video track to
+    /// the codec it accepted.
+    pub async fn handle_answer(&self, sdp: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let answer = RTCSessionDescription::answer(sdp.to_string())?;
+        self.pc.set_remote_description(answer).await?;
+        require_codec(sdp, "video", &encodable_codec_names())?;
+        self.follow_video_codec(sdp).await
+    }
+
+    /// Use the preferred codec if the remote SDP lists it, otherwise its first
+    /// choice, replacing the video track if that's not the current codec.
+    /// H.264 drops to Constrained Baseline if the selected profile isn't listed.
+    async fn follow_video_codec(&self, sdp: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let offered = offered_video_codecs(sdp);
+        let codec = if offered.contains(&self.preferred_codec) {
+            Some(self.preferred_codec)
+        } else {
+            offered.first().copied()
+        };
+        let Some(codec) = codec else {
+            return Ok(());
+        };
+        let mut h264 = self.h264;
+        if codec == VideoCodec::H264 && !has_h264_profile(sdp, h264.profile) {
+            log::info!(
+                "[screen] SFU doesn't take H.264 {}, using constrained-baseline",
+                h264.profile.name()
+            );
+            h264.profile = H264Profile::ConstrainedBaseline;
+        }
+        let current = self.video_tx.borrow().clone();
+        if codec != current.codec || h264 != current.h264 {
+            log::info!("[screen] Switching video codec to {:?}", codec);
+            let track = new_video_track(codec, h264);
+            self.video_sender
+                .replace_track(Some(Arc::clone(&track) as Arc<dyn TrackLocal + Send + Sync>))
+                .await?;
+            self.video_tx.send_replace(VideoOutput { codec, track, h264 });
+        }
+        Ok(())
+    }
+
     pub async fn handle_ice(&self, candidate: IceCandidateIn) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         let init = RTCIceCandidateInit {
             candidate: candidate.candidate,
@@ -192,6 +435,16 @@ impl ScreenPeer {
         Ok(())
     }
 
+    /// RTT, loss and byte counts for this connection.
+    pub async fn stats(&self) -> PeerStats {
+        peer_stats(&self.pc).await
+    }
+
+    /// ICE gathering state ("new", "gathering", "complete").
+    pub fn ice_gathering_state(&self) -> String {
+        self.pc.ice_gathering_state().to_string()
+    }
+
     pub async fn close(self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         self.pc.close().await?;
         Ok(())
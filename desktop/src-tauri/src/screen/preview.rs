@@ -1,10 +1,20 @@
 use std::sync::Arc;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
 use tokio::io::AsyncWriteExt;
 use tokio::net::TcpListener;
 use tokio::sync::watch;
 
 /// MJPEG HTTP server that streams preview frames on `http://127.0.0.1:{port}/preview`.
+/// `/preview/full` streams full-resolution frames while that's enabled, `/`
+/// serves a bare page showing the stream, for opening in a browser while debugging,
+/// and `/health` answers 200 so the frontend can poll readiness. Anything else
+/// is a 404. Every response closes the connection; there is no keep-alive.
+///
+/// `?mode=sse` on either preview path sends the same frames as Server-Sent
+/// Events instead (`event: frame`, base64 JPEG in `data`), for WebViews that
+/// throttle multipart streams.
 ///
 /// The browser renders `multipart/x-mixed-replace` natively — hardware-accelerated
 /// image decoding, zero JS per frame, no IPC overhead.
@@ -12,18 +22,57 @@ pub struct MjpegServer {
     port: u16,
     task: tokio::task::JoinHandle<()>,
     stop: Arc<AtomicBool>,
+    clients: Arc<PreviewClients>,
+}
+
+/// Clients currently streaming each preview path, so the capture loop can
+/// skip encoding JPEGs nobody is watching. Page and health requests don't count.
+#[derive(Default)]
+pub struct PreviewClients {
+    pub preview: AtomicUsize,
+    pub fullres: AtomicUsize,
+}
+
+/// Counts a streaming client for as long as its connection lives.
+struct ClientGuard<'a>(&'a AtomicUsize);
+
+impl<'a> ClientGuard<'a> {
+    fn new(count: &'a AtomicUsize) -> Self {
+        count.fetch_add(1, Ordering::Relaxed);
+        Self(count)
+    }
+}
+
+impl Drop for ClientGuard<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
 }
 
 impl MjpegServer {
-    /// Start the MJPEG server on a random available port.
-    /// Returns immediately; the server runs in a background tokio task.
-    pub async fn start(rx: watch::Receiver<Option<Vec<u8>>>) -> Result<Self, String> {
-        let listener = TcpListener::bind("127.0.0.1:0")
-            .await
-            .map_err(|e| format!("MJPEG bind failed: {}", e))?;
+    /// Start the MJPEG server on `preferred_port`, or a random available port
+    /// if that's unset or stays taken. Returns immediately; the server runs in
+    /// a background tokio task.
+    pub async fn start(
+        rx: watch::Receiver<Option<Vec<u8>>>,
+        fullres_rx: watch::Receiver<Option<Vec<u8>>>,
+        preferred_port: Option<u16>,
+    ) -> Result<Self, String> {
+        let listener = match preferred_port {
+            Some(port) => bind_preferred(port).await,
+            None => None,
+        };
+        let listener = match listener {
+            Some(listener) => listener,
+            None => TcpListener::bind("127.0.0.1:0")
+                .await
+                .map_err(|e| format!("MJPEG bind failed: {}", e))?,
+        };
         let port = listener.local_addr().map_err(|e| e.to_string())?.port();
         let stop = Arc::new(AtomicBool::new(false));
         let stop_clone = stop.clone();
+        let clients = Arc::new(PreviewClients::default());
+        let conn_clients = Arc::clone(&clients);
 
         eprintln!("[screen] MJPEG server listening on 127.0.0.1:{}", port);
 
@@ -34,9 +83,11 @@ impl MjpegServer {
                         match result {
                             Ok((stream, _addr)) => {
                                 let rx = rx.clone();
+                                let fullres_rx = fullres_rx.clone();
                                 let stop = stop_clone.clone();
+                                let clients = Arc::clone(&conn_clients);
                                 tokio::spawn(async move {
-                                    if let Err(e) = handle_connection(stream, rx, stop).await {
+                                    if let Err(e) = handle_connection(stream, rx, fullres_rx, stop, clients).await {
                                         // Client disconnected — normal
                                         let _ = e;
                                     }
@@ -52,13 +103,18 @@ impl MjpegServer {
             }
         });
 
-        Ok(Self { port, task, stop })
+        Ok(Self { port, task, stop, clients })
     }
 
     pub fn port(&self) -> u16 {
         self.port
     }
 
+    /// Live client counts, for the capture loop.
+    pub fn clients(&self) -> Arc<PreviewClients> {
+        Arc::clone(&self.clients)
+    }
+
     pub fn stop(self) {
         self.stop.store(true, Ordering::Release);
         self.task.abort();
@@ -66,26 +122,96 @@ impl MjpegServer {
     }
 }
 
+/// Bind attempts on the preferred port before falling back to a random one;
+/// the last session's socket may still be closing.
+const PREFERRED_PORT_ATTEMPTS: u32 = 3;
+const PREFERRED_PORT_RETRY: std::time::Duration = std::time::Duration::from_millis(100);
+
+/// Bind 127.0.0.1:`port`, retrying briefly while it's in use.
+async fn bind_preferred(port: u16) -> Option<TcpListener> {
+    for attempt in 1..=PREFERRED_PORT_ATTEMPTS {
+        match TcpListener::bind(("127.0.0.1", port)).await {
+            Ok(listener) => return Some(listener),
+            Err(e) if e.kind() == std::io::ErrorKind::AddrInUse && attempt < PREFERRED_PORT_ATTEMPTS => {
+                tokio::time::sleep(PREFERRED_PORT_RETRY).await;
+            }
+            Err(e) => {
+                eprintln!("[screen] MJPEG port {} unavailable ({}), using a random port", port, e);
+                return None;
+            }
+        }
+    }
+    None
+}
+
 const BOUNDARY: &str = "frame";
+/// Request headers bigger than this are rejected.
+const MAX_REQUEST_BYTES: usize = 8192;
+/// Clients that haven't finished sending headers by then are dropped.
+const REQUEST_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+const INDEX_HTML: &str = "<!DOCTYPE html>\n\
+<html><head><title>Screen preview</title></head>\n\
+<body style=\"margin:0;background:#111;display:flex;justify-content:center;align-items:center;height:100vh\">\n\
+<img src=\"/preview\" style=\"max-width:100%;max-height:100%\">\n\
+</body></html>\n";
 
 async fn handle_connection(
     mut stream: tokio::net::TcpStream,
-    mut rx: watch::Receiver<Option<Vec<u8>>>,
+    rx: watch::Receiver<Option<Vec<u8>>>,
+    fullres_rx: watch::Receiver<Option<Vec<u8>>>,
     stop: Arc<AtomicBool>,
+    clients: Arc<PreviewClients>,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    // Read the HTTP request (we don't care about the contents, just consume it)
-    let mut req_buf = vec![0u8; 4096];
-    let _ = stream.read(&mut req_buf).await?;
+    let Some(Request { method, path, query }) =
+        tokio::time::timeout(REQUEST_TIMEOUT, read_request(&mut stream)).await??
+    else {
+        return write_status(&mut stream, "400 Bad Request").await;
+    };
+
+    if method != "GET" {
+        return write_status(&mut stream, "405 Method Not Allowed").await;
+    }
+    if path == "/health" {
+        return write_status(&mut stream, "200 OK").await;
+    }
+    if path == "/" {
+        let response = format!(
+            "HTTP/1.1 200 OK\r\n\
+             Content-Type: text/html; charset=utf-8\r\n\
+             Content-Length: {}\r\n\
+             Cache-Control: no-cache, no-store\r\n\
+             Connection: close\r\n\
+             \r\n{}",
+            INDEX_HTML.len(),
+            INDEX_HTML
+        );
+        stream.write_all(response.as_bytes()).await?;
+        return Ok(());
+    }
+    let (mut rx, count) = match path.as_str() {
+        "/preview" => (rx, &clients.preview),
+        "/preview/full" => (fullres_rx, &clients.fullres),
+        _ => return write_status(&mut stream, "404 Not Found").await,
+    };
+    let _watching = ClientGuard::new(count);
+
+    let sse = query.split('&').any(|param| param == "mode=sse");
+    let content_type = if sse {
+        "text/event-stream".to_string()
+    } else {
+        format!("multipart/x-mixed-replace; boundary={}", BOUNDARY)
+    };
 
     // Send HTTP response headers
     let header = format!(
         "HTTP/1.1 200 OK\r\n\
-         Content-Type: multipart/x-mixed-replace; boundary={}\r\n\
+         Content-Type: {}\r\n\
          Cache-Control: no-cache, no-store\r\n\
          Connection: close\r\n\
          Access-Control-Allow-Origin: *\r\n\
          \r\n",
-        BOUNDARY
+        content_type
     );
     stream.write_all(header.as_bytes()).await?;
 
@@ -106,6 +232,12 @@ async fn handle_connection(
             }
         };
 
+        if sse {
+            let event = format!("event: frame\ndata: {}\n\n", STANDARD.encode(&jpeg));
+            stream.write_all(event.as_bytes()).await?;
+            continue;
+        }
+
         let part = format!(
             "--{}\r\nContent-Type: image/jpeg\r\nContent-Length: {}\r\n\r\n",
             BOUNDARY,
@@ -119,4 +251,58 @@ async fn handle_connection(
     Ok(())
 }
 
+/// Send a bodyless response with `status` and close.
+async fn write_status(
+    stream: &mut tokio::net::TcpStream,
+    status: &str,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Length: 0\r\nCache-Control: no-cache, no-store\r\nConnection: close\r\nAccess-Control-Allow-Origin: *\r\n\r\n",
+        status
+    );
+    stream.write_all(response.as_bytes()).await?;
+    Ok(())
+}
+
+/// The parts of a request line the server routes on.
+struct Request {
+    method: String,
+    path: String,
+    /// Everything after `?`, empty if there's none.
+    query: String,
+}
+
+/// Read the request headers (up to the blank line) and parse the request
+/// line. The request may arrive in several reads; GET carries no body, so
+/// nothing past the headers is read. None if the request is malformed, too
+/// large, or the client hung up first.
+async fn read_request(
+    stream: &mut tokio::net::TcpStream,
+) -> Result<Option<Request>, std::io::Error> {
+    let mut buf = Vec::with_capacity(1024);
+    let mut chunk = [0u8; 1024];
+    while !buf.windows(4).any(|w| w == b"\r\n\r\n") {
+        if buf.len() >= MAX_REQUEST_BYTES {
+            return Ok(None);
+        }
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            return Ok(None);
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    }
+
+    let request = String::from_utf8_lossy(&buf);
+    let mut request_line = request.lines().next().unwrap_or("").split_whitespace();
+    let (Some(method), Some(target)) = (request_line.next(), request_line.next()) else {
+        return Ok(None);
+    };
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    Ok(Some(Request {
+        method: method.to_string(),
+        path: path.to_string(),
+        query: query.to_string(),
+    }))
+}
+
 use tokio::io::AsyncReadExt;
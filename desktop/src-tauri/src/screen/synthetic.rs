@@ -0,0 +1,105 @@
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+#[cfg(target_os = "linux")]
+use super::capture::SourceType;
+use super::capture::{PortalResult, PortalStream};
+use super::encoder::FrameData;
+use super::frame_queue::{Closed, FrameSender};
+
+/// Set to "1" (1280x720) or "WxH" to make `screen_start` share a generated
+/// test pattern instead of going through the portal and PipeWire.
+const ENV_VAR: &str = "LEFAUXPAIN_SYNTHETIC_SCREEN";
+const DEFAULT_SIZE: (u32, u32) = (1280, 720);
+
+/// Stand-in for the portal result when the synthetic source is requested.
+pub fn source_from_env() -> Option<PortalResult> {
+    let value = std::env::var(ENV_VAR).ok()?;
+    let (width, height) = value
+        .split_once('x')
+        .and_then(|(w, h)| Some((w.parse::<u32>().ok()?, h.parse::<u32>().ok()?)))
+        .filter(|&(w, h)| w >= 16 && h >= 16)
+        .unwrap_or(DEFAULT_SIZE);
+    // PortalResult carries a PipeWire fd; nothing reads this one
+    #[cfg(target_os = "linux")]
+    let fd = std::fs::File::open("/dev/null").ok()?.into();
+
+    eprintln!("[screen] Using synthetic {}x{} source", width, height);
+    let (width, height) = (width & !1, height & !1);
+    Some(PortalResult {
+        node_id: 0,
+        width,
+        height,
+        #[cfg(target_os = "linux")]
+        fd,
+        streams: vec![PortalStream { node_id: 0, width, height }],
+        stream_index: 0,
+        #[cfg(target_os = "linux")]
+        source_type: Some(SourceType::Monitor),
+        restore_token: None,
+        synthetic: true,
+        #[cfg(windows)]
+        draw_cursor: false,
+        #[cfg(windows)]
+        device_name: String::new(),
+    })
+}
+
+/// Feed a moving pattern (scrolling gradient plus a sweeping bar) into the
+/// encode loop at `fps` until `stop` is set. Paused shares send nothing, like
+/// the PipeWire loop.
+pub fn frame_loop(
+    width: u32,
+    height: u32,
+    fps: u32,
+    frame_tx: FrameSender,
+    dropped: Arc<AtomicU32>,
+    paused: Arc<AtomicBool>,
+    stop: Arc<AtomicBool>,
+) {
+    let interval = Duration::from_micros(1_000_000 / fps.max(1) as u64);
+    let w = width as usize;
+    let mut frame_no: usize = 0;
+    let mut next = Instant::now();
+
+    while !stop.load(Ordering::Relaxed) {
+        if !paused.load(Ordering::Relaxed) {
+            let shift = frame_no * 4;
+            let bar = (frame_no * 8) % w;
+            let mut data = vec![0u8; w * height as usize * 4];
+            for (i, px) in data.chunks_exact_mut(4).enumerate() {
+                let (x, y) = (i % w, i / w);
+                if x.abs_diff(bar) < 16 {
+                    px.copy_from_slice(&[255, 255, 255, 255]);
+                } else {
+                    px.copy_from_slice(&[
+                        ((x + shift) % 256) as u8,
+                        (y % 256) as u8,
+                        ((x + y + shift / 2) % 256) as u8,
+                        255,
+                    ]);
+                }
+            }
+
+            // Same back-pressure as PipeWire: drop the oldest queued frame if the encoder is behind
+            let frame = FrameData { data, width, height, is_bgra: true };
+            match frame_tx.send(frame) {
+                Err(Closed) => break,
+                Ok(Some(_)) => {
+                    dropped.fetch_add(1, Ordering::Relaxed);
+                }
+                Ok(None) => {}
+            }
+            frame_no += 1;
+        }
+
+        next += interval;
+        match next.checked_duration_since(Instant::now()) {
+            Some(wait) => std::thread::sleep(wait),
+            None => next = Instant::now(),
+        }
+    }
+
+    eprintln!("[screen] Synthetic source stopped");
+}
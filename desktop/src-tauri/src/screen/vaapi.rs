@@ -11,7 +11,8 @@ use cros_codecs::{BlockingMode, Fourcc, FrameLayout, PlaneLayout, Resolution};
 use cros_codecs::libva::constants::VA_RT_FORMAT_YUV420;
 use cros_codecs::libva::{Display, Image, Surface, UsageHint, VAEntrypoint, VAProfile};
 
-use super::encoder::{FrameData, ScreenEncoder};
+use super::colorconv::to_nv12;
+use super::encoder::{CodecInfo, FrameData, H264Profile, H264Settings, ScreenEncoder, VideoCodec};
 
 type H264Encoder = StatelessEncoder<Surface<()>, VaapiBackend<(), Surface<()>>>;
 
@@ -20,6 +21,8 @@ pub struct VaapiEncoder {
     encoder: H264Encoder,
     width: u32,
     height: u32,
+    fps: u32,
+    profile: H264Profile,
     frame_count: u64,
     force_next_idr: bool,
     nv12_buf: Vec<u8>,
@@ -28,15 +31,33 @@ pub struct VaapiEncoder {
 }
 
 impl VaapiEncoder {
-    pub fn try_new(width: u32, height: u32, bitrate_kbps: u32) -> Option<Self> {
+    pub fn try_new(width: u32, height: u32, bitrate_kbps: u32, fps: u32, h264: H264Settings) -> Option<Self> {
         let display = Display::open().or_else(|| {
             eprintln!("[screen] VAAPI: no display found");
             None
         })?;
 
-        // Check for H.264 encoding support
+        // cros-codecs encodes Profile::Baseline as VA's constrained baseline
+        let (profile, va_profile) = match h264.profile {
+            H264Profile::ConstrainedBaseline | H264Profile::Baseline => {
+                (Profile::Baseline, VAProfile::VAProfileH264ConstrainedBaseline)
+            }
+            H264Profile::Main => (Profile::Main, VAProfile::VAProfileH264Main),
+            H264Profile::High => (Profile::High, VAProfile::VAProfileH264High),
+        };
+        let level = match h264.level_for(width, height, fps).idc() {
+            31 => Level::L3_1,
+            40 => Level::L4,
+            41 => Level::L4_1,
+            42 => Level::L4_2,
+            50 => Level::L5,
+            51 => Level::L5_1,
+            _ => Level::L5_2,
+        };
+
+        // Check for H.264 encoding support in the requested profile
         let entrypoints = display
-            .query_config_entrypoints(VAProfile::VAProfileH264Main)
+            .query_config_entrypoints(va_profile)
             .map_err(|e| {
                 eprintln!("[screen] VAAPI: failed to query entrypoints: {:?}", e);
                 e
@@ -49,18 +70,18 @@ impl VaapiEncoder {
             || entrypoints.contains(&VAEntrypoint::VAEntrypointEncSlice);
 
         if !has_enc {
-            eprintln!("[screen] VAAPI: no H.264 encode entrypoint");
+            eprintln!("[screen] VAAPI: no H.264 {} encode entrypoint", h264.profile.name());
             return None;
         }
 
         let config = EncoderConfig {
-            profile: Profile::Main,
+            profile,
             resolution: Resolution { width, height },
-            level: Level::L4,
+            level,
             pred_structure: PredictionStructure::LowDelay { limit: 2048 },
             initial_tunings: Tunings {
                 rate_control: RateControl::ConstantBitrate(bitrate_kbps as u64 * 1000),
-                framerate: 60,
+                framerate: fps,
                 min_quality: 1,
                 max_quality: 51,
             },
@@ -129,6 +150,8 @@ impl VaapiEncoder {
             encoder,
             width,
             height,
+            fps,
+            profile: h264.profile,
             frame_count: 0,
             force_next_idr: true, // first frame is always IDR
             nv12_buf: vec![0u8; nv12_size],
@@ -187,11 +210,7 @@ impl ScreenEncoder for VaapiEncoder {
         let h = frame.height as usize;
 
         // Convert BGRA/RGBA to NV12 into reusable buffer
-        if frame.is_bgra {
-            bgra_to_nv12(&frame.data, &mut self.nv12_buf, w, h);
-        } else {
-            rgba_to_nv12(&frame.data, &mut self.nv12_buf, w, h);
-        }
+        to_nv12(&frame.data, &mut self.nv12_buf, w, h, frame.is_bgra);
 
         // Create a fresh VA surface
         let surfaces = self.display.create_surfaces(
@@ -236,95 +255,32 @@ impl ScreenEncoder for VaapiEncoder {
     fn force_keyframe(&mut self) {
         self.force_next_idr = true;
     }
-}
 
-/// Convert BGRA pixels to NV12 using fixed-point BT.601 coefficients.
-/// NV12 layout: Y plane (w*h bytes) followed by interleaved UV plane (w*h/2 bytes).
-fn bgra_to_nv12(bgra: &[u8], nv12: &mut [u8], width: usize, height: usize) {
-    let y_size = width * height;
-    let (y_plane, uv_plane) = nv12.split_at_mut(y_size);
-
-    // Y plane: one sample per pixel
-    for row in 0..height {
-        let row_off = row * width;
-        for col in 0..width {
-            let px = (row_off + col) * 4;
-            let b = bgra[px] as i32;
-            let g = bgra[px + 1] as i32;
-            let r = bgra[px + 2] as i32;
-            y_plane[row_off + col] = (((66 * r + 129 * g + 25 * b + 128) >> 8) + 16) as u8;
+    fn set_bitrate(&mut self, kbps: u32) {
+        let tunings = Tunings {
+            rate_control: RateControl::ConstantBitrate(kbps as u64 * 1000),
+            framerate: self.fps,
+            min_quality: 1,
+            max_quality: 51,
+        };
+        if let Err(e) = self.encoder.tune(tunings) {
+            eprintln!("[screen] VAAPI: set bitrate {}kbps failed: {:?}", kbps, e);
         }
     }
 
-    // UV plane: 2x2 subsampled, interleaved U,V pairs
-    let uv_width = width / 2;
-    let uv_height = height / 2;
-    for row in 0..uv_height {
-        let src_row = row * 2;
-        for col in 0..uv_width {
-            let src_col = col * 2;
-            let mut r_sum = 0i32;
-            let mut g_sum = 0i32;
-            let mut b_sum = 0i32;
-            for dy in 0..2 {
-                for dx in 0..2 {
-                    let px = ((src_row + dy) * width + (src_col + dx)) * 4;
-                    b_sum += bgra[px] as i32;
-                    g_sum += bgra[px + 1] as i32;
-                    r_sum += bgra[px + 2] as i32;
-                }
-            }
-            let r = r_sum >> 2;
-            let g = g_sum >> 2;
-            let b = b_sum >> 2;
-            let uv_off = (row * uv_width + col) * 2;
-            // U
-            uv_plane[uv_off] = (((-38 * r - 74 * g + 112 * b + 128) >> 8) + 128) as u8;
-            // V
-            uv_plane[uv_off + 1] = (((112 * r - 94 * g - 18 * b + 128) >> 8) + 128) as u8;
-        }
+    fn backend(&self) -> &'static str {
+        "vaapi"
     }
-}
 
-/// Convert RGBA pixels to NV12 using fixed-point BT.601 coefficients.
-fn rgba_to_nv12(rgba: &[u8], nv12: &mut [u8], width: usize, height: usize) {
-    let y_size = width * height;
-    let (y_plane, uv_plane) = nv12.split_at_mut(y_size);
-
-    for row in 0..height {
-        let row_off = row * width;
-        for col in 0..width {
-            let px = (row_off + col) * 4;
-            let r = rgba[px] as i32;
-            let g = rgba[px + 1] as i32;
-            let b = rgba[px + 2] as i32;
-            y_plane[row_off + col] = (((66 * r + 129 * g + 25 * b + 128) >> 8) + 16) as u8;
-        }
+    fn is_hardware(&self) -> bool {
+        true
     }
 
-    let uv_width = width / 2;
-    let uv_height = height / 2;
-    for row in 0..uv_height {
-        let src_row = row * 2;
-        for col in 0..uv_width {
-            let src_col = col * 2;
-            let mut r_sum = 0i32;
-            let mut g_sum = 0i32;
-            let mut b_sum = 0i32;
-            for dy in 0..2 {
-                for dx in 0..2 {
-                    let px = ((src_row + dy) * width + (src_col + dx)) * 4;
-                    r_sum += rgba[px] as i32;
-                    g_sum += rgba[px + 1] as i32;
-                    b_sum += rgba[px + 2] as i32;
-                }
-            }
-            let r = r_sum >> 2;
-            let g = g_sum >> 2;
-            let b = b_sum >> 2;
-            let uv_off = (row * uv_width + col) * 2;
-            uv_plane[uv_off] = (((-38 * r - 74 * g + 112 * b + 128) >> 8) + 128) as u8;
-            uv_plane[uv_off + 1] = (((112 * r - 94 * g - 18 * b + 128) >> 8) + 128) as u8;
+    fn codec_info(&self) -> CodecInfo {
+        CodecInfo {
+            codec: VideoCodec::H264,
+            profile: Some(self.profile.name()),
+            annex_b: true,
         }
     }
 }
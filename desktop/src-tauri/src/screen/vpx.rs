@@ -0,0 +1,120 @@
+use std::time::Instant;
+
+use vpx_encode::{Config, Encoder, VideoCodecId};
+
+use super::colorconv::to_i420;
+use super::encoder::{CodecInfo, FrameData, ScreenEncoder, VideoCodec};
+
+/// VP8/VP9 encoder backed by libvpx.
+pub struct VpxEncoder {
+    encoder: Encoder,
+    codec: VideoCodec,
+    width: u32,
+    height: u32,
+    bitrate_kbps: u32,
+    start: Instant,
+    force_next_keyframe: bool,
+}
+
+impl VpxEncoder {
+    pub fn try_new(
+        codec: VideoCodec,
+        width: u32,
+        height: u32,
+        bitrate_kbps: u32,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let encoder = open(codec, width, height, bitrate_kbps)?;
+        eprintln!(
+            "[screen] libvpx {:?} encoder initialized ({}x{}, {}kbps)",
+            codec, width, height, bitrate_kbps
+        );
+        Ok(Self {
+            encoder,
+            codec,
+            width,
+            height,
+            bitrate_kbps,
+            start: Instant::now(),
+            force_next_keyframe: false,
+        })
+    }
+
+    /// vpx-encode exposes neither encode flags nor reconfiguration, so keyframes
+    /// and bitrate changes start a fresh encoder — its first frame is always a keyframe.
+    fn reopen(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.encoder = open(self.codec, self.width, self.height, self.bitrate_kbps)?;
+        Ok(())
+    }
+}
+
+fn open(
+    codec: VideoCodec,
+    width: u32,
+    height: u32,
+    bitrate_kbps: u32,
+) -> Result<Encoder, Box<dyn std::error::Error>> {
+    let codec = match codec {
+        VideoCodec::Vp9 => VideoCodecId::VP9,
+        _ => VideoCodecId::VP8,
+    };
+    Encoder::new(Config {
+        width,
+        height,
+        timebase: [1, 1000], // pts in milliseconds
+        bitrate: bitrate_kbps,
+        codec,
+    })
+    .map_err(|e| format!("libvpx init failed: {:?}", e).into())
+}
+
+impl ScreenEncoder for VpxEncoder {
+    fn encode(&mut self, frame: &FrameData) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        if self.force_next_keyframe {
+            self.force_next_keyframe = false;
+            self.reopen()?;
+        }
+
+        let fw = frame.width as usize;
+        let fh = frame.height as usize;
+        let i420 = to_i420(&frame.data, fw, fh, frame.is_bgra);
+
+        let pts = self.start.elapsed().as_millis() as i64;
+        let packets = self
+            .encoder
+            .encode(pts, &i420)
+            .map_err(|e| format!("libvpx encode failed: {:?}", e))?;
+
+        let mut output = Vec::new();
+        for packet in packets {
+            output.extend_from_slice(packet.data);
+        }
+        Ok(output)
+    }
+
+    fn force_keyframe(&mut self) {
+        self.force_next_keyframe = true;
+    }
+
+    fn set_bitrate(&mut self, kbps: u32) {
+        self.bitrate_kbps = kbps;
+        if let Err(e) = self.reopen() {
+            eprintln!("[screen] libvpx: set bitrate {}kbps failed: {:?}", kbps, e);
+        }
+    }
+
+    fn live_bitrate(&self) -> bool {
+        false
+    }
+
+    fn backend(&self) -> &'static str {
+        "libvpx"
+    }
+
+    fn codec_info(&self) -> CodecInfo {
+        CodecInfo {
+            codec: self.codec,
+            profile: (self.codec == VideoCodec::Vp9).then_some("0"),
+            annex_b: false,
+        }
+    }
+}
@@ -1,4 +1,5 @@
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicI32, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -9,17 +10,70 @@ use tokio::sync::mpsc;
 use webrtc::track::track_local::track_local_static_rtp::TrackLocalStaticRTP;
 use webrtc::track::track_local::TrackLocalWriter;
 
+use super::audio_playback::adapt_channels;
 use super::resampler::AudioResampler;
-use super::speaking::SpeakingDetector;
+use super::gain::GainRamp;
+use super::soundboard::{mix_clips, Clip};
+use super::speaking::{SpeakingDetector, SpeakingParams};
+use super::types::AudioDeviceInfo;
 
 const OPUS_SAMPLE_RATE: u32 = 48000;
 const OPUS_CHANNELS: usize = 2;
 const OPUS_FRAME_MS: usize = 20;
-const OPUS_FRAME_SAMPLES: usize = (OPUS_SAMPLE_RATE as usize * OPUS_FRAME_MS) / 1000; // 960
+/// Frame durations `set_frame_ms` accepts.
+const OPUS_FRAME_MS_CHOICES: [usize; 4] = [10, 20, 40, 60];
+const DEFAULT_OPUS_BITRATE: i32 = 128000;
+const DEFAULT_OPUS_COMPLEXITY: i32 = 10;
+/// Ring buffer between the input callback and the encode task.
+const DEFAULT_BUFFER_MS: u32 = 200;
+/// Must hold at least one Opus frame plus the encode task's polling slack.
+const MIN_BUFFER_MS: u32 = 40;
+const MAX_BUFFER_MS: u32 = 2000;
+/// Encode output buffer: libopus's recommended maximum. A 20ms packet is at
+/// most 1275 bytes, so encode never truncates.
+const OPUS_MAX_PACKET: usize = 4000;
+
+/// Samples per channel in one Opus frame of `ms` at 48kHz.
+fn opus_frame_samples(ms: usize) -> usize {
+    OPUS_SAMPLE_RATE as usize * ms / 1000
+}
+
+/// Bitrate to give the encoder for a configured `bitrate`: half of it for
+/// mono, which is one channel's share of the stereo budget, so switching to
+/// mono actually saves bandwidth. Never below Opus' 6 kbps floor.
+fn encoder_bitrate(bitrate: i32, mono: bool) -> i32 {
+    if mono {
+        (bitrate / 2).max(6000)
+    } else {
+        bitrate
+    }
+}
+
+/// Voice-tuned Opus encoder at 48kHz with FEC and DTX on. `bitrate` is the
+/// configured (stereo) bitrate, halved for mono by `encoder_bitrate`.
+fn new_opus_encoder(mono: bool, bitrate: i32, complexity: i32) -> Result<opus::Encoder, opus::Error> {
+    let channels = if mono { opus::Channels::Mono } else { opus::Channels::Stereo };
+    let mut encoder = opus::Encoder::new(OPUS_SAMPLE_RATE, channels, opus::Application::Voip)?;
+    let _ = encoder.set_bitrate(opus::Bitrate::Bits(encoder_bitrate(bitrate, mono)));
+    let _ = encoder.set_complexity(complexity);
+    let _ = encoder.set_inband_fec(true);
+    let _ = encoder.set_dtx(true);
+    Ok(encoder)
+}
 
 /// Messages from capture to the engine.
 pub enum CaptureEvent {
     Speaking(bool),
+    /// The input stream failed, e.g. the device was unplugged. `stream` is the
+    /// `stream_id()` it came from, since cpal can report several errors.
+    DeviceError { stream: u64, reason: String },
+}
+
+/// Playback mix buffer that monitored (sidetone) mic audio is written into.
+pub struct MonitorSink {
+    pub producer: Arc<std::sync::Mutex<Option<ringbuf::HeapProd<f32>>>>,
+    pub device_rate: u32,
+    pub device_channels: usize,
 }
 
 /// Wrapper around cpal::Stream to make it Send+Sync.
@@ -33,7 +87,29 @@ pub struct AudioCapture {
     stream: Option<SendStream>,
     encode_handle: Option<tokio::task::JoinHandle<()>>,
     muted: Arc<AtomicBool>,
+    monitor: Arc<AtomicBool>,
     mic_gain: Arc<std::sync::Mutex<f32>>,
+    /// Transmit speaking detector tuning, picked up on the next frame.
+    speaking_params: Arc<std::sync::Mutex<SpeakingParams>>,
+    /// Opus encoder settings, picked up by the encode task on the next frame.
+    opus_bitrate: Arc<AtomicI32>,
+    opus_complexity: Arc<AtomicI32>,
+    /// Encode a mono downmix instead of stereo, from the next frame.
+    mono: Arc<AtomicBool>,
+    /// Opus frame duration in ms, picked up at the next frame boundary.
+    frame_ms: Arc<AtomicUsize>,
+    /// Hardware rate to open the device at, if it supports it (applies on start).
+    preferred_rate: Option<u32>,
+    /// Input ring buffer size in ms of audio (applies on start).
+    buffer_ms: u32,
+    /// Soundboard clips mixed into the outgoing stream. Kept across restarts.
+    clips: Arc<std::sync::Mutex<Vec<Clip>>>,
+    /// Name of the device the running stream was opened on.
+    device_name: Option<String>,
+    /// Bumped on every start, to tell stale stream errors apart.
+    stream_id: u64,
+    /// Kept across restarts so the event loop's receiver stays connected.
+    event_tx: Option<mpsc::UnboundedSender<CaptureEvent>>,
     pub event_rx: Option<mpsc::UnboundedReceiver<CaptureEvent>>,
 }
 
@@ -43,31 +119,64 @@ impl AudioCapture {
             stream: None,
             encode_handle: None,
             muted: Arc::new(AtomicBool::new(false)),
+            monitor: Arc::new(AtomicBool::new(false)),
             mic_gain: Arc::new(std::sync::Mutex::new(1.0)),
+            speaking_params: Arc::new(std::sync::Mutex::new(SpeakingParams::default())),
+            opus_bitrate: Arc::new(AtomicI32::new(DEFAULT_OPUS_BITRATE)),
+            opus_complexity: Arc::new(AtomicI32::new(DEFAULT_OPUS_COMPLEXITY)),
+            mono: Arc::new(AtomicBool::new(false)),
+            frame_ms: Arc::new(AtomicUsize::new(OPUS_FRAME_MS)),
+            preferred_rate: None,
+            buffer_ms: DEFAULT_BUFFER_MS,
+            clips: Arc::new(std::sync::Mutex::new(Vec::new())),
+            device_name: None,
+            stream_id: 0,
+            event_tx: None,
             event_rx: None,
         }
     }
 
-    /// Start capturing from the given device (or default).
-    /// Encodes Opus and writes RTP to the provided track.
+    /// Start capturing from the given device id/name (or default).
+    /// Encodes Opus and writes RTP to the provided track. While monitoring is
+    /// enabled, captured audio is also copied into `monitor_sink` for local playback.
     pub fn start(
         &mut self,
-        device_name: Option<&str>,
+        device_id: Option<&str>,
         track: Arc<TrackLocalStaticRTP>,
+        monitor_sink: MonitorSink,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let host = cpal::default_host();
-        let device = if let Some(name) = device_name {
-            host.input_devices()?
-                .find(|d| d.name().map(|n| n == name).unwrap_or(false))
-                .ok_or_else(|| format!("input device '{}' not found", name))?
+        let host = super::host::current();
+        let device = if let Some(id) = device_id {
+            find_device(host.input_devices()?, id)
+                .ok_or_else(|| format!("input device '{}' not found", id))?
         } else {
             host.default_input_device()
                 .ok_or("no default input device")?
         };
 
         let supported = device.default_input_config()?;
-        let device_rate = supported.sample_rate().0;
         let device_channels = supported.channels() as usize;
+        let device_rate = match self.preferred_rate {
+            Some(hz) if hz != supported.sample_rate().0 => {
+                // Only use the preferred rate if some config with our channel count covers it
+                let available = device.supported_input_configs()?.any(|c| {
+                    c.channels() == supported.channels()
+                        && c.min_sample_rate().0 <= hz
+                        && hz <= c.max_sample_rate().0
+                });
+                if available {
+                    hz
+                } else {
+                    log::warn!(
+                        "Capture device doesn't support {}Hz, using default {}Hz",
+                        hz,
+                        supported.sample_rate().0
+                    );
+                    supported.sample_rate().0
+                }
+            }
+            _ => supported.sample_rate().0,
+        };
 
         log::info!(
             "Capture device: {} ({}Hz, {}ch)",
@@ -76,12 +185,23 @@ impl AudioCapture {
             device_channels,
         );
 
-        // Ring buffer: enough for ~200ms of audio at device rate
-        let buf_size = (device_rate as usize * device_channels * 200) / 1000;
-        let rb = HeapRb::<f32>::new(buf_size.max(8192));
+        // Ring buffer: `buffer_ms` of audio at device rate
+        let buf_size = (device_rate as usize * device_channels * self.buffer_ms as usize) / 1000;
+        let rb = HeapRb::<f32>::new(buf_size.max(1));
         let (mut producer, mut consumer) = rb.split();
 
-        let mic_gain = Arc::clone(&self.mic_gain);
+        // Event channel for speaking detection and stream errors. Reused while
+        // the event loop still holds the receiver, so a device switch doesn't
+        // cut it off.
+        if self.event_tx.as_ref().map_or(true, |tx| tx.is_closed()) {
+            let (tx, rx) = mpsc::unbounded_channel();
+            self.event_tx = Some(tx);
+            self.event_rx = Some(rx);
+        }
+        let event_tx = self.event_tx.clone().unwrap();
+        let error_tx = event_tx.clone();
+        self.stream_id += 1;
+        let stream_id = self.stream_id;
 
         // cpal input stream
         let config = StreamConfig {
@@ -90,71 +210,93 @@ impl AudioCapture {
             buffer_size: cpal::BufferSize::Default,
         };
 
-        let gain_for_stream = Arc::clone(&mic_gain);
         let stream = device.build_input_stream(
             &config,
             move |data: &[f32], _: &cpal::InputCallbackInfo| {
-                let gain = *gain_for_stream.lock().unwrap();
                 for &sample in data {
-                    let _ = producer.try_push(sample * gain);
+                    let _ = producer.try_push(sample);
                 }
             },
-            |err| log::error!("cpal input error: {}", err),
+            move |err| {
+                log::error!("cpal input error: {}", err);
+                let _ = error_tx.send(CaptureEvent::DeviceError {
+                    stream: stream_id,
+                    reason: err.to_string(),
+                });
+            },
             None,
         )?;
         stream.play()?;
         self.stream = Some(SendStream(stream));
-
-        // Event channel for speaking detection
-        let (event_tx, event_rx) = mpsc::unbounded_channel();
-        self.event_rx = Some(event_rx);
+        self.device_name = device.name().ok();
 
         // Spawn async encode task
         let muted = Arc::clone(&self.muted);
+        let monitor = Arc::clone(&self.monitor);
+        let opus_bitrate = Arc::clone(&self.opus_bitrate);
+        let opus_complexity = Arc::clone(&self.opus_complexity);
+        let mono = Arc::clone(&self.mono);
+        let frame_ms = Arc::clone(&self.frame_ms);
+        let clips = Arc::clone(&self.clips);
+        let mic_gain = Arc::clone(&self.mic_gain);
+        let speaking_params = Arc::clone(&self.speaking_params);
         let handle = tokio::spawn(async move {
+            let mut applied_frame_ms = frame_ms.load(Ordering::Relaxed);
+            let mut frame_samples = opus_frame_samples(applied_frame_ms);
+            // Device-rate frames that resample to one Opus frame
+            let device_frames = |frame_samples: usize| {
+                (frame_samples as f64 * device_rate as f64 / OPUS_SAMPLE_RATE as f64).ceil() as usize
+            };
+
             let needs_resample = device_rate != OPUS_SAMPLE_RATE;
             let mut resampler = if needs_resample {
-                let input_frames =
-                    (OPUS_FRAME_SAMPLES as f64 * device_rate as f64 / OPUS_SAMPLE_RATE as f64)
-                        .ceil() as usize;
                 Some(AudioResampler::new(
                     device_rate,
                     OPUS_SAMPLE_RATE,
-                    input_frames,
+                    device_frames(frame_samples),
                     OPUS_CHANNELS,
                 ))
             } else {
                 None
             };
 
-            let mut encoder = match opus::Encoder::new(
-                OPUS_SAMPLE_RATE,
-                opus::Channels::Stereo,
-                opus::Application::Voip,
-            ) {
-                Ok(e) => e,
-                Err(e) => {
-                    log::error!("Failed to create Opus encoder: {}", e);
-                    return;
-                }
+            // Monitor path: 48kHz stereo → playback device format
+            let mut monitor_resampler = if monitor_sink.device_rate != OPUS_SAMPLE_RATE {
+                Some(AudioResampler::new(
+                    OPUS_SAMPLE_RATE,
+                    monitor_sink.device_rate,
+                    frame_samples,
+                    OPUS_CHANNELS,
+                ))
+            } else {
+                None
             };
-            let _ = encoder.set_bitrate(opus::Bitrate::Bits(128000));
-            let _ = encoder.set_inband_fec(true);
-            let _ = encoder.set_dtx(true);
 
-            let mut speaking_detector = SpeakingDetector::new();
-            let mut opus_buf = vec![0u8; 4000];
+            let mut applied_mono = mono.load(Ordering::Relaxed);
+            let mut applied_bitrate = opus_bitrate.load(Ordering::Relaxed);
+            let mut applied_complexity = opus_complexity.load(Ordering::Relaxed);
+            let mut encoder =
+                match new_opus_encoder(applied_mono, applied_bitrate, applied_complexity) {
+                    Ok(e) => e,
+                    Err(e) => {
+                        log::error!("Failed to create Opus encoder: {}", e);
+                        return;
+                    }
+                };
+
+            // Gain is applied here, at 48kHz after resampling, so the speaking
+            // detector sees the raw input level whatever the gain.
+            let mut gain_ramp =
+                GainRamp::new(*mic_gain.lock().unwrap(), OPUS_SAMPLE_RATE, OPUS_CHANNELS);
+            let mut speaking_detector =
+                SpeakingDetector::with_params(*speaking_params.lock().unwrap());
+            let mut opus_buf = vec![0u8; OPUS_MAX_PACKET];
             let mut pcm_buf = Vec::new();
+            // 48kHz stereo waiting to be cut into Opus frames
+            let mut stereo_buf: Vec<f32> = Vec::new();
 
             // How many interleaved samples we need per frame at device rate
-            let device_frame_samples = if needs_resample {
-                let input_frames =
-                    (OPUS_FRAME_SAMPLES as f64 * device_rate as f64 / OPUS_SAMPLE_RATE as f64)
-                        .ceil() as usize;
-                input_frames * device_channels
-            } else {
-                OPUS_FRAME_SAMPLES * device_channels
-            };
+            let mut device_frame_samples = device_frames(frame_samples) * device_channels;
 
             let mut timestamp: u32 = 0;
             let mut sequence: u16 = 0;
@@ -162,6 +304,22 @@ impl AudioCapture {
             loop {
                 tokio::time::sleep(Duration::from_millis(5)).await;
 
+                // Frame size changes land here, between packets, so every
+                // packet's length and timestamp step agree
+                let want_frame_ms = frame_ms.load(Ordering::Relaxed);
+                if want_frame_ms != applied_frame_ms {
+                    applied_frame_ms = want_frame_ms;
+                    frame_samples = opus_frame_samples(applied_frame_ms);
+                    device_frame_samples = device_frames(frame_samples) * device_channels;
+                    if let Some(rs) = resampler.as_mut() {
+                        rs.set_chunk_size(device_frames(frame_samples));
+                    }
+                    if let Some(rs) = monitor_resampler.as_mut() {
+                        rs.set_chunk_size(frame_samples);
+                    }
+                    log::info!("Opus frame size now {}ms", applied_frame_ms);
+                }
+
                 // Drain from ring buffer
                 while consumer.occupied_len() > 0 {
                     if let Some(sample) = consumer.try_pop() {
@@ -174,33 +332,102 @@ impl AudioCapture {
                 while pcm_buf.len() >= device_frame_samples {
                     let frame: Vec<f32> = pcm_buf.drain(..device_frame_samples).collect();
 
-                    // Convert to stereo at 48kHz
-                    let stereo_48k = if needs_resample {
-                        let stereo = to_stereo(&frame, device_channels);
-                        resampler.as_mut().unwrap().process(&stereo)
-                    } else {
-                        to_stereo(&frame, device_channels)
-                    };
+                    // Convert to stereo at 48kHz. The resampler doesn't return
+                    // exactly one Opus frame per call, so collect and re-slice below.
+                    let stereo = to_stereo(&frame, device_channels);
+                    match resampler.as_mut() {
+                        Some(rs) => stereo_buf.extend(rs.process(&stereo)),
+                        None => stereo_buf.extend(stereo),
+                    }
+                }
 
-                    // Speaking detection on mono
+                while stereo_buf.len() >= frame_samples * OPUS_CHANNELS {
+                    let mut stereo_48k: Vec<f32> =
+                        stereo_buf.drain(..frame_samples * OPUS_CHANNELS).collect();
+
+                    // Speaking detection on mono, before gain
                     let mono: Vec<f32> = stereo_48k
                         .chunks(2)
                         .map(|c| (c[0] + c.get(1).copied().unwrap_or(c[0])) / 2.0)
                         .collect();
-                    if let Some(speaking) = speaking_detector.process(&mono, OPUS_FRAME_MS as f64) {
+                    speaking_detector.set_params(*speaking_params.lock().unwrap());
+                    if let Some(speaking) = speaking_detector.process(&mono, applied_frame_ms as f64) {
                         let _ = event_tx.send(CaptureEvent::Speaking(speaking));
                     }
 
+                    // Mic gain, clamped so a boost can't wrap in the i16 conversion
+                    let gain = *mic_gain.lock().unwrap();
+                    for sample in stereo_48k.iter_mut() {
+                        *sample = (*sample * gain_ramp.next(gain)).clamp(-1.0, 1.0);
+                    }
+
+                    // Sidetone: play the mic locally. Only ever written to the
+                    // playback mix, never to the outgoing track.
+                    if monitor.load(Ordering::Relaxed) {
+                        let local = match monitor_resampler.as_mut() {
+                            Some(rs) => rs.process(&stereo_48k),
+                            None => stereo_48k.clone(),
+                        };
+                        let local = adapt_channels(&local, OPUS_CHANNELS, monitor_sink.device_channels);
+                        if let Ok(mut guard) = monitor_sink.producer.lock() {
+                            if let Some(ref mut prod) = *guard {
+                                for &sample in &local {
+                                    let _ = prod.try_push(sample);
+                                }
+                            }
+                        }
+                    }
+
+                    // Soundboard: only the channel hears clips, not the sidetone.
+                    // Clips keep playing (unheard) while muted.
+                    if let Ok(mut clips) = clips.lock() {
+                        if !clips.is_empty() {
+                            mix_clips(&mut clips, &mut stereo_48k, gain);
+                        }
+                    }
+
                     if muted.load(Ordering::Relaxed) {
-                        timestamp = timestamp.wrapping_add(OPUS_FRAME_SAMPLES as u32);
+                        timestamp = timestamp.wrapping_add(frame_samples as u32);
                         continue;
                     }
 
+                    // The channel count is fixed per encoder, so a mono switch
+                    // needs a new one. Opus flags stereo in every packet's TOC
+                    // byte, so receivers follow the switch without renegotiating.
+                    let want_mono = mono.load(Ordering::Relaxed);
+                    if want_mono != applied_mono {
+                        match new_opus_encoder(want_mono, applied_bitrate, applied_complexity) {
+                            Ok(e) => {
+                                encoder = e;
+                                applied_mono = want_mono;
+                            }
+                            Err(e) => log::warn!("Opus encoder (mono={}) failed: {}", want_mono, e),
+                        }
+                    }
+
+                    // Apply bitrate/complexity changes to the live encoder
+                    let bitrate = opus_bitrate.load(Ordering::Relaxed);
+                    if bitrate != applied_bitrate {
+                        applied_bitrate = bitrate;
+                        if let Err(e) = encoder.set_bitrate(opus::Bitrate::Bits(encoder_bitrate(bitrate, applied_mono))) {
+                            log::warn!("Opus set_bitrate({}) failed: {}", bitrate, e);
+                        }
+                    }
+                    let complexity = opus_complexity.load(Ordering::Relaxed);
+                    if complexity != applied_complexity {
+                        applied_complexity = complexity;
+                        if let Err(e) = encoder.set_complexity(complexity) {
+                            log::warn!("Opus set_complexity({}) failed: {}", complexity, e);
+                        }
+                    }
+
                     // Opus encode (expects interleaved i16)
-                    let pcm_i16: Vec<i16> = stereo_48k
-                        .iter()
-                        .map(|&s| (s.clamp(-1.0, 1.0) * 32767.0) as i16)
-                        .collect();
+                    let to_i16 = |s: f32| (s.clamp(-1.0, 1.0) * 32767.0) as i16;
+                    let pcm_i16: Vec<i16> = if applied_mono {
+                        stereo_48k.chunks(2).map(|c| to_i16((c[0] + c[1]) / 2.0)).collect()
+                    } else {
+                        stereo_48k.iter().map(|&s| to_i16(s)).collect()
+                    };
 
                     let encoded_len = match encoder.encode(&pcm_i16, &mut opus_buf) {
                         Ok(len) => len,
@@ -219,6 +446,8 @@ impl AudioCapture {
                             payload_type: 111,
                             sequence_number: sequence,
                             timestamp,
+                            // Placeholder: write_rtp stamps each binding's negotiated SSRC
+                            // (random per RTCRtpSender, so voice and screen audio never share one)
                             ssrc: 0,
                             ..Default::default()
                         },
@@ -226,7 +455,7 @@ impl AudioCapture {
                     };
 
                     sequence = sequence.wrapping_add(1);
-                    timestamp = timestamp.wrapping_add(OPUS_FRAME_SAMPLES as u32);
+                    timestamp = timestamp.wrapping_add(frame_samples as u32);
 
                     if let Err(e) = track.write_rtp(&rtp_packet).await {
                         if e.to_string().contains("closed") {
@@ -244,27 +473,123 @@ impl AudioCapture {
 
     pub fn stop(&mut self) {
         self.stream = None;
+        self.device_name = None;
         if let Some(handle) = self.encode_handle.take() {
             handle.abort();
         }
         self.event_rx = None;
     }
 
+    /// `stop`, then wait for the encode task to finish so nothing from this
+    /// session is still writing to the track.
+    pub async fn stop_and_wait(&mut self) {
+        let handle = self.encode_handle.take();
+        self.stop();
+        if let Some(handle) = handle {
+            handle.abort();
+            let _ = handle.await;
+        }
+    }
+
     pub fn set_muted(&self, muted: bool) {
         self.muted.store(muted, Ordering::Relaxed);
     }
 
+    /// Rate to request from the device on the next start (None = device default).
+    /// 48kHz matches Opus and skips resampling.
+    pub fn set_preferred_rate(&mut self, hz: Option<u32>) {
+        self.preferred_rate = hz;
+    }
+
+    /// Input ring buffer size for the next start, clamped to
+    /// `MIN_BUFFER_MS..=MAX_BUFFER_MS`. Larger rides out scheduling hiccups in
+    /// the encode task; a buffer that fills drops samples.
+    pub fn set_buffer_ms(&mut self, ms: u32) {
+        self.buffer_ms = ms.clamp(MIN_BUFFER_MS, MAX_BUFFER_MS);
+    }
+
+    pub fn is_muted(&self) -> bool {
+        self.muted.load(Ordering::Relaxed)
+    }
+
+    pub fn set_monitor(&self, enabled: bool) {
+        self.monitor.store(enabled, Ordering::Relaxed);
+    }
+
     pub fn set_mic_gain(&self, gain: f32) {
         *self.mic_gain.lock().unwrap() = gain;
     }
 
+    pub fn mic_gain(&self) -> f32 {
+        *self.mic_gain.lock().unwrap()
+    }
+
+    pub fn speaking_params(&self) -> SpeakingParams {
+        *self.speaking_params.lock().unwrap()
+    }
+
+    /// Applies to the running detector from its next frame.
+    pub fn set_speaking_params(&self, params: SpeakingParams) {
+        *self.speaking_params.lock().unwrap() = params;
+    }
+
+    /// Start mixing a clip into the outgoing stream, on top of the mic and
+    /// any clips already playing.
+    pub fn play_clip(&self, clip: Clip) {
+        self.clips.lock().unwrap().push(clip);
+    }
+
+    pub fn stop_clips(&self) {
+        self.clips.lock().unwrap().clear();
+    }
+
+    /// Set the Opus target bitrate in bits/s, clamped to Opus' 6–510 kbps range.
+    /// This is the stereo rate; mono sends half of it.
+    pub fn set_opus_bitrate(&self, bps: i32) {
+        self.opus_bitrate.store(bps.clamp(6000, 510000), Ordering::Relaxed);
+    }
+
+    /// Set the Opus encoder complexity, clamped to 0–10.
+    pub fn set_opus_complexity(&self, level: i32) {
+        self.opus_complexity.store(level.clamp(0, 10), Ordering::Relaxed);
+    }
+
+    /// Send a mono downmix at half the configured bitrate, which sounds the
+    /// same for a mono mic. Switches the running encoder from its next frame.
+    pub fn set_mono(&self, enabled: bool) {
+        self.mono.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Opus frame duration: 10, 20, 40 or 60ms. Longer frames cut per-packet
+    /// overhead on slow links, shorter ones latency. The running encoder
+    /// switches between two packets.
+    pub fn set_frame_ms(&self, ms: usize) -> Result<(), String> {
+        if !OPUS_FRAME_MS_CHOICES.contains(&ms) {
+            return Err(format!("frame size must be one of {:?} ms", OPUS_FRAME_MS_CHOICES));
+        }
+        self.frame_ms.store(ms, Ordering::Relaxed);
+        Ok(())
+    }
+
+    pub fn is_mono(&self) -> bool {
+        self.mono.load(Ordering::Relaxed)
+    }
+
     pub fn is_running(&self) -> bool {
         self.stream.is_some()
     }
+
+    pub fn device_name(&self) -> Option<String> {
+        self.device_name.clone()
+    }
+
+    pub fn stream_id(&self) -> u64 {
+        self.stream_id
+    }
 }
 
 /// Convert any channel count to stereo interleaved.
-fn to_stereo(samples: &[f32], channels: usize) -> Vec<f32> {
+pub(super) fn to_stereo(samples: &[f32], channels: usize) -> Vec<f32> {
     if channels == 2 {
         return samples.to_vec();
     }
@@ -286,18 +611,92 @@ fn to_stereo(samples: &[f32], channels: usize) -> Vec<f32> {
     stereo
 }
 
+/// Open the input device and query its config without starting a stream.
+/// Warms up the host backend so the real capture start is quicker.
+pub fn probe_input_device(
+    device_id: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let host = super::host::current();
+    let device = if let Some(id) = device_id {
+        find_device(host.input_devices()?, id)
+            .ok_or_else(|| format!("input device '{}' not found", id))?
+    } else {
+        host.default_input_device()
+            .ok_or("no default input device")?
+    };
+    let config = device.default_input_config()?;
+    log::info!(
+        "Probed input device: {} ({}Hz, {}ch)",
+        device.name().unwrap_or_default(),
+        config.sample_rate().0,
+        config.channels(),
+    );
+    Ok(())
+}
+
+/// Named devices with ids, in enumeration order. The id is the name, so it
+/// doesn't depend on what else is connected. cpal exposes nothing more
+/// stable than that, so identically-named devices (common on
+/// PulseAudio/PipeWire) are told apart by their order among themselves:
+/// `#2`, `#3`... are appended to repeats. Those suffixed ids shift when
+/// another device *with the same name* appears or goes away ahead of them;
+/// the id then resolves to a different device or to none (capture falls
+/// back to the default).
+fn identify<I: Iterator<Item = cpal::Device>>(devices: I) -> Vec<(String, String, cpal::Device)> {
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    devices
+        .filter_map(|d| {
+            let name = d.name().ok()?;
+            let count = seen.entry(name.clone()).or_insert(0);
+            *count += 1;
+            let id = if *count == 1 {
+                name.clone()
+            } else {
+                format!("{}#{}", name, count)
+            };
+            Some((id, name, d))
+        })
+        .collect()
+}
+
+/// Resolve an id from `list_*_devices`, falling back to a plain device name.
+pub fn find_device<I: Iterator<Item = cpal::Device>>(devices: I, id: &str) -> Option<cpal::Device> {
+    let mut devices = identify(devices);
+    let index = devices
+        .iter()
+        .position(|(dev_id, _, _)| dev_id == id)
+        .or_else(|| devices.iter().position(|(_, name, _)| name == id))?;
+    Some(devices.swap_remove(index).2)
+}
+
+fn list_devices<I: Iterator<Item = cpal::Device>>(
+    devices: Option<I>,
+    default: Option<cpal::Device>,
+) -> Vec<AudioDeviceInfo> {
+    let default_name = default.and_then(|d| d.name().ok());
+    let Some(devices) = devices else {
+        return Vec::new();
+    };
+    let mut default_marked = false;
+    identify(devices)
+        .into_iter()
+        .map(|(id, name, _)| {
+            // Only the first device with the default's name is marked
+            let is_default = !default_marked && default_name.as_deref() == Some(name.as_str());
+            default_marked |= is_default;
+            AudioDeviceInfo { id, name, is_default }
+        })
+        .collect()
+}
+
 /// List available input devices.
-pub fn list_input_devices() -> Vec<String> {
-    let host = cpal::default_host();
-    host.input_devices()
-        .map(|devices| devices.filter_map(|d| d.name().ok()).collect())
-        .unwrap_or_default()
+pub fn list_input_devices() -> Vec<AudioDeviceInfo> {
+    let host = super::host::current();
+    list_devices(host.input_devices().ok(), host.default_input_device())
 }
 
 /// List available output devices.
-pub fn list_output_devices() -> Vec<String> {
-    let host = cpal::default_host();
-    host.output_devices()
-        .map(|devices| devices.filter_map(|d| d.name().ok()).collect())
-        .unwrap_or_default()
+pub fn list_output_devices() -> Vec<AudioDeviceInfo> {
+    let host = super::host::current();
+    list_devices(host.output_devices().ok(), host.default_output_device())
 }
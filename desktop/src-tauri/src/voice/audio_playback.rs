@@ -1,12 +1,185 @@
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
 use std::sync::Arc;
 
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
 use cpal::{SampleRate, StreamConfig};
-use ringbuf::{HeapRb, traits::{Consumer, Split}};
+use ringbuf::{HeapRb, traits::{Consumer, Observer, Producer, Split}};
+
+use super::audio_capture::{find_device, MonitorSink};
+use super::gain::GainRamp;
+use super::recorder::Recording;
+use super::types::AudioHealthEvent;
 
 const OPUS_SAMPLE_RATE: u32 = 48000;
 const OPUS_CHANNELS: usize = 2;
+/// Test tone level, well below full scale so it isn't startling.
+const TEST_TONE_AMPLITUDE: f32 = 0.25;
+/// Fade at each end of the test tone so it doesn't click.
+const TEST_TONE_FADE_MS: u32 = 10;
+const MIN_TEST_TONE_HZ: f32 = 20.0;
+const MAX_TEST_TONE_MS: u32 = 10_000;
+/// Mix ring buffer size. Latency is set by `LatencyMode`; this only caps how
+/// much decoded audio can queue before pushes are dropped.
+const DEFAULT_BUFFER_MS: u32 = 500;
+/// Enough for the low-latency ceiling plus a frame from each of a few tracks.
+/// Smaller than smooth mode's raised prebuffer, which is then capped to fit.
+const MIN_BUFFER_MS: u32 = 100;
+const MAX_BUFFER_MS: u32 = 2000;
+/// How long the queue must sit above the high-water mark (halfway between
+/// prebuffer and max) before samples are trimmed to pull it back.
+const SOFT_RESYNC_AFTER_MS: usize = 500;
+/// While trimming, drop about 1 frame in this many (~1% speed-up, inaudible).
+const SOFT_RESYNC_RATIO: usize = 100;
+/// Running dry this soon after playout resumed counts as an underrun.
+const UNDERRUN_WINDOW_MS: usize = 1000;
+/// Prebuffer added per underrun, and given back after each clean `EXTRA_DECAY_MS`.
+const EXTRA_PREBUFFER_STEP_MS: usize = 20;
+const EXTRA_DECAY_MS: usize = 10_000;
+
+/// How the playback buffer trades delay against dropouts.
+///
+/// The buffer doubles as the jitter buffer: after an underrun, playout waits
+/// until `prebuffer_ms` of audio has queued up, and anything queued beyond
+/// `max_ms` is dropped so delay can't build up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LatencyMode {
+    /// Small buffer: least delay, but network jitter is heard as glitches.
+    LowLatency,
+    /// Larger buffer: rides out jitter and brief stalls, with more delay.
+    Smooth,
+}
+
+impl LatencyMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "low_latency" => Some(Self::LowLatency),
+            "smooth" => Some(Self::Smooth),
+            _ => None,
+        }
+    }
+
+    fn prebuffer_ms(self) -> usize {
+        match self {
+            Self::LowLatency => 20,
+            Self::Smooth => 80,
+        }
+    }
+
+    fn max_ms(self) -> usize {
+        match self {
+            Self::LowLatency => 80,
+            Self::Smooth => 300,
+        }
+    }
+
+    /// How long the decode tasks' jitter buffers wait for a missing packet
+    /// before concealing it. Kept under `prebuffer_ms` plus the extra it can
+    /// grow by, so a concealed gap doesn't also drain the output buffer.
+    pub fn jitter_depth_ms(self) -> u32 {
+        match self {
+            Self::LowLatency => 40,
+            Self::Smooth => 60,
+        }
+    }
+
+    /// Cap on the prebuffer added after underruns: half the headroom, so the
+    /// raised prebuffer still sits well under `max_ms`.
+    fn max_extra_ms(self) -> usize {
+        (self.max_ms() - self.prebuffer_ms()) / 2
+    }
+}
+
+/// Playback buffer counters, written by the output callback and drained by
+/// `HealthReporter`.
+#[derive(Default)]
+pub struct PlaybackHealth {
+    underruns: AtomicU32,
+    overruns: AtomicU32,
+    /// Samples discarded by resyncs.
+    resynced: AtomicU64,
+    /// Samples played out of the buffer.
+    played: AtomicU64,
+    /// Samples queued at the last callback.
+    queued: AtomicU64,
+    /// Set whenever playout runs dry, including at the end of a talk spurt.
+    drained: AtomicBool,
+    extra_prebuffer: AtomicU64,
+    samples_per_ms: AtomicU64,
+}
+
+/// Turns `PlaybackHealth` counters into `voice:audio_health` events. Meant to
+/// be the only reader: each report takes the counts since the previous one.
+pub struct HealthReporter {
+    health: Arc<PlaybackHealth>,
+    prev_queued: u64,
+    drift_ppm: f64,
+}
+
+impl HealthReporter {
+    pub fn new(health: Arc<PlaybackHealth>) -> Self {
+        let prev_queued = health.queued.load(Ordering::Relaxed);
+        Self {
+            health,
+            prev_queued,
+            drift_ppm: 0.0,
+        }
+    }
+
+    pub fn report(&mut self) -> AudioHealthEvent {
+        let h = &self.health;
+        let underruns = h.underruns.swap(0, Ordering::Relaxed);
+        let overruns = h.overruns.swap(0, Ordering::Relaxed);
+        let resynced = h.resynced.swap(0, Ordering::Relaxed);
+        let played = h.played.swap(0, Ordering::Relaxed);
+        let drained = h.drained.swap(false, Ordering::Relaxed);
+        let queued = h.queued.load(Ordering::Relaxed);
+        let samples_per_ms = h.samples_per_ms.load(Ordering::Relaxed).max(1) as f64;
+
+        // Queue growth per sample played, counting what resyncs threw away.
+        // Windows where the buffer ran dry or overflowed say nothing about
+        // the clocks, so they're left out.
+        if played > 0 && !drained && overruns == 0 {
+            let grown = queued as f64 - self.prev_queued as f64 + resynced as f64;
+            let ppm = grown / played as f64 * 1e6;
+            self.drift_ppm = self.drift_ppm * 0.8 + ppm * 0.2;
+        }
+        self.prev_queued = queued;
+
+        AudioHealthEvent {
+            underruns,
+            overruns,
+            resynced_ms: resynced as f64 / samples_per_ms,
+            queued_ms: queued as f64 / samples_per_ms,
+            drift_ppm: self.drift_ppm,
+            extra_prebuffer_ms: h.extra_prebuffer.load(Ordering::Relaxed) as f64 / samples_per_ms,
+        }
+    }
+}
+
+/// Sine wave on every output channel, generated in the output callback.
+struct TestTone {
+    phase: f32,
+    /// Phase advance per frame, in radians.
+    step: f32,
+    frame: usize,
+    frames: usize,
+    fade_frames: usize,
+}
+
+impl TestTone {
+    /// Next sample, or None once the tone has finished.
+    fn next(&mut self) -> Option<f32> {
+        if self.frame >= self.frames {
+            return None;
+        }
+        let edge = self.frame.min(self.frames - self.frame).min(self.fade_frames);
+        let envelope = edge as f32 / self.fade_frames.max(1) as f32;
+        let sample = self.phase.sin() * TEST_TONE_AMPLITUDE * envelope;
+        self.phase = (self.phase + self.step) % std::f32::consts::TAU;
+        self.frame += 1;
+        Some(sample)
+    }
+}
 
 /// Wrapper around cpal::Stream to make it Send+Sync.
 struct SendStream(#[allow(dead_code)] cpal::Stream);
@@ -18,8 +191,19 @@ pub struct AudioPlayback {
     decode_handles: Vec<tokio::task::JoinHandle<()>>,
     pub deafened: Arc<AtomicBool>,
     pub master_volume: Arc<std::sync::Mutex<f32>>,
+    latency_mode: Arc<std::sync::Mutex<LatencyMode>>,
+    /// The latency mode's `jitter_depth_ms`, read by the decode tasks.
+    pub jitter_depth_ms: Arc<AtomicU32>,
+    /// Mix ring buffer size in ms of audio (applies on start).
+    buffer_ms: u32,
+    health: Arc<PlaybackHealth>,
+    /// Speaker test tone, mixed in by the output callback.
+    test_tone: Arc<std::sync::Mutex<Option<TestTone>>>,
     /// Shared producer for all decode tasks to write mixed audio into.
     pub mix_producer: Arc<std::sync::Mutex<Option<ringbuf::HeapProd<f32>>>>,
+    /// Copy of everything sent to the device, for the active recording.
+    record_tap: Arc<std::sync::Mutex<Option<ringbuf::HeapProd<f32>>>>,
+    recording: Option<Recording>,
     pub device_rate: u32,
     pub device_channels: usize,
 }
@@ -31,22 +215,28 @@ impl AudioPlayback {
             decode_handles: Vec::new(),
             deafened: Arc::new(AtomicBool::new(false)),
             master_volume: Arc::new(std::sync::Mutex::new(1.0)),
+            latency_mode: Arc::new(std::sync::Mutex::new(LatencyMode::Smooth)),
+            jitter_depth_ms: Arc::new(AtomicU32::new(LatencyMode::Smooth.jitter_depth_ms())),
+            buffer_ms: DEFAULT_BUFFER_MS,
+            test_tone: Arc::new(std::sync::Mutex::new(None)),
+            health: Arc::new(PlaybackHealth::default()),
             mix_producer: Arc::new(std::sync::Mutex::new(None)),
+            record_tap: Arc::new(std::sync::Mutex::new(None)),
+            recording: None,
             device_rate: OPUS_SAMPLE_RATE,
             device_channels: OPUS_CHANNELS,
         }
     }
 
-    /// Start the output stream on the given device (or default).
+    /// Start the output stream on the given device id/name (or default).
     pub fn start(
         &mut self,
-        device_name: Option<&str>,
+        device_id: Option<&str>,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let host = cpal::default_host();
-        let device = if let Some(name) = device_name {
-            host.output_devices()?
-                .find(|d| d.name().map(|n| n == name).unwrap_or(false))
-                .ok_or_else(|| format!("output device '{}' not found", name))?
+        let host = super::host::current();
+        let device = if let Some(id) = device_id {
+            find_device(host.output_devices()?, id)
+                .ok_or_else(|| format!("output device '{}' not found", id))?
         } else {
             host.default_output_device()
                 .ok_or("no default output device")?
@@ -63,9 +253,19 @@ impl AudioPlayback {
             self.device_channels,
         );
 
-        // Ring buffer: ~500ms of audio at device rate
-        let buf_size = (self.device_rate as usize * self.device_channels * 500) / 1000;
-        let rb = HeapRb::<f32>::new(buf_size.max(16384));
+        // A recording is written in the old device's format — end it rather
+        // than mix sample rates in one file
+        if let Some(rec) = &self.recording {
+            if rec.device_rate != self.device_rate || rec.device_channels != self.device_channels {
+                log::warn!("Output format changed, stopping recording");
+                self.stop_recording();
+            }
+        }
+
+        // Ring buffer: `buffer_ms` of audio at device rate
+        let buf_size =
+            (self.device_rate as usize * self.device_channels * self.buffer_ms as usize) / 1000;
+        let rb = HeapRb::<f32>::new(buf_size.max(1));
         let (producer, mut consumer) = rb.split();
 
         // Store producer for decode tasks
@@ -73,6 +273,28 @@ impl AudioPlayback {
 
         let deafened = Arc::clone(&self.deafened);
         let volume = Arc::clone(&self.master_volume);
+        let latency_mode = Arc::clone(&self.latency_mode);
+        let test_tone = Arc::clone(&self.test_tone);
+        let record_tap = Arc::clone(&self.record_tap);
+        let health = Arc::clone(&self.health);
+        let device_channels = self.device_channels;
+        // Samples (all channels) per millisecond of audio
+        let samples_per_ms = self.device_rate as usize * device_channels / 1000;
+        health.samples_per_ms.store(samples_per_ms as u64, Ordering::Relaxed);
+        health.extra_prebuffer.store(0, Ordering::Relaxed);
+        // Start out filling the buffer, same as after an underrun
+        let mut buffering = true;
+        // Samples played since playout last resumed
+        let mut since_resume: usize = 0;
+        // Prebuffer raised after underruns, in samples
+        let mut extra_prebuffer: usize = 0;
+        // Samples played while the queue sat above the high-water mark
+        let mut above_high: usize = 0;
+        let mut volume_ramp = GainRamp::new(
+            *self.master_volume.lock().unwrap(),
+            self.device_rate,
+            self.device_channels,
+        );
 
         let config = StreamConfig {
             channels: self.device_channels as u16,
@@ -83,18 +305,111 @@ impl AudioPlayback {
         let stream = device.build_output_stream(
             &config,
             move |data: &mut [f32], _: &cpal::OutputCallbackInfo| {
-                let vol = *volume.lock().unwrap();
+                let target = *volume.lock().unwrap();
                 let deaf = deafened.load(Ordering::Relaxed);
+                // Decode tasks stop pushing while deafened, but the sidetone
+                // and frames decoded just before still arrive — drop them so
+                // un-deafening resumes with live audio, not a stale backlog.
+                if deaf {
+                    consumer.clear();
+                }
 
+                let mode = *latency_mode.lock().unwrap();
+                // The prebuffer must fit in the ring or playout never resumes
+                // (and the extra never decays); keep a quarter free for bursts
+                let ceiling = consumer.capacity().get() * 3 / 4;
+                let base_prebuffer = (mode.prebuffer_ms() * samples_per_ms).min(ceiling);
+                extra_prebuffer = extra_prebuffer
+                    .min(mode.max_extra_ms() * samples_per_ms)
+                    .min(ceiling - base_prebuffer);
+                let prebuffer = base_prebuffer + extra_prebuffer;
+                let max = mode.max_ms() * samples_per_ms;
+                if consumer.is_full() {
+                    health.overruns.fetch_add(1, Ordering::Relaxed);
+                }
+                let queued = consumer.occupied_len();
+                if queued > max {
+                    // Too far behind — drop down to the prebuffer level,
+                    // keeping whole frames so channels stay aligned
+                    let excess = queued - prebuffer;
+                    let skipped = consumer.skip(excess - excess % device_channels);
+                    health.resynced.fetch_add(skipped as u64, Ordering::Relaxed);
+                    above_high = 0;
+                } else if buffering && queued >= prebuffer {
+                    buffering = false;
+                    since_resume = 0;
+                } else if !buffering {
+                    // Persistently high (sender clock running fast): trim a
+                    // frame here and there instead of waiting for a big skip
+                    if queued > (prebuffer + max) / 2 {
+                        above_high += data.len();
+                    } else {
+                        above_high = 0;
+                    }
+                    if above_high >= SOFT_RESYNC_AFTER_MS * samples_per_ms {
+                        let frames = (data.len() / device_channels / SOFT_RESYNC_RATIO).max(1);
+                        let skipped = consumer.skip(frames * device_channels);
+                        health.resynced.fetch_add(skipped as u64, Ordering::Relaxed);
+                    }
+                }
+                health.queued.store(consumer.occupied_len() as u64, Ordering::Relaxed);
+
+                let mut played = 0;
                 for sample in data.iter_mut() {
-                    *sample = if deaf {
+                    let vol = volume_ramp.next(target);
+                    *sample = if deaf || buffering {
                         0.0
                     } else if let Some(s) = consumer.try_pop() {
+                        played += 1;
                         s * vol
                     } else {
+                        // Underrun: wait for the prebuffer to refill. Running
+                        // dry right after resuming means the prebuffer is too
+                        // small for this link, so raise it.
+                        buffering = true;
+                        health.drained.store(true, Ordering::Relaxed);
+                        if since_resume + played < UNDERRUN_WINDOW_MS * samples_per_ms {
+                            health.underruns.fetch_add(1, Ordering::Relaxed);
+                            extra_prebuffer = (extra_prebuffer + EXTRA_PREBUFFER_STEP_MS * samples_per_ms)
+                                .min(mode.max_extra_ms() * samples_per_ms);
+                        }
                         0.0
                     };
                 }
+                since_resume += played;
+                health.played.fetch_add(played as u64, Ordering::Relaxed);
+                // Give the extra prebuffer back once playout has stayed clean
+                if since_resume >= EXTRA_DECAY_MS * samples_per_ms && extra_prebuffer > 0 {
+                    extra_prebuffer = extra_prebuffer.saturating_sub(EXTRA_PREBUFFER_STEP_MS * samples_per_ms);
+                    since_resume = UNDERRUN_WINDOW_MS * samples_per_ms;
+                }
+                health.extra_prebuffer.store(extra_prebuffer as u64, Ordering::Relaxed);
+
+                // The test tone plays on top of the mix, even while deafened
+                if let Ok(mut tone) = test_tone.lock() {
+                    if let Some(t) = tone.as_mut() {
+                        let mut finished = false;
+                        for frame in data.chunks_mut(device_channels) {
+                            let Some(s) = t.next() else {
+                                finished = true;
+                                break;
+                            };
+                            for sample in frame.iter_mut() {
+                                *sample += s * target;
+                            }
+                        }
+                        if finished {
+                            *tone = None;
+                        }
+                    }
+                }
+
+                // try_lock: never wait on the recorder from the audio thread
+                if let Ok(mut tap) = record_tap.try_lock() {
+                    if let Some(producer) = tap.as_mut() {
+                        producer.push_slice(data);
+                    }
+                }
             },
             |err| log::error!("cpal output error: {}", err),
             None,
@@ -106,24 +421,107 @@ impl AudioPlayback {
     }
 
     pub fn stop(&mut self) {
+        self.stop_recording();
         self.stream = None;
         for handle in self.decode_handles.drain(..) {
             handle.abort();
         }
         *self.mix_producer.lock().unwrap() = None;
+        *self.test_tone.lock().unwrap() = None;
     }
 
     pub fn set_deafened(&self, deafened: bool) {
         self.deafened.store(deafened, Ordering::Relaxed);
     }
 
+    pub fn is_deafened(&self) -> bool {
+        self.deafened.load(Ordering::Relaxed)
+    }
+
     pub fn set_master_volume(&self, volume: f32) {
         *self.master_volume.lock().unwrap() = volume;
     }
 
+    pub fn master_volume(&self) -> f32 {
+        *self.master_volume.lock().unwrap()
+    }
+
+    /// Play a `freq` Hz sine for `duration_ms` (at most 10s) at master volume,
+    /// whether or not deafened. Replaces a tone that's still playing.
+    pub fn play_test_tone(&self, freq: f32, duration_ms: u32) {
+        let rate = self.device_rate as f32;
+        let freq = freq.clamp(MIN_TEST_TONE_HZ, rate / 2.0);
+        let duration_ms = duration_ms.min(MAX_TEST_TONE_MS);
+        *self.test_tone.lock().unwrap() = Some(TestTone {
+            phase: 0.0,
+            step: std::f32::consts::TAU * freq / rate,
+            frame: 0,
+            frames: (self.device_rate as u64 * duration_ms as u64 / 1000) as usize,
+            fade_frames: (self.device_rate * TEST_TONE_FADE_MS / 1000) as usize,
+        });
+    }
+
+    /// Mix buffer size for the next start, clamped to
+    /// `MIN_BUFFER_MS..=MAX_BUFFER_MS`. Below the latency mode's ceiling
+    /// (300ms for smooth) bursts are dropped instead of smoothed out.
+    pub fn set_buffer_ms(&mut self, ms: u32) {
+        self.buffer_ms = ms.clamp(MIN_BUFFER_MS, MAX_BUFFER_MS);
+    }
+
+    /// Counters for `HealthReporter`. Shared across restarts.
+    pub fn health(&self) -> Arc<PlaybackHealth> {
+        Arc::clone(&self.health)
+    }
+
+    /// Takes effect on the next output callback and the decode tasks' next packet.
+    pub fn set_latency_mode(&self, mode: LatencyMode) {
+        *self.latency_mode.lock().unwrap() = mode;
+        self.jitter_depth_ms.store(mode.jitter_depth_ms(), Ordering::Relaxed);
+    }
+
     pub fn is_running(&self) -> bool {
         self.stream.is_some()
     }
+
+    /// Record what's sent to the output device (after volume, silence while
+    /// deafened) to a WAV file at the device's rate and channel count.
+    pub fn start_recording(
+        &mut self,
+        path: &str,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        if !self.is_running() {
+            return Err("playback is not running".into());
+        }
+        self.stop_recording();
+
+        // ~1s of slack for the writer thread
+        let rb = HeapRb::<f32>::new(self.device_rate as usize * self.device_channels);
+        let (producer, consumer) = rb.split();
+        self.recording = Some(Recording::start(
+            path,
+            self.device_rate,
+            self.device_channels,
+            consumer,
+        )?);
+        *self.record_tap.lock().unwrap() = Some(producer);
+        Ok(())
+    }
+
+    pub fn stop_recording(&mut self) {
+        *self.record_tap.lock().unwrap() = None;
+        if let Some(recording) = self.recording.take() {
+            recording.stop();
+        }
+    }
+
+    /// Handle to the mix buffer for local mic monitoring.
+    pub fn monitor_sink(&self) -> MonitorSink {
+        MonitorSink {
+            producer: Arc::clone(&self.mix_producer),
+            device_rate: self.device_rate,
+            device_channels: self.device_channels,
+        }
+    }
 }
 
 /// Adapt between different channel counts.
@@ -0,0 +1,70 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use tauri::{AppHandle, Emitter};
+
+use super::audio_capture::{list_input_devices, list_output_devices};
+use super::types::AudioDeviceList;
+
+/// How often the device lists are re-enumerated.
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+fn snapshot() -> AudioDeviceList {
+    AudioDeviceList {
+        inputs: list_input_devices(),
+        outputs: list_output_devices(),
+    }
+}
+
+/// Watches for audio devices being plugged in or removed, emitting
+/// `voice:devices_changed` with the new lists. Owned by the `VoiceEngine`
+/// while a session is up; dropping it stops the polling after the current
+/// interval.
+pub struct DeviceWatcher {
+    stop: Arc<AtomicBool>,
+}
+
+impl DeviceWatcher {
+    /// Polls the cpal enumeration and only emits once a changed list has been
+    /// the same for two polls in a row, so a device that shows up in stages
+    /// (or an ALSA card being reprobed) produces one event rather than several.
+    pub fn spawn(app: AppHandle) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = Arc::clone(&stop);
+        std::thread::spawn(move || watch(app, thread_stop));
+        Self { stop }
+    }
+}
+
+impl Drop for DeviceWatcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+fn watch(app: AppHandle, stop: Arc<AtomicBool>) {
+    let mut current = snapshot();
+    let mut pending: Option<AudioDeviceList> = None;
+    loop {
+        std::thread::sleep(POLL_INTERVAL);
+        if stop.load(Ordering::Relaxed) {
+            break;
+        }
+        let latest = snapshot();
+        if latest == current {
+            pending = None;
+        } else if pending.as_ref() == Some(&latest) {
+            log::info!(
+                "Audio devices changed: {} inputs, {} outputs",
+                latest.inputs.len(),
+                latest.outputs.len()
+            );
+            let _ = app.emit("voice:devices_changed", &latest);
+            current = latest;
+            pending = None;
+        } else {
+            pending = Some(latest);
+        }
+    }
+}
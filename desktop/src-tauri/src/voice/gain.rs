@@ -0,0 +1,33 @@
+//! Linear per-sample ramp toward a target gain, so slider moves don't click.
+//! Lives inside the audio callback; the target is read from the shared setting
+//! once per callback.
+
+const RAMP_MS: f32 = 20.0; // time for a full 0 → 1 change
+
+pub struct GainRamp {
+    current: f32,
+    step: f32,
+}
+
+impl GainRamp {
+    /// `channels` accounts for interleaved buffers: one step per sample, not per frame.
+    pub fn new(initial: f32, sample_rate: u32, channels: usize) -> Self {
+        let samples = RAMP_MS / 1000.0 * sample_rate as f32 * channels.max(1) as f32;
+        Self {
+            current: initial,
+            step: 1.0 / samples.max(1.0),
+        }
+    }
+
+    /// Gain for the next sample, moved at most one step toward `target`.
+    #[inline]
+    pub fn next(&mut self, target: f32) -> f32 {
+        let diff = target - self.current;
+        if diff.abs() <= self.step {
+            self.current = target;
+        } else {
+            self.current += self.step.copysign(diff);
+        }
+        self.current
+    }
+}
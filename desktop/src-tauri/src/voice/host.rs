@@ -0,0 +1,60 @@
+//! Which cpal host (audio backend) capture, playback and device listing use.
+//! Process-wide, since the device watcher and the list commands enumerate
+//! devices without the engine. Linux builds only have ALSA unless built with
+//! the `jack` feature.
+
+use std::sync::Mutex;
+
+use cpal::HostId;
+
+/// Host chosen with `voice_set_audio_host`; None = cpal's default.
+static SELECTED: Mutex<Option<HostId>> = Mutex::new(None);
+
+/// Names of the hosts compiled in and present on this system ("ALSA",
+/// "JACK", "WASAPI", "CoreAudio", ...).
+pub fn available() -> Vec<String> {
+    cpal::available_hosts()
+        .into_iter()
+        .map(|id| id.name().to_string())
+        .collect()
+}
+
+/// Use the host called `name` (case-insensitive) from now on. One that isn't
+/// available or can't be opened (e.g. no JACK server running) selects the
+/// default instead. Returns the name of the host now in use.
+pub fn select(name: &str) -> String {
+    let selected = match cpal::available_hosts()
+        .into_iter()
+        .find(|id| id.name().eq_ignore_ascii_case(name))
+    {
+        Some(id) => match cpal::host_from_id(id) {
+            Ok(_) => Some(id),
+            Err(e) => {
+                log::warn!("Audio host {} unavailable ({}), using default", id.name(), e);
+                None
+            }
+        },
+        None => {
+            log::warn!("Audio host '{}' not available, using default", name);
+            None
+        }
+    };
+    *SELECTED.lock().unwrap() = selected;
+    current().id().name().to_string()
+}
+
+/// The selected host, or the default one. A selected host that stops
+/// opening (JACK server quit) is dropped so this doesn't retry every call.
+pub fn current() -> cpal::Host {
+    let mut selected = SELECTED.lock().unwrap();
+    if let Some(id) = *selected {
+        match cpal::host_from_id(id) {
+            Ok(host) => return host,
+            Err(e) => {
+                log::warn!("Audio host {} unavailable ({}), using default", id.name(), e);
+                *selected = None;
+            }
+        }
+    }
+    cpal::default_host()
+}
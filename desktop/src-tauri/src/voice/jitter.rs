@@ -0,0 +1,108 @@
+use std::collections::BTreeMap;
+
+use bytes::Bytes;
+
+const FRAME_MS: u64 = 20;
+/// Longer gaps are skipped rather than concealed — PLC only sounds right for short losses.
+const MAX_CONCEAL_PACKETS: u64 = 5;
+/// A jump this far from the expected sequence is a sender restart, not loss.
+const RESYNC_PACKETS: u64 = 100;
+/// Extended sequence numbers start here so unwrapping never goes below zero.
+const SEQ_OFFSET: u64 = 1 << 32;
+
+pub enum JitterOutput {
+    /// Next packet in sequence order.
+    Packet(Bytes),
+    /// The next packet never arrived. `fec` is the packet after it, if already
+    /// buffered — its in-band FEC can rebuild the lost audio.
+    Lost { fec: Option<Bytes> },
+}
+
+/// Per-track reorder buffer for incoming Opus RTP.
+///
+/// In-order packets are released immediately, so a clean stream gets no added
+/// delay. When a packet is missing, later packets are held until
+/// the depth's worth of them has arrived; if the gap is still open by then
+/// it's reported as lost so the decoder can conceal it (FEC or PLC).
+pub struct JitterBuffer {
+    /// Buffered payloads keyed by extended (wrap-free) sequence number.
+    packets: BTreeMap<u64, Bytes>,
+    /// Extended sequence number of the next packet to release; None until the first packet.
+    next: Option<u64>,
+    /// Highest extended sequence number seen.
+    highest: u64,
+    /// Packets past a hole to wait for before concealing it.
+    depth_packets: u64,
+}
+
+impl JitterBuffer {
+    /// `depth_ms`: how long a missing packet is waited for before it's concealed.
+    pub fn new(depth_ms: u32) -> Self {
+        let mut buffer = Self {
+            packets: BTreeMap::new(),
+            next: None,
+            highest: 0,
+            depth_packets: 1,
+        };
+        buffer.set_depth_ms(depth_ms);
+        buffer
+    }
+
+    /// Change the depth; applies to the next `pop`. At least one frame.
+    pub fn set_depth_ms(&mut self, depth_ms: u32) {
+        self.depth_packets = (depth_ms as u64 / FRAME_MS).max(1);
+    }
+
+    /// Buffer a packet. Packets older than what was already released are dropped.
+    pub fn push(&mut self, seq: u16, payload: Bytes) {
+        let Some(next) = self.next else {
+            self.highest = SEQ_OFFSET + seq as u64;
+            self.next = Some(self.highest);
+            self.packets.insert(self.highest, payload);
+            return;
+        };
+
+        // Unwrap against the highest sequence seen: the nearest value with these low 16 bits
+        let delta = seq.wrapping_sub(self.highest as u16) as i16;
+        let ext = (self.highest as i64 + delta as i64) as u64;
+
+        if ext + RESYNC_PACKETS < next || ext >= next + RESYNC_PACKETS {
+            // Sender restarted or a long outage — start over from this packet
+            self.packets.clear();
+            self.next = Some(ext);
+            self.highest = ext;
+        } else if ext < next {
+            return; // too late, already played or concealed
+        }
+
+        self.highest = self.highest.max(ext);
+        self.packets.insert(ext, payload);
+    }
+
+    /// Next item to decode, or None to wait for more packets.
+    pub fn pop(&mut self) -> Option<JitterOutput> {
+        let next = self.next?;
+        if let Some(payload) = self.packets.remove(&next) {
+            self.next = Some(next + 1);
+            return Some(JitterOutput::Packet(payload));
+        }
+
+        // Gap: keep waiting until depth_packets past the hole have arrived
+        if self.highest < next + self.depth_packets {
+            return None;
+        }
+
+        // Long gap: jump to the last few missing packets before the next one we have
+        if let Some(&first) = self.packets.keys().next() {
+            if first - next > MAX_CONCEAL_PACKETS {
+                self.next = Some(first - MAX_CONCEAL_PACKETS);
+                return self.pop();
+            }
+        }
+
+        self.next = Some(next + 1);
+        Some(JitterOutput::Lost {
+            fec: self.packets.get(&(next + 1)).cloned(),
+        })
+    }
+}
@@ -0,0 +1,106 @@
+//! Stand-in for the SFU in debug builds: a second peer connection in this
+//! process that answers the voice peer's offer and sends its audio straight
+//! back, so capture → encode → send → receive → decode → playback can run
+//! without a server.
+
+use std::sync::Arc;
+use webrtc::api::interceptor_registry::register_default_interceptors;
+use webrtc::api::media_engine::MediaEngine;
+use webrtc::api::APIBuilder;
+use webrtc::interceptor::registry::Registry;
+use webrtc::peer_connection::configuration::RTCConfiguration;
+use webrtc::peer_connection::sdp::session_description::RTCSessionDescription;
+use webrtc::peer_connection::RTCPeerConnection;
+use webrtc::rtp_transceiver::rtp_codec::{RTCRtpCodecCapability, RTCRtpCodecParameters, RTPCodecType};
+use webrtc::track::track_local::track_local_static_rtp::TrackLocalStaticRTP;
+use webrtc::track::track_local::{TrackLocal, TrackLocalWriter};
+use webrtc::track::track_remote::TrackRemote;
+
+/// Remote RTP read buffer, as for the voice peer's decode tasks.
+const RTP_READ_BUF: usize = 1500;
+
+pub struct LoopbackSfu {
+    pc: Arc<RTCPeerConnection>,
+}
+
+impl LoopbackSfu {
+    /// Answer `offer` (with all ICE candidates already in it) and start
+    /// echoing whatever audio arrives. `codec` is the voice peer's mic track
+    /// capability, so the echo negotiates the same Opus parameters. Returns
+    /// the answer with this side's candidates included, since there's no
+    /// signalling channel to trickle them over.
+    pub async fn answer(
+        offer: &str,
+        codec: RTCRtpCodecCapability,
+    ) -> Result<(Self, String), Box<dyn std::error::Error + Send + Sync>> {
+        let mut media_engine = MediaEngine::default();
+        media_engine.register_codec(
+            RTCRtpCodecParameters {
+                capability: codec.clone(),
+                payload_type: 111,
+                ..Default::default()
+            },
+            RTPCodecType::Audio,
+        )?;
+
+        let mut registry = Registry::new();
+        registry = register_default_interceptors(registry, &mut media_engine)?;
+
+        let api = APIBuilder::new()
+            .with_media_engine(media_engine)
+            .with_interceptor_registry(registry)
+            .build();
+
+        // No ICE servers: both ends are in this process, host candidates do
+        let pc = Arc::new(api.new_peer_connection(RTCConfiguration::default()).await?);
+
+        let echo = Arc::new(TrackLocalStaticRTP::new(
+            codec,
+            "audio".to_string(),
+            "loopback".to_string(),
+        ));
+        let rtp_sender = pc
+            .add_track(Arc::clone(&echo) as Arc<dyn TrackLocal + Send + Sync>)
+            .await?;
+        tokio::spawn(async move {
+            let mut buf = vec![0u8; RTP_READ_BUF];
+            while rtp_sender.read(&mut buf).await.is_ok() {}
+        });
+
+        pc.on_track(Box::new(move |track, _receiver, _transceiver| {
+            let echo = Arc::clone(&echo);
+            Box::pin(async move {
+                log::info!("Loopback: echoing {}", track.codec().capability.mime_type);
+                tokio::spawn(echo_track(track, echo));
+            })
+        }));
+
+        pc.set_remote_description(RTCSessionDescription::offer(offer.to_string())?)
+            .await?;
+        let answer = pc.create_answer(None).await?;
+        let mut gathered = pc.gathering_complete_promise().await;
+        pc.set_local_description(answer).await?;
+        let _ = gathered.recv().await;
+
+        let local_desc = pc.local_description().await.ok_or("no local description")?;
+        Ok((Self { pc }, local_desc.sdp))
+    }
+
+    /// Close the loopback connection.
+    pub async fn close(self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.pc.close().await?;
+        Ok(())
+    }
+}
+
+/// Send every packet of `track` back out on `echo` until the track ends.
+/// `write_rtp` restamps SSRC and payload type for the voice peer's binding.
+async fn echo_track(track: Arc<TrackRemote>, echo: Arc<TrackLocalStaticRTP>) {
+    let mut buf = vec![0u8; RTP_READ_BUF];
+    while let Ok((packet, _)) = track.read(&mut buf).await {
+        if let Err(e) = echo.write_rtp(&packet).await {
+            log::warn!("Loopback: echo write failed: {}", e);
+        }
+    }
+    log::info!("Loopback: remote track ended");
+}
@@ -1,19 +1,65 @@
 pub mod audio_capture;
 pub mod audio_playback;
+pub mod device_watcher;
+pub mod gain;
+pub mod host;
+pub mod jitter;
+#[cfg(debug_assertions)]
+pub mod loopback;
 pub mod peer;
+pub mod recorder;
+pub mod remote_tracks;
 pub mod resampler;
+pub mod soundboard;
 pub mod speaking;
 pub mod types;
 
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 use std::sync::Arc;
-use tauri::{AppHandle, Emitter};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
 use tokio::sync::Mutex;
+use webrtc::rtp_transceiver::rtp_codec::RTCRtpCodecParameters;
+use webrtc::rtp_transceiver::rtp_sender::RTCRtpSender;
+use webrtc::track::track_local::track_local_static_rtp::TrackLocalStaticRTP;
+use webrtc::track::track_local::TrackLocal;
 
 use audio_capture::{AudioCapture, CaptureEvent};
-use audio_playback::AudioPlayback;
+use audio_playback::{AudioPlayback, HealthReporter, LatencyMode};
 use peer::{Peer, PeerEvent};
+use jitter::{JitterBuffer, JitterOutput};
+use remote_tracks::{RemoteTrackState, RemoteTracks};
+use speaking::{SpeakingDetector, SpeakingParams};
 use types::*;
 
+const DEFAULT_RECONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const DEFAULT_RECONNECT_ATTEMPTS: u32 = 3;
+/// Remote RTP read buffer. webrtc never delivers more than its receive MTU
+/// (1460); anything bigger fails the read with "buffer: short" and is dropped.
+const RTP_READ_BUF: usize = 1500;
+/// Largest Opus packet a well-behaved sender produces (RFC 6716: 1275 bytes
+/// per frame, up to 120ms of frames), with room for padding.
+const MAX_OPUS_PAYLOAD: usize = 1500;
+/// Longest Opus packet in samples per channel (120ms at 48kHz). Senders may
+/// use anything from 2.5ms to this, so the decode buffer is sized for it.
+const MAX_OPUS_FRAME_SAMPLES: usize = 5760;
+/// Frames per block for the playback resampler. Decoded frames of any length
+/// are buffered into these blocks, so it doesn't have to match the sender.
+const DECODE_RESAMPLE_CHUNK: usize = 960;
+/// No packet for this long means the sender stopped (DTX silence or a stall)
+/// and the last sound gets faded out. Longer than the jitter buffer's depth.
+const DTX_GAP: Duration = Duration::from_millis(80);
+/// Fade-in length (samples per channel, 5ms) when audio resumes after a gap.
+const FADE_IN_FRAMES: usize = 240;
+/// Silence fed to a remote speaking detector during DTX, in 20ms frames.
+const SILENCE_FRAME_MS: u128 = 20;
+const SILENCE_FRAME_SAMPLES: usize = 960;
+/// How often `voice:stats` is emitted while a peer exists.
+const STATS_INTERVAL: Duration = Duration::from_secs(1);
+/// `voice:audio_health` goes out every this many stats ticks; drift needs a
+/// longer window than jitter to show.
+const HEALTH_TICKS: u32 = 5;
+
 /// Central voice engine — held as Tauri managed state behind Arc<Mutex<>>.
 pub struct VoiceEngine {
     peer: Option<Peer>,
@@ -21,7 +67,26 @@ pub struct VoiceEngine {
     playback: AudioPlayback,
     input_device: Option<String>,
     output_device: Option<String>,
+    ice_servers: Vec<IceServerConfig>,
+    /// Mic track, kept across peer rebuilds so capture never restarts.
+    local_track: Option<Arc<TrackLocalStaticRTP>>,
+    /// Peer created by `voice_prewarm` ahead of the first offer.
+    prewarmed_peer: Option<(Peer, tokio::sync::mpsc::UnboundedReceiver<PeerEvent>)>,
+    /// How long the connection may stay failed/disconnected before the peer is rebuilt.
+    reconnect_timeout: Duration,
+    reconnect_max_attempts: u32,
+    /// Rebuilds since the connection was last established; reset on "connected".
+    reconnect_attempts: Arc<AtomicU32>,
+    remote_tracks: RemoteTracks,
+    /// Tuning for the remote tracks' speaking/level detectors (UI only).
+    remote_speaking: Arc<std::sync::Mutex<SpeakingParams>>,
     event_handle: Option<tokio::task::JoinHandle<()>>,
+    stats_handle: Option<tokio::task::JoinHandle<()>>,
+    /// Emits `voice:devices_changed` while a session is up.
+    device_watcher: Option<device_watcher::DeviceWatcher>,
+    /// In-process stand-in SFU from `voice_start_loopback`.
+    #[cfg(debug_assertions)]
+    loopback: Option<loopback::LoopbackSfu>,
 }
 
 impl VoiceEngine {
@@ -32,7 +97,59 @@ impl VoiceEngine {
             playback: AudioPlayback::new(),
             input_device: None,
             output_device: None,
+            ice_servers: Vec::new(),
+            local_track: None,
+            prewarmed_peer: None,
+            reconnect_timeout: DEFAULT_RECONNECT_TIMEOUT,
+            reconnect_max_attempts: DEFAULT_RECONNECT_ATTEMPTS,
+            reconnect_attempts: Arc::new(AtomicU32::new(0)),
+            remote_tracks: RemoteTracks::default(),
+            remote_speaking: Arc::new(std::sync::Mutex::new(SpeakingParams::default())),
             event_handle: None,
+            stats_handle: None,
+            device_watcher: None,
+            #[cfg(debug_assertions)]
+            loopback: None,
+        }
+    }
+
+    /// Mic track for the next peer. Reused on reconnect so capture keeps running,
+    /// unless mono was toggled since it was created.
+    fn local_track(&mut self) -> Arc<TrackLocalStaticRTP> {
+        let mono = self.capture.is_mono();
+        match &self.local_track {
+            Some(track) if peer::is_mono_track(track) == mono => Arc::clone(track),
+            _ => {
+                let track = peer::new_local_track(mono);
+                self.local_track = Some(Arc::clone(&track));
+                track
+            }
+        }
+    }
+
+    /// Open playback, probe the input device and create the peer connection
+    /// before the first offer arrives, so joining a call doesn't wait on them.
+    async fn prewarm(&mut self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.start_playback()?;
+
+        if let Err(e) = audio_capture::probe_input_device(self.input_device.as_deref()) {
+            log::warn!("Input device probe failed: {}", e);
+        }
+
+        if self.peer.is_none() && self.prewarmed_peer.is_none() {
+            let local_track = self.local_track();
+            self.prewarmed_peer =
+                Some(Peer::new(&self.ice_servers, local_track, &screen_video_codecs()).await?);
+        }
+        Ok(())
+    }
+
+    /// Close a prewarmed peer that will no longer be used.
+    fn discard_prewarmed_peer(&mut self) {
+        if let Some((peer, _)) = self.prewarmed_peer.take() {
+            tokio::spawn(async move {
+                let _ = peer.close().await;
+            });
         }
     }
 
@@ -44,6 +161,13 @@ impl VoiceEngine {
         Ok(())
     }
 
+    /// Start watching for device hotplug, unless already watching.
+    fn watch_devices(&mut self, app: &AppHandle) {
+        if self.device_watcher.is_none() {
+            self.device_watcher = Some(device_watcher::DeviceWatcher::spawn(app.clone()));
+        }
+    }
+
     /// Create peer connection, start capture, and begin event forwarding.
     async fn ensure_peer(
         &mut self,
@@ -54,12 +178,33 @@ impl VoiceEngine {
         }
 
         self.start_playback()?;
+        self.watch_devices(app);
 
-        let (peer, peer_rx) = Peer::new().await?;
+        let previous_track = self.local_track.clone();
+        let local_track = self.local_track();
+        if self
+            .prewarmed_peer
+            .as_ref()
+            .is_some_and(|(peer, _)| !Arc::ptr_eq(&peer.local_track, &local_track))
+        {
+            self.discard_prewarmed_peer();
+        }
+        let (peer, peer_rx) = match self.prewarmed_peer.take() {
+            Some(prewarmed) => prewarmed,
+            None => {
+                Peer::new(&self.ice_servers, Arc::clone(&local_track), &screen_video_codecs())
+                    .await?
+            }
+        };
 
         // Start mic capture, writing RTP to the peer's local track
-        self.capture
-            .start(self.input_device.as_deref(), Arc::clone(&peer.local_track))?;
+        if !self.capture.is_running() {
+            self.start_capture(app, local_track)?;
+        } else if previous_track.is_some_and(|track| !Arc::ptr_eq(&track, &local_track)) {
+            // Mono was toggled, so the track was replaced: move capture onto it
+            self.capture.stop();
+            self.start_capture(app, local_track)?;
+        }
 
         // Take capture speaking events
         let capture_rx = self.capture.event_rx.take();
@@ -68,34 +213,284 @@ impl VoiceEngine {
         let mix_producer = Arc::clone(&self.playback.mix_producer);
         let device_rate = self.playback.device_rate;
         let device_channels = self.playback.device_channels;
+        let reconnect_timeout = self.reconnect_timeout;
+        let reconnect_attempts = Arc::clone(&self.reconnect_attempts);
+        let remote_tracks = Arc::clone(&self.remote_tracks);
+        let remote_speaking = Arc::clone(&self.remote_speaking);
+        let deafened = Arc::clone(&self.playback.deafened);
+        let jitter_depth_ms = Arc::clone(&self.playback.jitter_depth_ms);
 
         // Spawn event forwarding: peer events + speaking → frontend
         let app_handle = app.clone();
         let event_handle = tokio::spawn(async move {
-            run_event_loop(app_handle, peer_rx, capture_rx, mix_producer, device_rate, device_channels).await;
+            let exit = run_event_loop(
+                app_handle.clone(),
+                peer_rx,
+                capture_rx,
+                mix_producer,
+                device_rate,
+                device_channels,
+                remote_tracks,
+                remote_speaking,
+                deafened,
+                jitter_depth_ms,
+                reconnect_timeout,
+                reconnect_attempts,
+            )
+            .await;
+            if let LoopExit::Reconnect(capture_rx) = exit {
+                reconnect(app_handle, capture_rx).await;
+            }
         });
         self.event_handle = Some(event_handle);
+
+        // Poll stats off the engine lock and push them to the frontend,
+        // along with the playback buffer's health
+        let pc = peer.connection();
+        let app_handle = app.clone();
+        let mut health = HealthReporter::new(self.playback.health());
+        self.stats_handle = Some(tokio::spawn(async move {
+            let mut interval = tokio::time::interval(STATS_INTERVAL);
+            let mut ticks: u32 = 0;
+            loop {
+                interval.tick().await;
+                let _ = app_handle.emit("voice:stats", &peer::peer_stats(&pc).await);
+
+                ticks += 1;
+                if ticks % HEALTH_TICKS == 0 {
+                    let report = health.report();
+                    if report.underruns > 0 || report.overruns > 0 {
+                        log::warn!(
+                            "Playback buffer: {} underruns, {} overruns, {:.0}ms resynced, drift {:.0}ppm",
+                            report.underruns,
+                            report.overruns,
+                            report.resynced_ms,
+                            report.drift_ppm
+                        );
+                    }
+                    let _ = app_handle.emit("voice:audio_health", &report);
+                }
+            }
+        }));
+
         self.peer = Some(peer);
         Ok(())
     }
 
-    fn stop(&mut self) {
+    /// Reopen a running mic capture so device/rate changes take effect.
+    fn restart_capture(
+        &mut self,
+        app: &AppHandle,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        if !self.capture.is_running() {
+            return Ok(());
+        }
+        if let Some(peer) = &self.peer {
+            let track = Arc::clone(&peer.local_track);
+            self.capture.stop();
+            self.start_capture(app, track)?;
+        }
+        Ok(())
+    }
+
+    /// Start mic capture on the selected device, falling back to the default
+    /// device if it can't be opened.
+    fn start_capture(
+        &mut self,
+        app: &AppHandle,
+        track: Arc<TrackLocalStaticRTP>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let monitor_sink = self.playback.monitor_sink();
+        match self.capture.start(
+            self.input_device.as_deref(),
+            Arc::clone(&track),
+            monitor_sink,
+        ) {
+            Err(e) if self.input_device.is_some() => {
+                self.fall_back_to_default_input(app, track, e.to_string())
+            }
+            result => result,
+        }
+    }
+
+    /// The input device went away: forget the selection, tell the frontend
+    /// (`voice:device_lost`) and capture from the system default instead
+    /// (`voice:device_changed` once it's running).
+    fn fall_back_to_default_input(
+        &mut self,
+        app: &AppHandle,
+        track: Arc<TrackLocalStaticRTP>,
+        reason: String,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let device = self.input_device.take();
+        log::warn!(
+            "Input device {} lost ({}), switching to default",
+            device.as_deref().unwrap_or("(default)"),
+            reason
+        );
+        let _ = app.emit("voice:device_lost", &DeviceLostEvent { device, reason });
+
         self.capture.stop();
+        self.capture.start(None, track, self.playback.monitor_sink())?;
+        let _ = app.emit(
+            "voice:device_changed",
+            &DeviceChangedEvent {
+                device: self.capture.device_name(),
+            },
+        );
+        Ok(())
+    }
+
+    pub fn status(&self) -> VoiceStatus {
+        VoiceStatus {
+            connected: self.peer.is_some(),
+            capturing: self.capture.is_running(),
+            playing: self.playback.is_running(),
+            muted: self.capture.is_muted(),
+            deafened: self.playback.is_deafened(),
+            master_volume: self.playback.master_volume(),
+            mic_gain: self.capture.mic_gain(),
+            input_device: self.input_device.clone(),
+            output_device: self.output_device.clone(),
+        }
+    }
+
+    /// Tear the session down and wait for it: the capture and event tasks
+    /// have finished and the peers are closed when this returns, so a new
+    /// `voice_start` can't race the old session.
+    async fn stop(&mut self) {
+        self.capture.stop_and_wait().await;
         self.playback.stop();
+        self.device_watcher = None;
         if let Some(handle) = self.event_handle.take() {
             handle.abort();
+            let _ = handle.await;
+        }
+        if let Some(handle) = self.stats_handle.take() {
+            handle.abort();
+            let _ = handle.await;
         }
         if let Some(peer) = self.peer.take() {
-            tokio::spawn(async move {
-                let _ = peer.close().await;
-            });
+            if let Err(e) = peer.close().await {
+                log::warn!("Voice peer close failed: {}", e);
+            }
+        }
+        if let Some((peer, _)) = self.prewarmed_peer.take() {
+            let _ = peer.close().await;
+        }
+        #[cfg(debug_assertions)]
+        if let Some(sfu) = self.loopback.take() {
+            let _ = sfu.close().await;
         }
+        self.local_track = None;
+        self.remote_tracks.lock().unwrap().clear();
+        self.reconnect_attempts.store(0, Ordering::Relaxed);
         log::info!("Voice engine stopped");
     }
+
+    /// Send `track` on the voice connection as well; see `voice_renegotiate`.
+    pub async fn add_track(
+        &self,
+        track: Arc<dyn TrackLocal + Send + Sync>,
+    ) -> Result<Arc<RTCRtpSender>, Box<dyn std::error::Error + Send + Sync>> {
+        let peer = self.peer.as_ref().ok_or("no peer connection")?;
+        peer.add_track(track).await
+    }
+
+    /// Offer for the voice connection's current tracks.
+    pub async fn create_offer(&self) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        let peer = self.peer.as_ref().ok_or("no peer connection")?;
+        peer.create_offer().await
+    }
+
+    /// Create the peer and connect it to a `LoopbackSfu`. The loopback is
+    /// stored before the answer is applied, so `stop` tears down both on
+    /// failure.
+    #[cfg(debug_assertions)]
+    async fn start_loopback(
+        &mut self,
+        app: &AppHandle,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.ensure_peer(app).await?;
+        let peer = self.peer.as_ref().ok_or("no peer connection")?;
+        peer.create_offer().await?;
+        let offer = peer.gathered_description().await?;
+        let (sfu, answer) = loopback::LoopbackSfu::answer(&offer, peer.local_track.codec()).await?;
+        self.loopback = Some(sfu);
+        let peer = self.peer.as_ref().ok_or("no peer connection")?;
+        peer.handle_answer(&answer).await
+    }
+}
+
+/// Video codecs registered on every voice peer, so a screen share's tracks can
+/// be added to it (`voice_add_screen_track`) without a new connection.
+#[cfg(any(target_os = "linux", windows))]
+fn screen_video_codecs() -> Vec<RTCRtpCodecParameters> {
+    crate::screen::peer::video_codecs()
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+fn screen_video_codecs() -> Vec<RTCRtpCodecParameters> {
+    Vec::new()
+}
+
+/// Why the event loop exited.
+enum LoopExit {
+    Closed,
+    /// Connection stayed down past the timeout. Carries the capture receiver
+    /// so the rebuilt peer's loop keeps forwarding speaking events.
+    Reconnect(Option<tokio::sync::mpsc::UnboundedReceiver<CaptureEvent>>),
+}
+
+/// Replace a dead peer connection with a fresh one. Capture and playback keep
+/// running — and with them mute/deafen/volume/gain/device settings, which live
+/// on `AudioCapture`/`AudioPlayback` rather than the peer — so the user never
+/// comes back unmuted. The frontend is expected to have the SFU send a new offer.
+/// Boxed because it re-enters `ensure_peer`, which spawns the loop that calls this.
+fn reconnect(
+    app: AppHandle,
+    capture_rx: Option<tokio::sync::mpsc::UnboundedReceiver<CaptureEvent>>,
+) -> std::pin::Pin<Box<dyn std::future::Future<Output = ()> + Send>> {
+    Box::pin(async move {
+        let state = app.state::<VoiceState>();
+        let mut engine = state.inner().lock().await;
+        if engine.peer.is_none() {
+            return; // stopped while we were waiting
+        }
+
+        let attempt = engine.reconnect_attempts.fetch_add(1, Ordering::Relaxed) + 1;
+        let max_attempts = engine.reconnect_max_attempts;
+        if attempt > max_attempts {
+            log::warn!("Voice reconnect: giving up after {} attempts", max_attempts);
+            let _ = app.emit(
+                "voice:reconnect_failed",
+                &ReconnectEvent { attempt: max_attempts, max_attempts },
+            );
+            return;
+        }
+
+        log::info!("Voice reconnect: attempt {}/{}", attempt, max_attempts);
+        let _ = app.emit("voice:reconnecting", &ReconnectEvent { attempt, max_attempts });
+
+        // This task is the old event loop's — drop its handle without aborting ourselves
+        engine.event_handle = None;
+        if let Some(handle) = engine.stats_handle.take() {
+            handle.abort();
+        }
+        engine.capture.event_rx = capture_rx;
+        if let Some(peer) = engine.peer.take() {
+            let _ = peer.close().await;
+        }
+        if let Err(e) = engine.ensure_peer(&app).await {
+            log::error!("Voice reconnect failed: {}", e);
+            let _ = app.emit("voice:reconnect_failed", &ReconnectEvent { attempt, max_attempts });
+        }
+    })
 }
 
 /// Event forwarding loop: peer events + capture speaking → frontend.
 /// Remote tracks get decoded and written to the mix buffer.
+#[allow(clippy::too_many_arguments)]
 async fn run_event_loop(
     app: AppHandle,
     mut peer_rx: tokio::sync::mpsc::UnboundedReceiver<PeerEvent>,
@@ -103,7 +498,16 @@ async fn run_event_loop(
     mix_producer: Arc<std::sync::Mutex<Option<ringbuf::HeapProd<f32>>>>,
     device_rate: u32,
     device_channels: usize,
-) {
+    remote_tracks: RemoteTracks,
+    remote_speaking: Arc<std::sync::Mutex<SpeakingParams>>,
+    deafened: Arc<AtomicBool>,
+    jitter_depth_ms: Arc<AtomicU32>,
+    reconnect_timeout: Duration,
+    reconnect_attempts: Arc<AtomicU32>,
+) -> LoopExit {
+    // Set while the connection is failed/disconnected
+    let mut down_deadline: Option<tokio::time::Instant> = None;
+
     loop {
         tokio::select! {
             Some(event) = peer_rx.recv() => {
@@ -111,16 +515,36 @@ async fn run_event_loop(
                     PeerEvent::IceCandidate(candidate) => {
                         let _ = app.emit("voice:ice_candidate", &candidate);
                     }
+                    PeerEvent::IceGatheringComplete => {
+                        let _ = app.emit("voice:ice_gathering_complete", ());
+                    }
                     PeerEvent::RemoteTrack(track) => {
                         log::info!("Remote track received, spawning decode task");
                         spawn_decode_task(
+                            app.clone(),
                             track,
                             Arc::clone(&mix_producer),
                             device_rate,
                             device_channels,
+                            Arc::clone(&remote_tracks),
+                            Arc::clone(&remote_speaking),
+                            Arc::clone(&deafened),
+                            Arc::clone(&jitter_depth_ms),
                         );
                     }
                     PeerEvent::ConnectionState(state) => {
+                        match state.as_str() {
+                            "connected" => {
+                                down_deadline = None;
+                                reconnect_attempts.store(0, Ordering::Relaxed);
+                            }
+                            "failed" | "disconnected" => {
+                                if down_deadline.is_none() {
+                                    down_deadline = Some(tokio::time::Instant::now() + reconnect_timeout);
+                                }
+                            }
+                            _ => {}
+                        }
                         let _ = app.emit(
                             "voice:connection_state",
                             &ConnectionStateEvent { state },
@@ -128,6 +552,15 @@ async fn run_event_loop(
                     }
                 }
             }
+            _ = async {
+                match down_deadline {
+                    Some(deadline) => tokio::time::sleep_until(deadline).await,
+                    None => std::future::pending().await,
+                }
+            } => {
+                log::warn!("Voice connection down for {:?}, rebuilding peer", reconnect_timeout);
+                return LoopExit::Reconnect(capture_rx);
+            }
             Some(event) = async {
                 match capture_rx.as_mut() {
                     Some(rx) => rx.recv().await,
@@ -141,22 +574,74 @@ async fn run_event_loop(
                             &SpeakingEvent { speaking },
                         );
                     }
+                    CaptureEvent::DeviceError { stream, reason } => {
+                        handle_input_device_error(&app, stream, reason).await;
+                    }
                 }
             }
             else => break,
         }
     }
+    LoopExit::Closed
+}
+
+/// An input stream reported an error. cpal can report several per stream, so
+/// only errors from the stream that's currently running get acted on.
+async fn handle_input_device_error(app: &AppHandle, stream: u64, reason: String) {
+    let state = app.state::<VoiceState>();
+    let mut engine = state.inner().lock().await;
+    let Some(track) = engine.peer.as_ref().map(|p| Arc::clone(&p.local_track)) else {
+        return;
+    };
+    if !engine.capture.is_running() || engine.capture.stream_id() != stream {
+        return;
+    }
+    if let Err(e) = engine.fall_back_to_default_input(app, track, reason) {
+        log::error!("No input device to fall back to: {}", e);
+    }
 }
 
 /// Spawn a decode task for a single remote track.
+/// The track is listed in `remote_tracks` while the task runs. While
+/// `deafened`, packets are still read (so RTCP receiver reports keep flowing)
+/// but not decoded. The jitter buffer follows `jitter_depth_ms`, which the
+/// latency mode sets.
+#[allow(clippy::too_many_arguments)]
 fn spawn_decode_task(
+    app: AppHandle,
     track: Arc<webrtc::track::track_remote::TrackRemote>,
     mix_producer: Arc<std::sync::Mutex<Option<ringbuf::HeapProd<f32>>>>,
     device_rate: u32,
     device_channels: usize,
+    remote_tracks: RemoteTracks,
+    speaking_params: Arc<std::sync::Mutex<SpeakingParams>>,
+    deafened: Arc<AtomicBool>,
+    jitter_depth_ms: Arc<AtomicU32>,
 ) {
     tokio::spawn(async move {
-        use ringbuf::traits::Producer;
+        // The SFU forwards every peer's mic with the same track id ("audio"),
+        // so tracks are told apart by SSRC, which is unique per forwarded track
+        let ssrc = track.ssrc();
+        let track_id = ssrc.to_string();
+        let emit_speaking = |speaking: Option<bool>| {
+            if let Some(speaking) = speaking {
+                let _ = app.emit(
+                    "voice:peer_speaking",
+                    &PeerSpeakingEvent { track_id: track_id.clone(), ssrc, speaking },
+                );
+            }
+        };
+        let state = {
+            let mut tracks = remote_tracks.lock().unwrap();
+            let state = Arc::new(RemoteTrackState::new(
+                track.codec().capability.mime_type,
+                tracks.len(),
+            ));
+            tracks.insert(track_id.clone(), Arc::clone(&state));
+            state
+        };
+        let mut speaking_detector = SpeakingDetector::with_params(*speaking_params.lock().unwrap());
+
         let mut decoder = match opus::Decoder::new(48000, opus::Channels::Stereo) {
             Ok(d) => d,
             Err(e) => {
@@ -167,22 +652,68 @@ fn spawn_decode_task(
 
         let needs_resample = device_rate != 48000;
         let mut resampler = if needs_resample {
-            Some(resampler::AudioResampler::new(48000, device_rate, 960, 2))
+            Some(resampler::AudioResampler::new(48000, device_rate, DECODE_RESAMPLE_CHUNK, 2))
         } else {
             None
         };
 
-        let mut pcm_buf = vec![0i16; 960 * 2];
-        let mut rtp_buf = vec![0u8; 4000];
+        let mut pcm_buf = vec![0i16; MAX_OPUS_FRAME_SAMPLES * 2];
+        let mut rtp_buf = vec![0u8; RTP_READ_BUF];
+        let mut oversized: u64 = 0;
+        let mut jitter = JitterBuffer::new(jitter_depth_ms.load(Ordering::Relaxed));
+        // Samples per channel of the last decoded frame; sizes loss concealment
+        let mut frame_size: usize = 960;
+
+        // False once the sender has gone quiet (DTX) and the tail was faded out
+        let mut playing = false;
+        // Packets are being dropped undecoded because we're deafened
+        let mut skipping = false;
 
         loop {
+            // Decoded 48kHz stereo frames to play out
+            let mut frames: Vec<Vec<f32>> = Vec::new();
+
             // track.read returns (Packet, Attributes) directly
-            let (packet, _attrs) = match track.read(&mut rtp_buf).await {
+            let read = tokio::time::timeout(DTX_GAP, track.read(&mut rtp_buf)).await;
+            let Ok(read) = read else {
+                if skipping {
+                    continue;
+                }
+                // Nothing for a while — usually DTX during silence. End on a
+                // short concealed frame faded to zero rather than cutting off.
+                if playing {
+                    playing = false;
+                    if let Ok(n) = decoder.decode(&[], &mut pcm_buf[..frame_size * 2], false) {
+                        let mut frame = pcm_to_f32(&pcm_buf[..n * 2]);
+                        apply_fade(&mut frame, false);
+                        frames.push(frame);
+                    }
+                    emit_speaking(play_frames(frames, &mut speaking_detector, &speaking_params, &state, &mut resampler, device_channels, &mix_producer));
+                } else {
+                    // Already silent: let the detector hear the gap so the
+                    // indicator releases after its hold instead of sticking
+                    let silence = vec![0.0f32; SILENCE_FRAME_SAMPLES * 2];
+                    for _ in 0..DTX_GAP.as_millis() / SILENCE_FRAME_MS {
+                        emit_speaking(detect_speaking(&silence, &mut speaking_detector, &speaking_params, &state));
+                    }
+                }
+                continue;
+            };
+            let (packet, _attrs) = match read {
                 Ok(r) => r,
                 Err(e) => {
-                    if e.to_string().contains("closed") {
+                    let msg = e.to_string();
+                    if msg.contains("closed") {
                         break;
                     }
+                    // Oversized packet — webrtc already dropped it, keep reading
+                    if msg.contains("short") {
+                        oversized += 1;
+                        if oversized == 1 || oversized % 500 == 0 {
+                            log::warn!("Dropped oversized RTP packet ({} so far)", oversized);
+                        }
+                        continue;
+                    }
                     log::error!("Remote track read error: {}", e);
                     break;
                 }
@@ -191,60 +722,222 @@ fn spawn_decode_task(
             if packet.payload.is_empty() {
                 continue;
             }
-
-            let decoded = match decoder.decode(&packet.payload, &mut pcm_buf, false) {
-                Ok(n) => n,
-                Err(e) => {
-                    log::error!("Opus decode error: {}", e);
-                    continue;
+            // Don't hand the decoder anything a real Opus sender wouldn't produce
+            if packet.payload.len() > MAX_OPUS_PAYLOAD {
+                oversized += 1;
+                if oversized == 1 || oversized % 500 == 0 {
+                    log::warn!(
+                        "Dropped oversized Opus payload of {} bytes ({} so far)",
+                        packet.payload.len(),
+                        oversized
+                    );
                 }
-            };
-
-            // i16 → f32
-            let mut f32_samples: Vec<f32> = pcm_buf[..decoded * 2]
-                .iter()
-                .map(|&s| s as f32 / 32768.0)
-                .collect();
+                continue;
+            }
 
-            // Resample if needed
-            if let Some(ref mut rs) = resampler {
-                f32_samples = rs.process(&f32_samples);
+            // Deafened: nobody hears this track, so skip decode and resample.
+            // Speaking is cleared rather than left stuck where it was.
+            if deafened.load(Ordering::Relaxed) {
+                if !skipping {
+                    skipping = true;
+                    playing = false;
+                    speaking_detector = SpeakingDetector::with_params(*speaking_params.lock().unwrap());
+                    state.set_level(0.0);
+                    if state.is_speaking() {
+                        state.set_speaking(false);
+                        emit_speaking(Some(false));
+                    }
+                }
+                continue;
+            }
+            if skipping {
+                // Start over rather than conceal the whole gap as lost packets
+                skipping = false;
+                jitter = JitterBuffer::new(jitter_depth_ms.load(Ordering::Relaxed));
             }
 
-            // Adapt channels
-            let output = audio_playback::adapt_channels(&f32_samples, 2, device_channels);
+            jitter.set_depth_ms(jitter_depth_ms.load(Ordering::Relaxed));
+            jitter.push(packet.header.sequence_number, packet.payload);
 
-            // Write to ring buffer
-            if let Ok(mut guard) = mix_producer.lock() {
-                if let Some(ref mut prod) = *guard {
-                    for &sample in &output {
-                        let _ = prod.try_push(sample);
+            while let Some(item) = jitter.pop() {
+                let result = match item {
+                    JitterOutput::Packet(payload) => decoder.decode(&payload, &mut pcm_buf, false),
+                    // Rebuild the lost frame from the next packet's in-band FEC, else
+                    // plain PLC. Both conceal one frame of the last decoded size.
+                    JitterOutput::Lost { fec: Some(next) } => {
+                        decoder.decode(&next, &mut pcm_buf[..frame_size * 2], true)
+                    }
+                    JitterOutput::Lost { fec: None } => {
+                        decoder.decode(&[], &mut pcm_buf[..frame_size * 2], false)
                     }
+                };
+                let decoded = match result {
+                    Ok(n) => n,
+                    Err(e) => {
+                        log::error!("Opus decode error: {}", e);
+                        continue;
+                    }
+                };
+                if decoded > 0 {
+                    frame_size = decoded;
+                }
+
+                let mut frame = pcm_to_f32(&pcm_buf[..decoded * 2]);
+                // Coming back from silence: ramp in so the first frame doesn't pop
+                if !playing {
+                    playing = true;
+                    apply_fade(&mut frame, true);
                 }
+                frames.push(frame);
             }
+
+            emit_speaking(play_frames(frames, &mut speaking_detector, &speaking_params, &state, &mut resampler, device_channels, &mix_producer));
         }
 
+        if state.is_speaking() {
+            emit_speaking(Some(false));
+        }
+        remote_tracks.lock().unwrap().remove(&track_id);
         log::info!("Remote track decode task ended");
     });
 }
 
+/// i16 → f32
+fn pcm_to_f32(pcm: &[i16]) -> Vec<f32> {
+    pcm.iter().map(|&s| s as f32 / 32768.0).collect()
+}
+
+/// Linear ramp over the first (`fade_in`) or whole (fade out) stereo frame.
+/// Fading in only takes `FADE_IN_FRAMES` so speech onsets stay crisp.
+fn apply_fade(samples: &mut [f32], fade_in: bool) {
+    let frames = samples.len() / 2;
+    if fade_in {
+        let ramp = FADE_IN_FRAMES.min(frames);
+        for i in 0..ramp {
+            let g = i as f32 / ramp as f32;
+            samples[i * 2] *= g;
+            samples[i * 2 + 1] *= g;
+        }
+    } else {
+        for i in 0..frames {
+            let g = 1.0 - i as f32 / frames as f32;
+            samples[i * 2] *= g;
+            samples[i * 2 + 1] *= g;
+        }
+    }
+}
+
+/// Equal-power pan of a stereo frame, scaled so center is unity gain.
+fn apply_pan(samples: &mut [f32], pan: f32) {
+    if pan == 0.0 {
+        return;
+    }
+    let angle = (pan + 1.0) * std::f32::consts::FRAC_PI_4;
+    let left = angle.cos() * std::f32::consts::SQRT_2;
+    let right = angle.sin() * std::f32::consts::SQRT_2;
+    for frame in samples.chunks_exact_mut(2) {
+        frame[0] *= left;
+        frame[1] *= right;
+    }
+}
+
+/// Run one 48kHz stereo frame through the track's speaking detector,
+/// updating its level. Returns the new speaking state when it changed.
+fn detect_speaking(
+    samples: &[f32],
+    speaking_detector: &mut SpeakingDetector,
+    speaking_params: &std::sync::Mutex<SpeakingParams>,
+    state: &RemoteTrackState,
+) -> Option<bool> {
+    speaking_detector.set_params(*speaking_params.lock().unwrap());
+    let frame_ms = (samples.len() / 2) as f64 / 48.0;
+    let change = speaking_detector.process(samples, frame_ms);
+    if let Some(speaking) = change {
+        state.set_speaking(speaking);
+    }
+    state.set_level(speaking_detector.level());
+    change
+}
+
+/// Level/speaking, per-track mute and volume, then resample and write decoded
+/// frames into the playback mix. Returns the last speaking change, if any.
+#[allow(clippy::too_many_arguments)]
+fn play_frames(
+    frames: Vec<Vec<f32>>,
+    speaking_detector: &mut SpeakingDetector,
+    speaking_params: &std::sync::Mutex<SpeakingParams>,
+    state: &RemoteTrackState,
+    resampler: &mut Option<resampler::AudioResampler>,
+    device_channels: usize,
+    mix_producer: &std::sync::Mutex<Option<ringbuf::HeapProd<f32>>>,
+) -> Option<bool> {
+    use ringbuf::traits::Producer;
+    let mut speaking_change = None;
+    for mut f32_samples in frames {
+        // Level/speaking are measured before per-track mute and volume
+        if let Some(speaking) = detect_speaking(&f32_samples, speaking_detector, speaking_params, state) {
+            speaking_change = Some(speaking);
+        }
+        if state.is_muted() {
+            continue;
+        }
+        let volume = state.volume();
+        if volume != 1.0 {
+            for s in f32_samples.iter_mut() {
+                *s *= volume;
+            }
+        }
+
+        // Mono devices just get the downmix, centered
+        if device_channels > 1 {
+            apply_pan(&mut f32_samples, state.pan());
+        }
+
+        // Resample if needed
+        if let Some(ref mut rs) = resampler {
+            f32_samples = rs.process(&f32_samples);
+        }
+
+        // Adapt channels
+        let output = audio_playback::adapt_channels(&f32_samples, 2, device_channels);
+
+        // Write to ring buffer
+        if let Ok(mut guard) = mix_producer.lock() {
+            if let Some(ref mut prod) = *guard {
+                for &sample in &output {
+                    let _ = prod.try_push(sample);
+                }
+            }
+        }
+    }
+    speaking_change
+}
+
 // ── Tauri Commands ──────────────────────────────────────────────────────
 
 pub type VoiceState = Arc<Mutex<VoiceEngine>>;
 
 #[tauri::command]
 pub async fn voice_start(
-    _app: AppHandle,
+    app: AppHandle,
     state: tauri::State<'_, VoiceState>,
 ) -> Result<(), String> {
     let mut engine = state.inner().lock().await;
-    engine.start_playback().map_err(|e| e.to_string())
+    engine.start_playback().map_err(|e| e.to_string())?;
+    engine.watch_devices(&app);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn voice_prewarm(state: tauri::State<'_, VoiceState>) -> Result<(), String> {
+    let mut engine = state.inner().lock().await;
+    engine.prewarm().await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 pub async fn voice_stop(state: tauri::State<'_, VoiceState>) -> Result<(), String> {
     let mut engine = state.inner().lock().await;
-    engine.stop();
+    engine.stop().await;
     Ok(())
 }
 
@@ -283,6 +976,114 @@ pub async fn voice_handle_ice(
     }
 }
 
+/// Create the peer (starting capture and playback) and return our offer, for
+/// servers that expect the client to initiate instead of sending an offer to
+/// `voice_handle_offer`. The reply goes to `voice_handle_answer`.
+#[tauri::command]
+pub async fn voice_create_offer(
+    app: AppHandle,
+    state: tauri::State<'_, VoiceState>,
+) -> Result<SdpOffer, String> {
+    let mut engine = state.inner().lock().await;
+    engine.ensure_peer(&app).await.map_err(|e| e.to_string())?;
+    let sdp = engine.create_offer().await.map_err(|e| e.to_string())?;
+    Ok(SdpOffer { sdp })
+}
+
+/// Offer for the current voice connection after its tracks changed (e.g.
+/// `voice_add_screen_track`). Send it to the SFU and pass its answer to
+/// `voice_handle_answer`; until then the new tracks aren't sent.
+#[tauri::command]
+pub async fn voice_renegotiate(state: tauri::State<'_, VoiceState>) -> Result<SdpOffer, String> {
+    let engine = state.inner().lock().await;
+    let sdp = engine.create_offer().await.map_err(|e| e.to_string())?;
+    Ok(SdpOffer { sdp })
+}
+
+/// Debug builds only: connect the voice peer to an in-process stand-in SFU
+/// that sends the mic straight back, so the whole capture → playback path
+/// runs without a server. Candidates are exchanged in the SDP rather than
+/// trickled. `voice_stop` ends it; a reconnect doesn't rebuild it.
+#[cfg(debug_assertions)]
+#[tauri::command]
+pub async fn voice_start_loopback(
+    app: AppHandle,
+    state: tauri::State<'_, VoiceState>,
+) -> Result<(), String> {
+    let mut engine = state.inner().lock().await;
+    if engine.peer.is_some() {
+        return Err("voice peer already connected".to_string());
+    }
+    if let Err(e) = engine.start_loopback(&app).await {
+        // Don't leave a half-connected peer behind for the next voice_start
+        engine.stop().await;
+        return Err(e.to_string());
+    }
+    log::info!("Voice loopback connected");
+    Ok(())
+}
+
+/// Apply the SFU's answer to an offer from `voice_create_offer` or
+/// `voice_renegotiate`.
+#[tauri::command]
+pub async fn voice_handle_answer(
+    state: tauri::State<'_, VoiceState>,
+    sdp: String,
+) -> Result<(), String> {
+    let engine = state.inner().lock().await;
+    let peer = engine.peer.as_ref().ok_or("no peer connection")?;
+    peer.handle_answer(&sdp).await.map_err(|e| e.to_string())
+}
+
+/// RTT, loss and byte counts for the voice connection.
+#[tauri::command]
+pub async fn voice_get_stats(state: tauri::State<'_, VoiceState>) -> Result<PeerStats, String> {
+    let engine = state.inner().lock().await;
+    let peer = engine.peer.as_ref().ok_or("no peer connection")?;
+    Ok(peer.stats().await)
+}
+
+/// ICE gathering state of the voice connection ("new", "gathering", "complete").
+#[tauri::command]
+pub async fn voice_get_ice_gathering_state(state: tauri::State<'_, VoiceState>) -> Result<String, String> {
+    let engine = state.inner().lock().await;
+    let peer = engine.peer.as_ref().ok_or("no peer connection")?;
+    Ok(peer.ice_gathering_state())
+}
+
+/// Set the STUN/TURN servers for voice and screen peer connections.
+/// Applies to the next peer that gets created; an empty list restores the default STUN server.
+#[tauri::command]
+pub async fn voice_configure_ice(
+    _app: AppHandle,
+    state: tauri::State<'_, VoiceState>,
+    servers: Vec<IceServerConfig>,
+) -> Result<(), String> {
+    #[cfg(any(target_os = "linux", windows))]
+    if let Some(screen) = _app.try_state::<crate::screen::ScreenState>() {
+        screen.inner().lock().await.set_ice_servers(servers.clone());
+    }
+    let mut engine = state.inner().lock().await;
+    engine.ice_servers = servers;
+    // A prewarmed peer was built with the old servers
+    engine.discard_prewarmed_peer();
+    Ok(())
+}
+
+/// Configure automatic peer rebuild: how long the connection may stay
+/// failed/disconnected, and how many rebuilds to try before giving up.
+#[tauri::command]
+pub async fn voice_set_reconnect_policy(
+    state: tauri::State<'_, VoiceState>,
+    timeout_ms: u64,
+    max_attempts: u32,
+) -> Result<(), String> {
+    let mut engine = state.inner().lock().await;
+    engine.reconnect_timeout = Duration::from_millis(timeout_ms);
+    engine.reconnect_max_attempts = max_attempts;
+    Ok(())
+}
+
 #[tauri::command]
 pub async fn voice_set_mute(
     state: tauri::State<'_, VoiceState>,
@@ -313,6 +1114,80 @@ pub async fn voice_set_master_volume(
     Ok(())
 }
 
+/// Playback buffering: "low_latency" for minimal delay on good networks (may
+/// glitch under jitter), "smooth" (default) for fewer dropouts. See `LatencyMode`.
+#[tauri::command]
+pub async fn voice_set_latency_mode(
+    state: tauri::State<'_, VoiceState>,
+    mode: String,
+) -> Result<(), String> {
+    let mode = LatencyMode::from_name(&mode)
+        .ok_or_else(|| format!("unknown latency mode: {}", mode))?;
+    let engine = state.inner().lock().await;
+    engine.playback.set_latency_mode(mode);
+    Ok(())
+}
+
+/// Record the call as heard locally to a WAV file at `path`. Stops on
+/// `voice_stop_recording` or when leaving voice.
+#[tauri::command]
+pub async fn voice_start_recording(
+    state: tauri::State<'_, VoiceState>,
+    path: String,
+) -> Result<(), String> {
+    let mut engine = state.inner().lock().await;
+    engine.playback.start_recording(&path).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn voice_stop_recording(state: tauri::State<'_, VoiceState>) -> Result<(), String> {
+    let mut engine = state.inner().lock().await;
+    engine.playback.stop_recording();
+    Ok(())
+}
+
+/// Play a WAV clip to the channel, mixed with the mic at the current mic gain.
+/// Clips already playing keep going alongside it.
+#[tauri::command]
+pub async fn voice_play_sound(
+    state: tauri::State<'_, VoiceState>,
+    path: String,
+) -> Result<(), String> {
+    let clip = tokio::task::spawn_blocking(move || soundboard::Clip::load(&path))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())?;
+    let engine = state.inner().lock().await;
+    engine.capture.play_clip(clip);
+    Ok(())
+}
+
+/// Play a sine tone on the output device so the user can check their speakers,
+/// without a call: playback is started if it isn't running. Master volume
+/// applies, deafen doesn't.
+#[tauri::command]
+pub async fn voice_play_test_tone(
+    state: tauri::State<'_, VoiceState>,
+    freq: f32,
+    duration_ms: u32,
+) -> Result<(), String> {
+    if !freq.is_finite() {
+        return Err("invalid test tone frequency".into());
+    }
+    let mut engine = state.inner().lock().await;
+    engine.start_playback().map_err(|e| e.to_string())?;
+    engine.playback.play_test_tone(freq, duration_ms);
+    Ok(())
+}
+
+/// Stop every playing clip.
+#[tauri::command]
+pub async fn voice_stop_sound(state: tauri::State<'_, VoiceState>) -> Result<(), String> {
+    let engine = state.inner().lock().await;
+    engine.capture.stop_clips();
+    Ok(())
+}
+
 #[tauri::command]
 pub async fn voice_set_mic_gain(
     state: tauri::State<'_, VoiceState>,
@@ -323,53 +1198,230 @@ pub async fn voice_set_mic_gain(
     Ok(())
 }
 
+#[tauri::command]
+pub async fn voice_set_monitor(
+    state: tauri::State<'_, VoiceState>,
+    enabled: bool,
+) -> Result<(), String> {
+    let engine = state.inner().lock().await;
+    engine.capture.set_monitor(enabled);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn voice_set_opus_bitrate(
+    state: tauri::State<'_, VoiceState>,
+    bps: i32,
+) -> Result<(), String> {
+    let engine = state.inner().lock().await;
+    engine.capture.set_opus_bitrate(bps);
+    Ok(())
+}
+
+/// Send the mic as a mono downmix instead of stereo, at half the configured
+/// Opus bitrate. The encoder switches from its next frame and receivers follow
+/// (Opus marks mono/stereo in every packet), so audio keeps flowing. The SDP
+/// only advertises it (`sprop-stereo=0`) once the peer is rebuilt: the track's
+/// codec is fixed at creation, so the next peer — after a rejoin or reconnect —
+/// gets a new track and capture moves onto it. Renegotiations on the current
+/// peer keep the old fmtp.
+#[tauri::command]
+pub async fn voice_set_mono(
+    state: tauri::State<'_, VoiceState>,
+    enabled: bool,
+) -> Result<(), String> {
+    let engine = state.inner().lock().await;
+    engine.capture.set_mono(enabled);
+    Ok(())
+}
+
+/// Opus frame duration for outgoing voice: 10, 20 (default), 40 or 60ms.
+/// Takes effect between two packets of the running stream. The SFU and
+/// receivers have to accept the new packet time; nothing is renegotiated.
+#[tauri::command]
+pub async fn voice_set_frame_ms(
+    state: tauri::State<'_, VoiceState>,
+    ms: usize,
+) -> Result<(), String> {
+    let engine = state.inner().lock().await;
+    engine.capture.set_frame_ms(ms)
+}
+
+#[tauri::command]
+pub async fn voice_set_opus_complexity(
+    state: tauri::State<'_, VoiceState>,
+    level: i32,
+) -> Result<(), String> {
+    let engine = state.inner().lock().await;
+    engine.capture.set_opus_complexity(level);
+    Ok(())
+}
+
+/// Snapshot of every remote track currently being decoded.
+#[tauri::command]
+pub async fn voice_list_remote_tracks(
+    state: tauri::State<'_, VoiceState>,
+) -> Result<Vec<RemoteTrackInfo>, String> {
+    let engine = state.inner().lock().await;
+    let tracks = engine.remote_tracks.lock().unwrap();
+    Ok(tracks.iter().map(|(id, track)| track.info(id)).collect())
+}
+
+/// Place a remote speaker in the stereo field: -1 left, 0 center, 1 right.
+/// Speakers get spread out automatically as they join; this overrides it.
+/// `track_id` is the id from `voice_list_remote_tracks`/`voice:peer_speaking`.
+#[tauri::command]
+pub async fn voice_set_peer_pan(
+    state: tauri::State<'_, VoiceState>,
+    track_id: String,
+    pan: f32,
+) -> Result<(), String> {
+    let engine = state.inner().lock().await;
+    let tracks = engine.remote_tracks.lock().unwrap();
+    let track = tracks.get(&track_id).ok_or(format!("unknown track: {}", track_id))?;
+    track.set_pan(pan);
+    Ok(())
+}
+
+/// Tune when the local mic counts as speaking: the smoothed RMS `threshold`
+/// and how long (ms) the indicator stays lit after dropping below it.
+/// Attack/release keep their current values. Applies from the next frame.
+#[tauri::command]
+pub async fn voice_set_speaking_params(
+    state: tauri::State<'_, VoiceState>,
+    threshold: f32,
+    hold_ms: f64,
+) -> Result<(), String> {
+    if !threshold.is_finite() || !hold_ms.is_finite() {
+        return Err("speaking params must be finite".into());
+    }
+    let engine = state.inner().lock().await;
+    let mut params = engine.capture.speaking_params();
+    params.threshold = threshold.max(0.0);
+    params.hold_ms = hold_ms.max(0.0);
+    engine.capture.set_speaking_params(params);
+    Ok(())
+}
+
+/// Tune the remote speaking rings/levels (snappy vs. smooth). Only the receive
+/// side is affected — the transmit detector is set by `voice_set_speaking_params`. Omitted values
+/// are left unchanged; applies to running tracks from their next frame.
+#[tauri::command]
+pub async fn voice_set_remote_speaking_params(
+    state: tauri::State<'_, VoiceState>,
+    threshold: Option<f32>,
+    attack: Option<f32>,
+    release: Option<f32>,
+    hold_ms: Option<f64>,
+) -> Result<(), String> {
+    let engine = state.inner().lock().await;
+    let mut params = engine.remote_speaking.lock().unwrap();
+    if let Some(threshold) = threshold {
+        params.threshold = threshold.max(0.0);
+    }
+    if let Some(attack) = attack {
+        params.attack = attack.clamp(0.01, 1.0);
+    }
+    if let Some(release) = release {
+        params.release = release.clamp(0.01, 1.0);
+    }
+    if let Some(hold_ms) = hold_ms {
+        params.hold_ms = hold_ms.max(0.0);
+    }
+    Ok(())
+}
+
 #[tauri::command]
 pub async fn voice_list_devices() -> Result<AudioDeviceList, String> {
-    let inputs = audio_capture::list_input_devices()
-        .into_iter()
-        .map(|name| AudioDeviceInfo {
-            is_default: false,
-            name,
-        })
-        .collect();
-    let outputs = audio_capture::list_output_devices()
-        .into_iter()
-        .map(|name| AudioDeviceInfo {
-            is_default: false,
-            name,
-        })
-        .collect();
-    Ok(AudioDeviceList { inputs, outputs })
+    Ok(AudioDeviceList {
+        inputs: audio_capture::list_input_devices(),
+        outputs: audio_capture::list_output_devices(),
+    })
+}
+
+/// cpal hosts (audio backends) this build can use on this system, e.g.
+/// ["ALSA", "JACK"] on Linux built with the `jack` feature.
+#[tauri::command]
+pub async fn voice_list_hosts() -> Result<AudioHostList, String> {
+    Ok(AudioHostList {
+        hosts: host::available(),
+        active: host::current().id().name().to_string(),
+    })
+}
+
+/// Switch capture, playback and device listing to another cpal host
+/// ("ALSA", "JACK", ...). An unavailable host falls back to the default.
+/// Device ids belong to a host, so the selected devices are reset to the new
+/// host's defaults. Returns the host now in use.
+#[tauri::command]
+pub async fn voice_set_audio_host(
+    app: AppHandle,
+    state: tauri::State<'_, VoiceState>,
+    host: String,
+) -> Result<String, String> {
+    let active = host::select(&host);
+    log::info!("Audio host: {}", active);
+
+    let mut engine = state.inner().lock().await;
+    engine.input_device = None;
+    engine.output_device = None;
+    if engine.playback.is_running() {
+        engine.playback.stop();
+        engine.playback.start(None).map_err(|e| e.to_string())?;
+    }
+    engine.restart_capture(&app).map_err(|e| e.to_string())?;
+    Ok(active)
 }
 
+/// If the device can't be opened, capture falls back to the default device
+/// (see `voice:device_lost`).
 #[tauri::command]
 pub async fn voice_set_input_device(
+    app: AppHandle,
     state: tauri::State<'_, VoiceState>,
-    device_name: String,
+    device_id: String,
 ) -> Result<(), String> {
     let mut engine = state.inner().lock().await;
-    engine.input_device = Some(device_name);
-    if engine.capture.is_running() {
-        if let Some(peer) = &engine.peer {
-            let track = Arc::clone(&peer.local_track);
-            let device = engine.input_device.as_deref().map(|s| s.to_string());
-            engine.capture.stop();
-            engine
-                .capture
-                .start(device.as_deref(), track)
-                .map_err(|e| e.to_string())?;
-        }
-    }
+    engine.input_device = Some(device_id);
+    engine.restart_capture(&app).map_err(|e| e.to_string())
+}
+
+/// Hardware sample rate to open the mic at (e.g. 48000 to skip resampling).
+/// Falls back to the device default if unsupported. None restores the default.
+#[tauri::command]
+pub async fn voice_set_preferred_rate(
+    app: AppHandle,
+    state: tauri::State<'_, VoiceState>,
+    hz: Option<u32>,
+) -> Result<(), String> {
+    let mut engine = state.inner().lock().await;
+    engine.capture.set_preferred_rate(hz);
+    engine.restart_capture(&app).map_err(|e| e.to_string())
+}
+
+/// Ring buffer sizes for mic capture and playback, in ms of audio. cpal
+/// streams are built around their buffers, so this applies the next time each
+/// stream starts (next call, device switch or reconnect after a device loss),
+/// not to running streams. Values are clamped to a safe range.
+#[tauri::command]
+pub async fn voice_set_buffer_ms(
+    state: tauri::State<'_, VoiceState>,
+    capture_ms: u32,
+    playback_ms: u32,
+) -> Result<(), String> {
+    let mut engine = state.inner().lock().await;
+    engine.capture.set_buffer_ms(capture_ms);
+    engine.playback.set_buffer_ms(playback_ms);
     Ok(())
 }
 
 #[tauri::command]
 pub async fn voice_set_output_device(
     state: tauri::State<'_, VoiceState>,
-    device_name: String,
+    device_id: String,
 ) -> Result<(), String> {
     let mut engine = state.inner().lock().await;
-    engine.output_device = Some(device_name);
+    engine.output_device = Some(device_id);
     if engine.playback.is_running() {
         let device = engine.output_device.as_deref().map(|s| s.to_string());
         engine.playback.stop();
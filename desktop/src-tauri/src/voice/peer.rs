@@ -9,16 +9,139 @@ use webrtc::interceptor::registry::Registry;
 use webrtc::peer_connection::configuration::RTCConfiguration;
 use webrtc::peer_connection::sdp::session_description::RTCSessionDescription;
 use webrtc::peer_connection::RTCPeerConnection;
+use webrtc::stats::StatsReportType;
 use webrtc::rtp_transceiver::rtp_codec::{RTCRtpCodecCapability, RTCRtpCodecParameters, RTPCodecType};
+use webrtc::rtp_transceiver::rtp_sender::RTCRtpSender;
 use webrtc::track::track_local::track_local_static_rtp::TrackLocalStaticRTP;
 use webrtc::track::track_local::TrackLocal;
 use webrtc::track::track_remote::TrackRemote;
 
-use super::types::{IceCandidateIn, IceCandidateOut};
+use super::types::{IceCandidateIn, IceCandidateOut, IceServerConfig, PeerStats};
+
+const DEFAULT_STUN_URL: &str = "stun:stun.l.google.com:19302";
+
+/// Build the ICE server list for a peer connection.
+/// Falls back to Google's public STUN server when nothing is configured.
+pub fn ice_servers(config: &[IceServerConfig]) -> Vec<RTCIceServer> {
+    if config.is_empty() {
+        return vec![RTCIceServer {
+            urls: vec![DEFAULT_STUN_URL.to_string()],
+            ..Default::default()
+        }];
+    }
+    config
+        .iter()
+        .map(|server| RTCIceServer {
+            urls: server.urls.clone(),
+            username: server.username.clone().unwrap_or_default(),
+            credential: server.credential.clone().unwrap_or_default(),
+            ..Default::default()
+        })
+        .collect()
+}
+
+/// Encoding names ("opus", "H264", ...) from the rtpmap lines of every
+/// `m=<kind>` section of an SDP. None if the SDP has no such section.
+pub fn offered_codecs(sdp: &str, kind: &str) -> Option<Vec<String>> {
+    let mut found = false;
+    let mut in_section = false;
+    let mut codecs = Vec::new();
+    for line in sdp.lines() {
+        if let Some(media) = line.strip_prefix("m=") {
+            in_section = media.split_whitespace().next() == Some(kind);
+            found |= in_section;
+        } else if let Some(map) = line.strip_prefix("a=rtpmap:").filter(|_| in_section) {
+            if let Some(name) = map.split_whitespace().nth(1).and_then(|e| e.split('/').next()) {
+                codecs.push(name.to_string());
+            }
+        }
+    }
+    found.then_some(codecs)
+}
+
+/// Fail with `codec_negotiation_failed` unless the offer has a `kind` section
+/// carrying one of `supported`. Otherwise the answer would succeed with no
+/// usable media and the call would just be silent.
+pub fn require_codec(sdp: &str, kind: &str, supported: &[&str]) -> Result<(), String> {
+    let offered = offered_codecs(sdp, kind).unwrap_or_default();
+    if offered
+        .iter()
+        .any(|o| supported.iter().any(|s| o.eq_ignore_ascii_case(s)))
+    {
+        return Ok(());
+    }
+    Err(format!(
+        "codec_negotiation_failed: {} offered [{}], supported [{}]",
+        kind,
+        offered.join(", "),
+        supported.join(", ")
+    ))
+}
+
+/// fmtp parameter telling the other side we send mono (RFC 7587).
+const SPROP_MONO: &str = "sprop-stereo=0";
+
+/// Opus codec for the mic track, matching the Go SFU. The rtpmap always says
+/// 2 channels — RFC 7587 requires it even for mono, and browsers reject
+/// anything else — so a mono sender is signalled with `sprop-stereo=0`.
+fn opus_capability(mono: bool) -> RTCRtpCodecCapability {
+    let mut sdp_fmtp_line =
+        "minptime=10;useinbandfec=1;usedtx=1;maxaveragebitrate=128000".to_string();
+    if mono {
+        sdp_fmtp_line.push(';');
+        sdp_fmtp_line.push_str(SPROP_MONO);
+    }
+    RTCRtpCodecCapability {
+        mime_type: "audio/opus".to_string(),
+        clock_rate: 48000,
+        channels: 2,
+        sdp_fmtp_line,
+        ..Default::default()
+    }
+}
+
+/// Create the local Opus track that mic audio is written to.
+/// Owned by the engine so it can outlive a single peer connection.
+/// The capability can't change after creation, so switching to or from
+/// `mono` means a new track (and peer connection).
+pub fn new_local_track(mono: bool) -> Arc<TrackLocalStaticRTP> {
+    Arc::new(TrackLocalStaticRTP::new(
+        opus_capability(mono),
+        "audio".to_string(),
+        "voice".to_string(),
+    ))
+}
+
+/// Whether `track` was created to advertise mono.
+pub fn is_mono_track(track: &TrackLocalStaticRTP) -> bool {
+    track.codec().sdp_fmtp_line.split(';').any(|p| p == SPROP_MONO)
+}
+
+/// Summarise `pc.get_stats()`: RTT from the nominated candidate pair, RTP
+/// byte counts summed over all streams, loss from the remote's receiver reports.
+pub async fn peer_stats(pc: &RTCPeerConnection) -> PeerStats {
+    let mut stats = PeerStats::default();
+    for report in pc.get_stats().await.reports.values() {
+        match report {
+            StatsReportType::CandidatePair(pair) if pair.nominated => {
+                if pair.current_round_trip_time > 0.0 {
+                    stats.rtt_ms = Some(pair.current_round_trip_time * 1000.0);
+                }
+            }
+            StatsReportType::OutboundRTP(rtp) => stats.bytes_sent += rtp.bytes_sent,
+            StatsReportType::InboundRTP(rtp) => stats.bytes_received += rtp.bytes_received,
+            StatsReportType::RemoteInboundRTP(rtp) => stats.packets_lost += rtp.packets_lost,
+            _ => {}
+        }
+    }
+    stats
+}
 
 /// Events emitted by the peer connection.
 pub enum PeerEvent {
     IceCandidate(IceCandidateOut),
+    /// All local candidates have been gathered (end-of-candidates).
+    IceGatheringComplete,
     RemoteTrack(Arc<TrackRemote>),
     ConnectionState(String),
 }
@@ -29,25 +152,28 @@ pub struct Peer {
 }
 
 impl Peer {
-    /// Create a new peer connection. Returns the Peer and a receiver for events.
-    pub async fn new() -> Result<(Self, mpsc::UnboundedReceiver<PeerEvent>), Box<dyn std::error::Error + Send + Sync>> {
-        // Media engine with Opus codec matching Go SFU exactly
+    /// Create a new peer connection sending `local_track`. `video_codecs` are
+    /// registered too, so video tracks can be added later with `add_track`.
+    /// Returns the Peer and a receiver for events.
+    pub async fn new(
+        ice_config: &[IceServerConfig],
+        local_track: Arc<TrackLocalStaticRTP>,
+        video_codecs: &[RTCRtpCodecParameters],
+    ) -> Result<(Self, mpsc::UnboundedReceiver<PeerEvent>), Box<dyn std::error::Error + Send + Sync>> {
+        // Media engine with Opus codec matching Go SFU exactly. Uses the
+        // track's capability so the answer advertises its mono/stereo fmtp.
         let mut media_engine = MediaEngine::default();
         media_engine.register_codec(
             RTCRtpCodecParameters {
-                capability: RTCRtpCodecCapability {
-                    mime_type: "audio/opus".to_string(),
-                    clock_rate: 48000,
-                    channels: 2,
-                    sdp_fmtp_line: "minptime=10;useinbandfec=1;usedtx=1;maxaveragebitrate=128000"
-                        .to_string(),
-                    ..Default::default()
-                },
+                capability: local_track.codec(),
                 payload_type: 111,
                 ..Default::default()
             },
             RTPCodecType::Audio,
         )?;
+        for codec in video_codecs {
+            media_engine.register_codec(codec.clone(), RTPCodecType::Video)?;
+        }
 
         // Interceptors: NACK for packet loss recovery
         let mut registry = Registry::new();
@@ -59,29 +185,12 @@ impl Peer {
             .build();
 
         let config = RTCConfiguration {
-            ice_servers: vec![RTCIceServer {
-                urls: vec!["stun:stun.l.google.com:19302".to_string()],
-                ..Default::default()
-            }],
+            ice_servers: ice_servers(ice_config),
             ..Default::default()
         };
 
         let pc = Arc::new(api.new_peer_connection(config).await?);
 
-        // Create local audio track for sending mic audio
-        let local_track = Arc::new(TrackLocalStaticRTP::new(
-            RTCRtpCodecCapability {
-                mime_type: "audio/opus".to_string(),
-                clock_rate: 48000,
-                channels: 2,
-                sdp_fmtp_line: "minptime=10;useinbandfec=1;usedtx=1;maxaveragebitrate=128000"
-                    .to_string(),
-                ..Default::default()
-            },
-            "audio".to_string(),
-            "voice".to_string(),
-        ));
-
         // Add local track to PC
         let rtp_sender = pc
             .add_track(Arc::clone(&local_track) as Arc<dyn TrackLocal + Send + Sync>)
@@ -108,6 +217,8 @@ impl Peer {
                         sdp_mid: json.sdp_mid,
                         sdp_mline_index: json.sdp_mline_index,
                     }));
+                } else {
+                    let _ = tx.send(PeerEvent::IceGatheringComplete);
                 }
             })
         }));
@@ -140,6 +251,7 @@ impl Peer {
     pub async fn handle_offer(&self, sdp: &str) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
         let offer = RTCSessionDescription::offer(sdp.to_string())?;
         self.pc.set_remote_description(offer).await?;
+        require_codec(sdp, "audio", &["opus"])?;
 
         let answer = self.pc.create_answer(None).await?;
         self.pc.set_local_description(answer).await?;
@@ -153,6 +265,55 @@ impl Peer {
         Ok(local_desc.sdp)
     }
 
+    /// Send another track on this connection. Takes effect once renegotiated
+    /// (`create_offer` + `handle_answer`); the caller reads RTCP from the
+    /// returned sender.
+    pub async fn add_track(
+        &self,
+        track: Arc<dyn TrackLocal + Send + Sync>,
+    ) -> Result<Arc<RTCRtpSender>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(self.pc.add_track(track).await?)
+    }
+
+    /// Generate an offer covering the current tracks and set it as the local
+    /// description. The SFU's answer goes to `handle_answer`.
+    pub async fn create_offer(&self) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        let offer = self.pc.create_offer(None).await?;
+        self.pc.set_local_description(offer).await?;
+
+        let local_desc = self
+            .pc
+            .local_description()
+            .await
+            .ok_or("no local description")?;
+
+        Ok(local_desc.sdp)
+    }
+
+    /// Local description once ICE gathering has finished, with every
+    /// candidate in it, for a remote end that can't take trickled ones.
+    #[cfg(debug_assertions)]
+    pub async fn gathered_description(&self) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        let mut gathered = self.pc.gathering_complete_promise().await;
+        let _ = gathered.recv().await;
+
+        let local_desc = self
+            .pc
+            .local_description()
+            .await
+            .ok_or("no local description")?;
+
+        Ok(local_desc.sdp)
+    }
+
+    /// Apply the SFU's answer to our last offer.
+    pub async fn handle_answer(&self, sdp: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let answer = RTCSessionDescription::answer(sdp.to_string())?;
+        self.pc.set_remote_description(answer).await?;
+        require_codec(sdp, "audio", &["opus"])?;
+        Ok(())
+    }
+
     /// Add a remote ICE candidate.
     pub async fn handle_ice(&self, candidate: IceCandidateIn) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         let init = RTCIceCandidateInit {
@@ -165,6 +326,21 @@ impl Peer {
         Ok(())
     }
 
+    /// Shared handle to the connection, for polling stats off the engine lock.
+    pub fn connection(&self) -> Arc<RTCPeerConnection> {
+        Arc::clone(&self.pc)
+    }
+
+    /// RTT, loss and byte counts for this connection.
+    pub async fn stats(&self) -> PeerStats {
+        peer_stats(&self.pc).await
+    }
+
+    /// ICE gathering state ("new", "gathering", "complete").
+    pub fn ice_gathering_state(&self) -> String {
+        self.pc.ice_gathering_state().to_string()
+    }
+
     /// Close the peer connection.
     pub async fn close(self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         self.pc.close().await?;
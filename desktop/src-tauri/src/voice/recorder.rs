@@ -0,0 +1,85 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use ringbuf::{traits::Consumer, HeapCons};
+
+/// How often the writer thread drains the tap buffer.
+const WRITE_INTERVAL: Duration = Duration::from_millis(50);
+
+/// A WAV file being written from the playback mix.
+///
+/// The output callback pushes into a ring buffer and never touches the file;
+/// a dedicated thread drains it to disk.
+pub struct Recording {
+    pub device_rate: u32,
+    pub device_channels: usize,
+    stop: Arc<AtomicBool>,
+    handle: Option<std::thread::JoinHandle<()>>,
+}
+
+impl Recording {
+    /// Create `path` and start writing whatever `consumer` receives as
+    /// 32-bit float WAV at the given format.
+    pub fn start(
+        path: &str,
+        device_rate: u32,
+        device_channels: usize,
+        mut consumer: HeapCons<f32>,
+    ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let spec = hound::WavSpec {
+            channels: device_channels as u16,
+            sample_rate: device_rate,
+            bits_per_sample: 32,
+            sample_format: hound::SampleFormat::Float,
+        };
+        let mut writer = hound::WavWriter::create(path, spec)?;
+        log::info!("Recording to {} ({}Hz, {}ch)", path, device_rate, device_channels);
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_flag = Arc::clone(&stop);
+        let path = path.to_string();
+        let handle = std::thread::spawn(move || {
+            let mut buf = vec![0.0f32; device_rate as usize * device_channels / 10];
+            loop {
+                let stopping = stop_flag.load(Ordering::Relaxed);
+                // Drain everything queued, including the tail after a stop
+                loop {
+                    let n = consumer.pop_slice(&mut buf);
+                    if n == 0 {
+                        break;
+                    }
+                    for &sample in &buf[..n] {
+                        if let Err(e) = writer.write_sample(sample) {
+                            log::error!("Recording write failed: {}", e);
+                            return;
+                        }
+                    }
+                }
+                if stopping {
+                    break;
+                }
+                std::thread::sleep(WRITE_INTERVAL);
+            }
+            match writer.finalize() {
+                Ok(()) => log::info!("Recording saved to {}", path),
+                Err(e) => log::error!("Recording finalize failed: {}", e),
+            }
+        });
+
+        Ok(Self {
+            device_rate,
+            device_channels,
+            stop,
+            handle: Some(handle),
+        })
+    }
+
+    /// Flush what's buffered, finish the WAV header and close the file.
+    pub fn stop(mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
@@ -0,0 +1,81 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+
+use super::types::RemoteTrackInfo;
+
+/// Live state of one remote track — written by its decode task, read and
+/// adjusted by commands.
+pub struct RemoteTrackState {
+    codec: String,
+    level: AtomicU32, // f32 bits, smoothed RMS
+    speaking: AtomicBool,
+    /// Per-speaker mute and volume, applied in the decode path. Nothing sets
+    /// them yet; they're reported so the roster already has the fields.
+    muted: AtomicBool,
+    volume: Mutex<f32>,
+    /// Stereo position, -1 (left) .. 1 (right).
+    pan: Mutex<f32>,
+}
+
+/// Auto-assigned positions for joining speakers: center first, then
+/// alternating outwards.
+const DEFAULT_PANS: [f32; 7] = [0.0, -0.5, 0.5, -0.25, 0.25, -0.75, 0.75];
+
+impl RemoteTrackState {
+    /// `index` is how many tracks were already present; it picks the default pan.
+    pub fn new(codec: String, index: usize) -> Self {
+        Self {
+            codec,
+            level: AtomicU32::new(0),
+            speaking: AtomicBool::new(false),
+            muted: AtomicBool::new(false),
+            volume: Mutex::new(1.0),
+            pan: Mutex::new(DEFAULT_PANS[index % DEFAULT_PANS.len()]),
+        }
+    }
+
+    pub fn set_level(&self, level: f32) {
+        self.level.store(level.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn set_speaking(&self, speaking: bool) {
+        self.speaking.store(speaking, Ordering::Relaxed);
+    }
+
+    pub fn is_speaking(&self) -> bool {
+        self.speaking.load(Ordering::Relaxed)
+    }
+
+    pub fn is_muted(&self) -> bool {
+        self.muted.load(Ordering::Relaxed)
+    }
+
+    pub fn volume(&self) -> f32 {
+        *self.volume.lock().unwrap()
+    }
+
+    pub fn pan(&self) -> f32 {
+        *self.pan.lock().unwrap()
+    }
+
+    pub fn set_pan(&self, pan: f32) {
+        *self.pan.lock().unwrap() = pan.clamp(-1.0, 1.0);
+    }
+
+    pub fn info(&self, id: &str) -> RemoteTrackInfo {
+        RemoteTrackInfo {
+            id: id.to_string(),
+            codec: self.codec.clone(),
+            level: f32::from_bits(self.level.load(Ordering::Relaxed)),
+            speaking: self.speaking.load(Ordering::Relaxed),
+            muted: self.is_muted(),
+            volume: self.volume(),
+            pan: self.pan(),
+        }
+    }
+}
+
+/// Remote tracks by id: the track's SSRC as a string. Entries live as long
+/// as their decode task.
+pub type RemoteTracks = Arc<Mutex<HashMap<String, Arc<RemoteTrackState>>>>;
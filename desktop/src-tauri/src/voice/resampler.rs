@@ -1,56 +1,76 @@
 use rubato::{FftFixedIn, Resampler};
 
 /// Wraps rubato to convert between device sample rate and 48kHz (Opus native).
+///
+/// Input of any length is accepted: whole blocks are resampled and any
+/// remainder is carried over to the next call, so nothing is padded or
+/// dropped and output tracks the input rate exactly over time.
 pub struct AudioResampler {
     resampler: FftFixedIn<f32>,
-    input_frames: usize,
+    from_rate: u32,
+    to_rate: u32,
     channels: usize,
+    /// De-interleaved input not yet resampled (less than one block after `process`).
+    pending: Vec<Vec<f32>>,
 }
 
 impl AudioResampler {
     /// Create a resampler that converts `from_rate` → `to_rate`.
-    /// `chunk_size` is the number of frames per input chunk.
+    /// `chunk_size` is the number of frames resampled per block.
     pub fn new(from_rate: u32, to_rate: u32, chunk_size: usize, channels: usize) -> Self {
-        let resampler = FftFixedIn::new(
+        Self {
+            resampler: Self::fft(from_rate, to_rate, chunk_size, channels),
+            from_rate,
+            to_rate,
+            channels,
+            pending: (0..channels).map(|_| Vec::with_capacity(chunk_size * 2)).collect(),
+        }
+    }
+
+    fn fft(from_rate: u32, to_rate: u32, chunk_size: usize, channels: usize) -> FftFixedIn<f32> {
+        FftFixedIn::new(
             from_rate as usize,
             to_rate as usize,
             chunk_size,
             1, // sub_chunks
             channels,
         )
-        .expect("failed to create resampler");
+        .expect("failed to create resampler")
+    }
 
-        Self {
-            resampler,
-            input_frames: chunk_size,
-            channels,
-        }
+    /// Switch to blocks of `chunk_size` frames. Buffered input carries over;
+    /// only the filter's history is reset.
+    pub fn set_chunk_size(&mut self, chunk_size: usize) {
+        self.resampler = Self::fft(self.from_rate, self.to_rate, chunk_size, self.channels);
     }
 
-    /// Resample a chunk of interleaved f32 samples.
-    /// Returns resampled interleaved samples.
+    /// Resample interleaved f32 samples.
+    /// Returns resampled interleaved samples — empty until a full block is buffered.
     pub fn process(&mut self, interleaved: &[f32]) -> Vec<f32> {
-        let frames = interleaved.len() / self.channels;
-
-        // De-interleave into per-channel vecs
-        let mut channels: Vec<Vec<f32>> = (0..self.channels).map(|_| Vec::with_capacity(frames)).collect();
+        // De-interleave onto the carried-over input
         for (i, sample) in interleaved.iter().enumerate() {
-            channels[i % self.channels].push(*sample);
+            self.pending[i % self.channels].push(*sample);
         }
 
-        // Pad to expected input size if needed
-        for ch in &mut channels {
-            ch.resize(self.input_frames, 0.0);
-        }
+        let mut result = Vec::new();
+        loop {
+            let needed = self.resampler.input_frames_next();
+            if self.pending[0].len() < needed {
+                break;
+            }
 
-        let output = self.resampler.process(&channels, None).expect("resample failed");
+            let output = self.resampler.process(&self.pending, None).expect("resample failed");
+            for ch in &mut self.pending {
+                ch.drain(..needed);
+            }
 
-        // Re-interleave
-        let out_frames = output[0].len();
-        let mut result = Vec::with_capacity(out_frames * self.channels);
-        for i in 0..out_frames {
-            for ch in &output {
-                result.push(ch[i]);
+            // Re-interleave
+            let out_frames = output[0].len();
+            result.reserve(out_frames * self.channels);
+            for i in 0..out_frames {
+                for ch in &output {
+                    result.push(ch[i]);
+                }
             }
         }
         result
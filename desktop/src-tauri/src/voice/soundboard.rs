@@ -0,0 +1,74 @@
+use super::audio_capture::to_stereo;
+use super::resampler::AudioResampler;
+
+const OPUS_SAMPLE_RATE: u32 = 48000;
+const OPUS_CHANNELS: usize = 2;
+/// Resampler block size (frames) when converting a clip.
+const RESAMPLE_CHUNK: usize = 1024;
+
+/// A sound clip being mixed into the outgoing voice stream.
+pub struct Clip {
+    /// 48kHz stereo, interleaved.
+    samples: Vec<f32>,
+    pos: usize,
+}
+
+impl Clip {
+    /// Decode a WAV file to 48kHz stereo.
+    pub fn load(path: &str) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let mut reader = hound::WavReader::open(path)?;
+        let spec = reader.spec();
+        let channels = spec.channels as usize;
+        if channels == 0 {
+            return Err("WAV file has no channels".into());
+        }
+
+        let decoded: Vec<f32> = match spec.sample_format {
+            hound::SampleFormat::Float => reader.samples::<f32>().collect::<Result<_, _>>()?,
+            hound::SampleFormat::Int => {
+                let scale = 1.0 / (1i64 << (spec.bits_per_sample - 1)) as f32;
+                reader
+                    .samples::<i32>()
+                    .map(|s| s.map(|s| s as f32 * scale))
+                    .collect::<Result<_, _>>()?
+            }
+        };
+
+        let stereo = to_stereo(&decoded, channels);
+        let samples = if spec.sample_rate == OPUS_SAMPLE_RATE {
+            stereo
+        } else {
+            let mut resampler = AudioResampler::new(
+                spec.sample_rate,
+                OPUS_SAMPLE_RATE,
+                RESAMPLE_CHUNK,
+                OPUS_CHANNELS,
+            );
+            // Pad so the last partial block gets flushed through
+            let mut padded = stereo;
+            padded.resize(padded.len() + RESAMPLE_CHUNK * OPUS_CHANNELS, 0.0);
+            resampler.process(&padded)
+        };
+
+        log::info!(
+            "Loaded sound clip {} ({:.1}s)",
+            path,
+            samples.len() as f64 / (OPUS_SAMPLE_RATE as usize * OPUS_CHANNELS) as f64
+        );
+        Ok(Self { samples, pos: 0 })
+    }
+}
+
+/// Add the next stretch of every playing clip to `out` (48kHz stereo) at
+/// `gain`, dropping clips that have finished.
+pub fn mix_clips(clips: &mut Vec<Clip>, out: &mut [f32], gain: f32) {
+    for clip in clips.iter_mut() {
+        let remaining = &clip.samples[clip.pos..];
+        let n = remaining.len().min(out.len());
+        for (o, &s) in out.iter_mut().zip(&remaining[..n]) {
+            *o += s * gain;
+        }
+        clip.pos += n;
+    }
+    clips.retain(|clip| clip.pos < clip.samples.len());
+}
@@ -6,7 +6,34 @@ const EMA_ATTACK: f32 = 0.4;
 const EMA_RELEASE: f32 = 0.05;
 const HOLD_MS: f64 = 250.0;
 
+/// Detector tuning, defaulting to the constants above. The transmit detector is
+/// tuned via `voice_set_speaking_params`; remote (cosmetic) detectors separately
+/// via `voice_set_remote_speaking_params`.
+#[derive(Debug, Clone, Copy)]
+pub struct SpeakingParams {
+    /// Smoothed RMS above which a frame counts as speech.
+    pub threshold: f32,
+    /// EMA weight when the level rises (higher = snappier onset).
+    pub attack: f32,
+    /// EMA weight when the level falls (lower = slower fade).
+    pub release: f32,
+    /// How long speaking stays on after the level drops below threshold.
+    pub hold_ms: f64,
+}
+
+impl Default for SpeakingParams {
+    fn default() -> Self {
+        Self {
+            threshold: SPEAK_THRESHOLD,
+            attack: EMA_ATTACK,
+            release: EMA_RELEASE,
+            hold_ms: HOLD_MS,
+        }
+    }
+}
+
 pub struct SpeakingDetector {
+    params: SpeakingParams,
     smoothed_rms: f32,
     was_speaking: bool,
     hold_until: f64, // milliseconds (monotonic)
@@ -14,8 +41,9 @@ pub struct SpeakingDetector {
 }
 
 impl SpeakingDetector {
-    pub fn new() -> Self {
+    pub fn with_params(params: SpeakingParams) -> Self {
         Self {
+            params,
             smoothed_rms: 0.0,
             was_speaking: false,
             hold_until: 0.0,
@@ -39,14 +67,14 @@ impl SpeakingDetector {
 
         // EMA smoothing
         let alpha = if rms > self.smoothed_rms {
-            EMA_ATTACK
+            self.params.attack
         } else {
-            EMA_RELEASE
+            self.params.release
         };
         self.smoothed_rms = alpha * rms + (1.0 - alpha) * self.smoothed_rms;
 
-        let is_speaking = if self.smoothed_rms > SPEAK_THRESHOLD {
-            self.hold_until = self.clock_ms + HOLD_MS;
+        let is_speaking = if self.smoothed_rms > self.params.threshold {
+            self.hold_until = self.clock_ms + self.params.hold_ms;
             true
         } else if self.clock_ms < self.hold_until {
             true
@@ -62,6 +90,16 @@ impl SpeakingDetector {
         }
     }
 
+    /// Takes effect from the next frame; current level and hold carry over.
+    pub fn set_params(&mut self, params: SpeakingParams) {
+        self.params = params;
+    }
+
+    /// Smoothed RMS of the frames seen so far.
+    pub fn level(&self) -> f32 {
+        self.smoothed_rms
+    }
+
     #[allow(dead_code)]
     pub fn reset(&mut self) {
         self.smoothed_rms = 0.0;
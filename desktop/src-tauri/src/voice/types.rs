@@ -5,6 +5,12 @@ pub struct SdpAnswer {
     pub sdp: String,
 }
 
+/// Offer we generated, for the SFU to answer.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SdpOffer {
+    pub sdp: String,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct IceCandidateOut {
     pub candidate: String,
@@ -23,24 +29,140 @@ pub struct IceCandidateIn {
     pub sdp_mline_index: Option<u16>,
 }
 
+/// A STUN/TURN server pushed from the frontend.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct IceServerConfig {
+    pub urls: Vec<String>,
+    pub username: Option<String>,
+    pub credential: Option<String>,
+}
+
 #[derive(Debug, Serialize, Clone)]
 pub struct SpeakingEvent {
     pub speaking: bool,
 }
 
+/// `voice:peer_speaking` — a remote track started or stopped speaking.
+#[derive(Debug, Serialize, Clone)]
+pub struct PeerSpeakingEvent {
+    pub track_id: String,
+    pub ssrc: u32,
+    pub speaking: bool,
+}
+
+/// Voice half of `get_engine_state`. Devices are None when using the system default.
+#[derive(Debug, Serialize, Clone)]
+pub struct VoiceStatus {
+    /// A peer connection exists (joined or rejoining a channel).
+    pub connected: bool,
+    pub capturing: bool,
+    pub playing: bool,
+    pub muted: bool,
+    pub deafened: bool,
+    pub master_volume: f32,
+    pub mic_gain: f32,
+    pub input_device: Option<String>,
+    pub output_device: Option<String>,
+}
+
+/// Snapshot of one remote track for the participant roster.
+#[derive(Debug, Serialize, Clone)]
+pub struct RemoteTrackInfo {
+    /// The track's SSRC as a string (track ids aren't unique per peer), as in
+    /// `voice:peer_speaking`'s `track_id`.
+    pub id: String,
+    pub codec: String,
+    /// Smoothed RMS level, 0..1.
+    pub level: f32,
+    pub speaking: bool,
+    pub muted: bool,
+    pub volume: f32,
+    /// -1 (left) .. 1 (right).
+    pub pan: f32,
+}
+
 #[derive(Debug, Serialize, Clone)]
 pub struct ConnectionStateEvent {
     pub state: String,
 }
 
 #[derive(Debug, Serialize, Clone)]
+pub struct ReconnectEvent {
+    pub attempt: u32,
+    pub max_attempts: u32,
+}
+
+/// `voice:device_lost` — the selected input device failed and capture is
+/// switching to the system default.
+#[derive(Debug, Serialize, Clone)]
+pub struct DeviceLostEvent {
+    /// Selected device id; None if the default device itself failed.
+    pub device: Option<String>,
+    pub reason: String,
+}
+
+/// `voice:device_changed` — capture is now running on this device.
+#[derive(Debug, Serialize, Clone)]
+pub struct DeviceChangedEvent {
+    pub device: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
 pub struct AudioDeviceList {
     pub inputs: Vec<AudioDeviceInfo>,
     pub outputs: Vec<AudioDeviceInfo>,
 }
 
+/// cpal hosts (audio backends) available, and the one in use.
 #[derive(Debug, Serialize, Clone)]
+pub struct AudioHostList {
+    pub hosts: Vec<String>,
+    pub active: String,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
 pub struct AudioDeviceInfo {
+    /// Pass to `voice_set_input_device`/`voice_set_output_device`. The device
+    /// name, which stays the same across enumerations and plugging other
+    /// devices in or out. Only repeated names get a `#n` suffix, and that one
+    /// can change: see `identify`.
+    pub id: String,
     pub name: String,
     pub is_default: bool,
 }
+
+/// Connection stats for `voice_get_stats` / `screen_get_stats` and the
+/// once-a-second `voice:stats` event.
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct PeerStats {
+    /// Round-trip time on the selected ICE candidate pair. None before the
+    /// first STUN response.
+    pub rtt_ms: Option<f64>,
+    /// Interarrival jitter. webrtc-rs doesn't report it yet, so this stays
+    /// None until it does.
+    pub jitter_ms: Option<f64>,
+    /// Outgoing packets the SFU reported lost in its receiver reports.
+    pub packets_lost: i64,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+}
+
+/// Playback buffer health, emitted as `voice:audio_health` every few seconds
+/// during a call. Counts cover the time since the previous event.
+#[derive(Debug, Serialize, Clone)]
+pub struct AudioHealthEvent {
+    /// Times playout ran dry soon after resuming, so the prebuffer was raised.
+    /// The normal drain at the end of a talk spurt isn't counted.
+    pub underruns: u32,
+    /// Output callbacks that found the buffer full, i.e. decoded audio was dropped.
+    pub overruns: u32,
+    /// Audio discarded to pull the queue back down.
+    pub resynced_ms: f64,
+    pub queued_ms: f64,
+    /// Smoothed queue growth relative to the playback clock, in parts per
+    /// million: positive when audio arrives faster than it plays. Jitter makes
+    /// it noisy; only sustained values mean clock drift.
+    pub drift_ppm: f64,
+    /// Extra prebuffer added after underruns.
+    pub extra_prebuffer_ms: f64,
+}
@@ -67,7 +67,7 @@ func New(stunServer string, publicIP string) *SFU {
 		webrtc.WithSettingEngine(se),
 	)
 
-	// Screen share media engine: H.264 + VP8 video + Opus audio
+	// Screen share media engine: H.264 + VP8 + VP9 video + Opus audio
 	screenME := &webrtc.MediaEngine{}
 	// H.264 (preferred — better quality per bit for screen content)
 	if err := screenME.RegisterCodec(webrtc.RTPCodecParameters{
@@ -90,6 +90,17 @@ func New(stunServer string, publicIP string) *SFU {
 	}, webrtc.RTPCodecTypeVideo); err != nil {
 		log.Printf("sfu: register VP8 codec: %v", err)
 	}
+	// VP9 profile 0, for desktop clients that prefer it
+	if err := screenME.RegisterCodec(webrtc.RTPCodecParameters{
+		RTPCodecCapability: webrtc.RTPCodecCapability{
+			MimeType:    webrtc.MimeTypeVP9,
+			ClockRate:   90000,
+			SDPFmtpLine: "profile-id=0",
+		},
+		PayloadType: 98,
+	}, webrtc.RTPCodecTypeVideo); err != nil {
+		log.Printf("sfu: register VP9 codec: %v", err)
+	}
 	if err := screenME.RegisterCodec(webrtc.RTPCodecParameters{
 		RTPCodecCapability: webrtc.RTPCodecCapability{
 			MimeType:    webrtc.MimeTypeOpus,